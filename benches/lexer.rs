@@ -0,0 +1,32 @@
+//! Measures how fast the lexer tokenizes a large SQL input.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use nikke::tokenize;
+
+/// Builds a script of `count` statements mixing keywords, identifiers, literals, comments and
+/// multi-character operators.
+fn sql_script(count: usize) -> String {
+    let mut sql = String::new();
+    for i in 0..count {
+        sql.push_str(&format!(
+            "-- statement {i}\n\
+             SELECT id, \"user name\", price * 1.5 AS total, x'0aff' FROM orders_{i} \
+             WHERE status <> 'it''s shipped' AND qty >= {i} OR note LIKE '%日本%' \
+             ORDER BY id DESC LIMIT 10\n"
+        ));
+    }
+    sql
+}
+
+fn bench_tokenize(c: &mut Criterion) {
+    let sql = sql_script(2_000);
+    let mut group = c.benchmark_group("lexer");
+    group.throughput(Throughput::Bytes(sql.len() as u64));
+    group.bench_function("tokenize", |b| {
+        b.iter(|| tokenize(black_box(&sql)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_tokenize);
+criterion_main!(benches);
@@ -0,0 +1,687 @@
+//! Semantic checks that need more than the syntax of a query, such as resolving column names
+//! against the catalog.
+
+use crate::ast::{Expression, Insert, QuantifiedOperand, Select, Table};
+use crate::catalog::{Catalog, TableSchema};
+use crate::executor::output_columns;
+use crate::functions::FunctionRegistry;
+use crate::plan::sort_expression;
+
+pub(crate) const DEFAULT_OUTSIDE_TARGET: &str =
+    "DEFAULT is only allowed as an INSERT or UPDATE value";
+
+/// A column referenced inside a subquery that belongs to an enclosing query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OuterReference {
+    /// The identifier as written, e.g. `a.y` or `y`.
+    pub identifier: String,
+    /// The enclosing table the identifier resolved to.
+    pub table: String,
+    /// How many query levels out the table was found (1 = the directly enclosing query).
+    pub depth: usize,
+}
+
+/// Resolves identifiers in queries against a catalog and checks function calls.
+pub struct Analyzer<'a> {
+    catalog: &'a Catalog,
+    functions: &'a FunctionRegistry,
+}
+
+/// A table visible to column references.
+struct ScopeTable {
+    /// What qualified columns use to refer to the table: its alias if it has one, otherwise its
+    /// name.
+    qualifier: String,
+    /// The table's name, or the alias of a subquery in FROM.
+    name: String,
+    columns: Vec<String>,
+}
+
+impl ScopeTable {
+    fn has_column(&self, column: &str) -> bool {
+        self.columns.iter().any(|c| c.eq_ignore_ascii_case(column))
+    }
+}
+
+/// The tables visible at one query level.
+type Scope = Vec<ScopeTable>;
+
+impl<'a> Analyzer<'a> {
+    /// Creates an analyzer that resolves tables and columns using `catalog`.
+    pub fn new(catalog: &'a Catalog) -> Self {
+        Self::with_functions(catalog, FunctionRegistry::builtin())
+    }
+
+    /// Like `new`, but checks function calls against `functions` instead of the built-ins.
+    pub fn with_functions(catalog: &'a Catalog, functions: &'a FunctionRegistry) -> Self {
+        Analyzer { catalog, functions }
+    }
+
+    /// Checks that every function called in `select` and its subqueries is registered and is
+    /// given an accepted number of arguments, and that GROUPING is only given expressions
+    /// the query groups by.
+    pub fn check_functions(&self, select: &Select) -> Result<(), String> {
+        for subquery in table_references(select).filter_map(|table| table.subquery.as_ref()) {
+            self.check_functions(subquery)?;
+        }
+        select_expressions(select)
+            .into_iter()
+            .try_for_each(|expr| self.check_expression_functions(expr, select))
+    }
+
+    fn check_expression_functions(&self, expr: &Expression, select: &Select) -> Result<(), String> {
+        match expr {
+            Expression::Function {
+                name,
+                args,
+                named_args,
+                ..
+            } => {
+                self.functions
+                    .check_call(name, args.len() + named_args.len())?;
+                if name.eq_ignore_ascii_case("GROUPING") {
+                    check_grouping_args(args, select)?;
+                }
+            }
+            Expression::Exists(subquery) => return self.check_functions(subquery),
+            Expression::Quantified {
+                right: QuantifiedOperand::Subquery(subquery),
+                ..
+            } => self.check_functions(subquery)?,
+            _ => {}
+        }
+        expr.children()
+            .into_iter()
+            .try_for_each(|child| self.check_expression_functions(child, select))
+    }
+
+    /// Checks that an `INSERT ... SELECT` produces as many columns as it targets, expanding `*`
+    /// and `* EXCEPT` against the source table.
+    pub fn check_insert(&self, insert: &Insert) -> Result<(), String> {
+        let Some(select) = &insert.select else {
+            return Ok(());
+        };
+        let schema = self.lookup_table(&select.table.name)?;
+        let produced = output_columns(&select.columns, schema)?.len();
+        if produced != insert.columns.len() {
+            return Err(format!(
+                "INSERT has {} target columns, but SELECT produces {} columns",
+                insert.columns.len(),
+                produced
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the output column names that `select` produces more than once, ignoring case.
+    /// Each is listed once, spelled as it first appears.
+    ///
+    /// Duplicates are legal, so this is an opt-in diagnostic for callers that access results by
+    /// column name and would otherwise silently get the first of several same-named columns.
+    pub fn duplicate_output_names(&self, select: &Select) -> Result<Vec<String>, String> {
+        let schema = self.lookup_table(&select.table.name)?;
+        let outputs = output_columns(&select.columns, schema)?;
+
+        let mut duplicates: Vec<String> = Vec::new();
+        for (i, (name, _)) in outputs.iter().enumerate() {
+            let first = outputs[..i]
+                .iter()
+                .find(|(earlier, _)| earlier.eq_ignore_ascii_case(name));
+            if let Some((first, _)) = first {
+                if !duplicates.iter().any(|d| d.eq_ignore_ascii_case(name)) {
+                    duplicates.push(first.clone());
+                }
+            }
+        }
+        Ok(duplicates)
+    }
+
+    /// Resolves every identifier in `select` and its subqueries, returning the identifiers inside
+    /// subqueries that refer to an enclosing query's tables (correlated references).
+    ///
+    /// An identifier that matches no table in any visible scope is an error.
+    pub fn correlated_references(&self, select: &Select) -> Result<Vec<OuterReference>, String> {
+        let mut scopes = Vec::new();
+        let mut references = Vec::new();
+        self.resolve_select(select, &mut scopes, &mut references)?;
+        Ok(references)
+    }
+
+    fn resolve_select(
+        &self,
+        select: &Select,
+        scopes: &mut Vec<Scope>,
+        references: &mut Vec<OuterReference>,
+    ) -> Result<(), String> {
+        let mut scope = Vec::new();
+        for table in table_references(select) {
+            if let Some(subquery) = &table.subquery {
+                // A LATERAL subquery sees the tables before it as an enclosing query level;
+                // any other subquery in FROM only sees the queries around this one
+                if table.lateral {
+                    scopes.push(std::mem::take(&mut scope));
+                }
+                let result = self.resolve_select(subquery, scopes, references);
+                if table.lateral {
+                    scope = scopes.pop().unwrap_or_default();
+                }
+                result?;
+            }
+            scope.push(self.scope_entry(table)?);
+        }
+        scopes.push(scope);
+
+        let result = select_expressions(select)
+            .into_iter()
+            .try_for_each(|expr| self.resolve_expression(expr, scopes, references));
+        scopes.pop();
+        result
+    }
+
+    fn resolve_expression(
+        &self,
+        expr: &Expression,
+        scopes: &mut Vec<Scope>,
+        references: &mut Vec<OuterReference>,
+    ) -> Result<(), String> {
+        match expr {
+            Expression::Identifier(name) => {
+                // The parser represents a NULL literal as an identifier
+                if name == "NULL" {
+                    return Ok(());
+                }
+                let (table, depth) = resolve_identifier(name, scopes)?;
+                if depth > 0 {
+                    references.push(OuterReference {
+                        identifier: name.clone(),
+                        table,
+                        depth,
+                    });
+                }
+                Ok(())
+            }
+            Expression::Or(left, right) | Expression::And(left, right) => {
+                self.resolve_expression(left, scopes, references)?;
+                self.resolve_expression(right, scopes, references)
+            }
+            Expression::Binary { left, right, .. } => {
+                self.resolve_expression(left, scopes, references)?;
+                self.resolve_expression(right, scopes, references)
+            }
+            Expression::AtTimeZone { expr, zone } => {
+                self.resolve_expression(expr, scopes, references)?;
+                self.resolve_expression(zone, scopes, references)
+            }
+            Expression::Like { expr, pattern, .. } => {
+                self.resolve_expression(expr, scopes, references)?;
+                self.resolve_expression(pattern, scopes, references)
+            }
+            Expression::Subscript { base, index } => {
+                self.resolve_expression(base, scopes, references)?;
+                self.resolve_expression(index, scopes, references)
+            }
+            Expression::Not(inner)
+            | Expression::Negate(inner)
+            | Expression::Cast { expr: inner, .. } => {
+                self.resolve_expression(inner, scopes, references)
+            }
+            Expression::Function { .. }
+            | Expression::Array(_)
+            | Expression::Row(_)
+            | Expression::InList { .. }
+            | Expression::Between { .. }
+            | Expression::Wildcard { .. } => expr
+                .children()
+                .into_iter()
+                .try_for_each(|arg| self.resolve_expression(arg, scopes, references)),
+            Expression::Exists(subquery) => self.resolve_select(subquery, scopes, references),
+            Expression::Quantified { left, right, .. } => {
+                self.resolve_expression(left, scopes, references)?;
+                match right {
+                    QuantifiedOperand::Array(array) => {
+                        self.resolve_expression(array, scopes, references)
+                    }
+                    QuantifiedOperand::Subquery(subquery) => {
+                        self.resolve_select(subquery, scopes, references)
+                    }
+                }
+            }
+            Expression::Asterisk
+            | Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::Text(_)
+            | Expression::Boolean(_)
+            | Expression::Blob(_)
+            | Expression::Interval(_) => Ok(()),
+            Expression::Default => Err(DEFAULT_OUTSIDE_TARGET.to_string()),
+        }
+    }
+
+    /// Describes a table reference for name resolution. A subquery in FROM has the columns
+    /// its select list produces.
+    fn scope_entry(&self, table: &Table) -> Result<ScopeTable, String> {
+        let columns = self.table_columns(table)?;
+        Ok(ScopeTable {
+            qualifier: table.alias.as_ref().unwrap_or(&table.name).clone(),
+            name: table.name.clone(),
+            columns,
+        })
+    }
+
+    fn table_columns(&self, table: &Table) -> Result<Vec<String>, String> {
+        let Some(subquery) = &table.subquery else {
+            let schema = self.lookup_table(&table.name)?;
+            return Ok(schema.columns.iter().map(|c| c.name.clone()).collect());
+        };
+        let source = self.table_columns(&subquery.table)?;
+        let mut columns = Vec::new();
+        for column in &subquery.columns {
+            if let Some(alias) = &column.alias {
+                columns.push(alias.clone());
+                continue;
+            }
+            match &column.expression {
+                Expression::Asterisk => columns.extend(source.iter().cloned()),
+                Expression::Wildcard { except, .. } => columns.extend(
+                    source
+                        .iter()
+                        .filter(|c| !except.iter().any(|e| e.eq_ignore_ascii_case(c)))
+                        .cloned(),
+                ),
+                // A qualified column keeps only its column name, as in `SELECT t.id` giving `id`
+                Expression::Identifier(name) => {
+                    let column = name.rsplit_once('.').map_or(name.as_str(), |(_, c)| c);
+                    columns.push(column.to_string());
+                }
+                other => columns.push(other.to_sql()),
+            }
+        }
+        Ok(columns)
+    }
+
+    fn lookup_table(&self, name: &str) -> Result<&'a TableSchema, String> {
+        self.catalog
+            .get_table(name)
+            .ok_or_else(|| format!("Table '{}' does not exist", name))
+    }
+}
+
+/// Checks that each argument of a GROUPING call is one of the GROUP BY expressions of `select`,
+/// written the same way.
+fn check_grouping_args(args: &[Expression], select: &Select) -> Result<(), String> {
+    let Some(group_by) = &select.group_by else {
+        return Err("GROUPING is only allowed in a query with GROUP BY".to_string());
+    };
+    match args.iter().find(|arg| !group_by.contains(arg)) {
+        Some(arg) => Err(format!(
+            "GROUPING arguments must be GROUP BY expressions, got {}",
+            arg.to_sql()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Returns the table in FROM followed by the joined tables, in the order they are written.
+fn table_references(select: &Select) -> impl Iterator<Item = &Table> {
+    std::iter::once(&select.table).chain(select.joins.iter().map(|join| &join.table))
+}
+
+/// Returns the top-level expressions of every clause of `select`.
+fn select_expressions(select: &Select) -> Vec<&Expression> {
+    let mut expressions: Vec<&Expression> =
+        select.columns.iter().map(|item| &item.expression).collect();
+    expressions.extend(select.joins.iter().filter_map(|j| j.condition.as_ref()));
+    expressions.extend(select.where_clause.as_ref());
+    expressions.extend(select.group_by.iter().flatten());
+    expressions.extend(select.having.as_ref());
+    expressions.extend(
+        select
+            .order_by
+            .iter()
+            .flatten()
+            .map(|ordering| sort_expression(ordering, &select.columns)),
+    );
+    expressions
+}
+
+/// Finds the table an identifier belongs to, searching from the innermost scope outwards.
+/// Returns the table name and how many scopes out it was found.
+fn resolve_identifier(name: &str, scopes: &[Scope]) -> Result<(String, usize), String> {
+    let (qualifier, column) = match name.split_once('.') {
+        Some((qualifier, column)) => (Some(qualifier), column),
+        None => (None, name),
+    };
+
+    if let Some(qualifier) = qualifier {
+        let known = scopes
+            .iter()
+            .flatten()
+            .any(|table| table.qualifier.eq_ignore_ascii_case(qualifier));
+        if !known {
+            return Err(format!(
+                "Unknown table or alias '{}' in '{}'",
+                qualifier, name
+            ));
+        }
+    }
+
+    for (depth, scope) in scopes.iter().rev().enumerate() {
+        let candidates: Vec<&ScopeTable> = scope
+            .iter()
+            .filter(|table| qualifier.is_none_or(|q| table.qualifier.eq_ignore_ascii_case(q)))
+            .collect();
+
+        if qualifier.is_some() && !candidates.is_empty() {
+            // A qualifier binds to the innermost table with that name, even if the column is missing
+            let table = candidates[0];
+            return if table.has_column(column) {
+                Ok((table.name.clone(), depth))
+            } else {
+                Err(format!("Column '{}' does not exist", name))
+            };
+        }
+
+        let matches: Vec<&ScopeTable> = candidates
+            .into_iter()
+            .filter(|table| table.has_column(column))
+            .collect();
+        match matches.len() {
+            0 => continue,
+            1 => return Ok((matches[0].name.clone(), depth)),
+            _ => return Err(format!("Column '{}' is ambiguous", name)),
+        }
+    }
+
+    Err(format!("Unresolved column '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ColumnDef, DataType, Query};
+    use crate::functions::{Arity, FunctionSignature, ReturnType};
+    use crate::parser::Parser;
+
+    fn catalog() -> Catalog {
+        let column = |name: &str| ColumnDef {
+            name: name.to_string(),
+            data_type: DataType::Integer,
+            not_null: false,
+            default: None,
+        };
+        let mut catalog = Catalog::new();
+        catalog
+            .add_table(TableSchema::new("a", vec![column("id"), column("y")]))
+            .unwrap();
+        catalog
+            .add_table(TableSchema::new("b", vec![column("id"), column("x")]))
+            .unwrap();
+        catalog
+    }
+
+    fn correlated(sql: &str) -> Result<Vec<OuterReference>, String> {
+        let catalog = catalog();
+        let Query::Select(select) = Parser::new(sql)?.parse()? else {
+            panic!("Expected a SELECT");
+        };
+        Analyzer::new(&catalog).correlated_references(&select)
+    }
+
+    /// Tests that qualified and unqualified outer columns are reported as correlated.
+    #[test]
+    fn test_correlated_subquery() {
+        let references =
+            correlated("SELECT * FROM a WHERE EXISTS (SELECT 1 FROM b WHERE b.x = a.y)").unwrap();
+        assert_eq!(
+            references,
+            vec![OuterReference {
+                identifier: "a.y".to_string(),
+                table: "a".to_string(),
+                depth: 1,
+            }]
+        );
+
+        let references =
+            correlated("SELECT * FROM a WHERE EXISTS (SELECT 1 FROM b WHERE x = y)").unwrap();
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].identifier, "y");
+    }
+
+    /// Tests that a column of the subquery's own table shadows the outer one.
+    #[test]
+    fn test_local_column_is_not_correlated() {
+        let references =
+            correlated("SELECT * FROM a WHERE EXISTS (SELECT 1 FROM b WHERE id = 1)").unwrap();
+        assert!(references.is_empty());
+    }
+
+    /// Tests that DEFAULT outside an INSERT or UPDATE value is a semantic error.
+    #[test]
+    fn test_default_outside_target() {
+        assert_eq!(
+            correlated("SELECT id FROM a WHERE y = DEFAULT"),
+            Err("DEFAULT is only allowed as an INSERT or UPDATE value".to_string())
+        );
+    }
+
+    /// Tests that qualifiers resolve through table aliases, that an alias hides the table name,
+    /// and that unqualified columns still resolve to the aliased table.
+    #[test]
+    fn test_alias_qualified_columns() {
+        assert_eq!(
+            correlated("SELECT p.y, id FROM a AS p WHERE p.id = 1"),
+            Ok(vec![])
+        );
+        let references = correlated(
+            "SELECT * FROM a AS p WHERE EXISTS (SELECT 1 FROM b AS q JOIN a ON q.x = p.y)",
+        )
+        .unwrap();
+        assert_eq!(
+            references,
+            vec![OuterReference {
+                identifier: "p.y".to_string(),
+                table: "a".to_string(),
+                depth: 1,
+            }]
+        );
+
+        assert_eq!(
+            correlated("SELECT a.y FROM a AS p"),
+            Err("Unknown table or alias 'a' in 'a.y'".to_string())
+        );
+        assert_eq!(
+            correlated("SELECT z.y FROM a"),
+            Err("Unknown table or alias 'z' in 'z.y'".to_string())
+        );
+        assert_eq!(
+            correlated("SELECT p.x FROM a AS p"),
+            Err("Column 'p.x' does not exist".to_string())
+        );
+        // ORDER BY may name a select list alias, but not qualify it
+        assert_eq!(correlated("SELECT y AS k FROM a ORDER BY k"), Ok(vec![]));
+        assert_eq!(
+            correlated("SELECT y AS k FROM a ORDER BY a.k"),
+            Err("Column 'a.k' does not exist".to_string())
+        );
+    }
+
+    /// Tests that a LATERAL subquery in FROM may refer to the tables before it, that one
+    /// without LATERAL may not, and that a subquery's output columns resolve through its alias.
+    #[test]
+    fn test_lateral_subquery() {
+        let references =
+            correlated("SELECT s.x FROM a, LATERAL (SELECT x FROM b WHERE b.id = a.id) s").unwrap();
+        assert_eq!(
+            references,
+            vec![OuterReference {
+                identifier: "a.id".to_string(),
+                table: "a".to_string(),
+                depth: 1,
+            }]
+        );
+        assert_eq!(
+            correlated("SELECT s.x FROM a, (SELECT x FROM b WHERE b.id = a.id) s"),
+            Err("Unknown table or alias 'a' in 'a.id'".to_string())
+        );
+
+        assert_eq!(
+            correlated(
+                "SELECT s.id, s.x, y FROM a, (SELECT b.id, x FROM b) AS s WHERE s.id = a.id"
+            ),
+            Ok(vec![])
+        );
+        assert_eq!(
+            correlated("SELECT s.y FROM (SELECT * EXCEPT (y) FROM a) AS s"),
+            Err("Column 's.y' does not exist".to_string())
+        );
+    }
+
+    /// Tests that names that resolve nowhere are errors.
+    #[test]
+    fn test_unresolved_identifiers() {
+        assert_eq!(
+            correlated("SELECT * FROM a WHERE EXISTS (SELECT 1 FROM b WHERE b.z = a.y)"),
+            Err("Column 'b.z' does not exist".to_string())
+        );
+        assert_eq!(
+            correlated("SELECT * FROM a WHERE EXISTS (SELECT 1 FROM b WHERE missing = 1)"),
+            Err("Unresolved column 'missing'".to_string())
+        );
+        assert_eq!(
+            correlated("SELECT * FROM a JOIN b ON a.id = b.id WHERE id = 1"),
+            Err("Column 'id' is ambiguous".to_string())
+        );
+    }
+
+    fn check_functions(sql: &str, functions: &FunctionRegistry) -> Result<(), String> {
+        let catalog = catalog();
+        let Query::Select(select) = Parser::new(sql)?.parse()? else {
+            panic!("Expected a SELECT");
+        };
+        Analyzer::with_functions(&catalog, functions).check_functions(&select)
+    }
+
+    /// Tests that unknown functions and wrong arities are errors, including in subqueries.
+    #[test]
+    fn test_check_functions() {
+        let builtin = FunctionRegistry::builtin();
+        assert!(check_functions("SELECT COUNT(*), GREATEST(id, y) FROM a", builtin).is_ok());
+        assert_eq!(
+            check_functions("SELECT COUNT() FROM a", builtin),
+            Err("Function 'COUNT' expects 1 argument, got 0".to_string())
+        );
+        assert_eq!(
+            check_functions("SELECT GREATEST() FROM a", builtin),
+            Err("Function 'GREATEST' expects at least 1 argument, got 0".to_string())
+        );
+        assert!(check_functions("SELECT LEAST(id) FROM a", builtin).is_ok());
+        assert_eq!(
+            check_functions(
+                "SELECT id FROM a WHERE EXISTS (SELECT 1 FROM b WHERE cont(x) = 1)",
+                builtin
+            ),
+            Err("Unknown function 'cont'".to_string())
+        );
+
+        assert!(check_functions(
+            "SELECT id, y, GROUPING(id, y) FROM a GROUP BY id, y HAVING GROUPING(y) = 0",
+            builtin
+        )
+        .is_ok());
+        assert_eq!(
+            check_functions("SELECT GROUPING(id) FROM a", builtin),
+            Err("GROUPING is only allowed in a query with GROUP BY".to_string())
+        );
+        assert_eq!(
+            check_functions("SELECT id, GROUPING(y) FROM a GROUP BY id", builtin),
+            Err("GROUPING arguments must be GROUP BY expressions, got y".to_string())
+        );
+
+        let mut functions = FunctionRegistry::new();
+        functions
+            .register(FunctionSignature::new(
+                "cont",
+                Arity::Exact(1),
+                ReturnType::Fixed(DataType::Integer),
+            ))
+            .unwrap();
+        assert!(check_functions("SELECT cont(y) FROM a", &functions).is_ok());
+        assert_eq!(
+            check_functions("SELECT cont(y, id) FROM a", &functions),
+            Err("Function 'CONT' expects 1 argument, got 2".to_string())
+        );
+    }
+
+    fn check_insert(sql: &str) -> Result<(), String> {
+        let mut catalog = catalog();
+        let z = ColumnDef {
+            name: "z".to_string(),
+            data_type: DataType::Integer,
+            not_null: false,
+            default: None,
+        };
+        catalog.add_table(TableSchema::new("s", vec![z])).unwrap();
+        let Query::Insert(insert) = Parser::new(sql)?.parse()? else {
+            panic!("Expected an INSERT");
+        };
+        Analyzer::new(&catalog).check_insert(&insert)
+    }
+
+    /// Tests that INSERT ... SELECT must produce one value per target column, counting `*`.
+    #[test]
+    fn test_check_insert() {
+        assert!(check_insert("INSERT INTO b (id, x) SELECT id, y FROM a").is_ok());
+        assert!(check_insert("INSERT INTO b (id, x) SELECT * FROM a").is_ok());
+        assert!(check_insert("INSERT INTO b (id) SELECT * EXCEPT (y) FROM a").is_ok());
+        assert!(check_insert("INSERT INTO b (id) VALUES (1)").is_ok());
+
+        let mismatch =
+            Err("INSERT has 2 target columns, but SELECT produces 1 columns".to_string());
+        assert_eq!(
+            check_insert("INSERT INTO b (id, x) SELECT id FROM a"),
+            mismatch
+        );
+        assert_eq!(
+            check_insert("INSERT INTO b (id, x) SELECT * EXCEPT (y) FROM a"),
+            mismatch
+        );
+        assert_eq!(
+            check_insert("INSERT INTO a (id, y) SELECT * FROM s"),
+            mismatch
+        );
+        assert_eq!(
+            check_insert("INSERT INTO b (id) SELECT * FROM a"),
+            Err("INSERT has 1 target columns, but SELECT produces 2 columns".to_string())
+        );
+    }
+
+    fn duplicate_output_names(sql: &str) -> Result<Vec<String>, String> {
+        let catalog = catalog();
+        let Query::Select(select) = Parser::new(sql)?.parse()? else {
+            panic!("Expected a SELECT");
+        };
+        Analyzer::new(&catalog).duplicate_output_names(&select)
+    }
+
+    /// Tests that output columns sharing a name are reported once each, including via `*`.
+    #[test]
+    fn test_duplicate_output_names() {
+        assert_eq!(duplicate_output_names("SELECT id, y FROM a"), Ok(vec![]));
+        assert_eq!(
+            duplicate_output_names("SELECT id, y, ID, id FROM a"),
+            Ok(vec!["id".to_string()])
+        );
+        assert_eq!(
+            duplicate_output_names("SELECT *, y FROM a"),
+            Ok(vec!["y".to_string()])
+        );
+        assert_eq!(
+            duplicate_output_names("SELECT * EXCEPT (y), y FROM a"),
+            Ok(vec![])
+        );
+        assert_eq!(
+            duplicate_output_names("SELECT id FROM missing"),
+            Err("Table 'missing' does not exist".to_string())
+        );
+    }
+}
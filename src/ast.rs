@@ -1,8 +1,10 @@
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Expression {
     Or(Box<Expression>, Box<Expression>),
     And(Box<Expression>, Box<Expression>),
     Not(Box<Expression>),
+    /// Unary minus, `-expr`. A minus sign right before a number is part of the literal instead.
+    Negate(Box<Expression>),
     Binary {
         left: Box<Expression>,
         operator: BinaryOperator,
@@ -14,10 +16,93 @@ pub enum Expression {
     Float(f64),
     Text(String),
     Boolean(bool),
-    Function(String, Vec<Expression>),
+    Blob(Vec<u8>),
+    /// The `DEFAULT` keyword, only meaningful as an INSERT or UPDATE value.
+    Default,
+    Function {
+        name: String,
+        args: Vec<Expression>,
+        /// Arguments passed by name, as in `make_date(year => 2024)`. They follow every
+        /// positional argument.
+        named_args: Vec<(String, Expression)>,
+        /// The `FILTER (WHERE ...)` condition of an aggregate call.
+        filter: Option<Box<Expression>>,
+        /// The `ORDER BY` inside an aggregate call, as in `STRING_AGG(name, ',' ORDER BY name)`.
+        order_by: Vec<Ordering>,
+    },
+    Array(Vec<Expression>),
+    Subscript {
+        base: Box<Expression>,
+        index: Box<Expression>,
+    },
+    Exists(Box<Select>),
+    Interval(String),
+    AtTimeZone {
+        expr: Box<Expression>,
+        zone: Box<Expression>,
+    },
+    /// A row constructor, `(a, b)`.
+    Row(Vec<Expression>),
+    /// `expr IN (a, b, ...)`.
+    InList {
+        expr: Box<Expression>,
+        list: Vec<Expression>,
+    },
+    /// A postfix cast, `expr::type`.
+    Cast {
+        expr: Box<Expression>,
+        data_type: DataType,
+    },
+    Like {
+        expr: Box<Expression>,
+        pattern: Box<Expression>,
+        escape: Option<char>,
+    },
+    /// `expr [NOT] BETWEEN low AND high`, with inclusive bounds.
+    Between {
+        expr: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+    },
+    /// `* EXCEPT (...) REPLACE (...)` in a select list. A bare `*` is `Asterisk`.
+    Wildcard {
+        except: Vec<String>,
+        replace: Vec<Replacement>,
+    },
+    /// A quantified comparison, `left = ANY (...)` or `left > ALL (...)`.
+    Quantified {
+        left: Box<Expression>,
+        operator: BinaryOperator,
+        quantifier: Quantifier,
+        right: QuantifiedOperand,
+    },
+}
+
+/// Whether a quantified comparison must hold for any or for all values. `SOME` parses as `Any`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantifier {
+    Any,
+    All,
 }
 
-#[derive(Debug)]
+/// The values a quantified comparison is made against.
+#[derive(Debug, PartialEq)]
+pub enum QuantifiedOperand {
+    /// An array-valued expression, as in `x = ANY (ARRAY[1, 2])`.
+    Array(Box<Expression>),
+    /// A subquery returning one column, as in `x > ALL (SELECT y FROM t)`.
+    Subquery(Box<Select>),
+}
+
+/// A `expr AS column` item of a `* REPLACE (...)` modifier.
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    pub expression: Expression,
+    pub column: String,
+}
+
+#[derive(Debug, PartialEq)]
 pub enum BinaryOperator {
     Equal,
     NotEqual,
@@ -25,9 +110,35 @@ pub enum BinaryOperator {
     LessThanOrEqual,
     GreaterThan,
     GreaterThanOrEqual,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    JsonGet,
+    JsonGetText,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataType {
+    Integer,
+    Float,
+    Text,
+    Boolean,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    pub data_type: DataType,
+    pub not_null: bool,
+    /// The value `DEFAULT` stands for; a column without one defaults to NULL.
+    pub default: Option<Value>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Insert {
     pub table: Table,
     pub columns: Vec<String>,
@@ -35,51 +146,719 @@ pub struct Insert {
     pub select: Option<Box<Select>>,
 }
 
-#[derive(Debug)]
+/// `UPDATE table SET column = expression, ... [WHERE condition]`.
+#[derive(Debug, PartialEq)]
+pub struct Update {
+    pub table: Table,
+    /// The `column = expression` pairs of the SET clause, in the order written.
+    pub assignments: Vec<(String, Expression)>,
+    pub where_clause: Option<Expression>,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Join {
+    pub join_type: JoinType,
     pub table: Table,
     pub condition: Option<Expression>,
 }
 
-#[derive(Debug)]
+/// How a JOIN pairs rows. A bare `JOIN` is an inner join, and `FROM a, b` is a cross join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Ordering {
     pub expression: Expression,
     pub direction: SortOrder,
+    /// The `COLLATE` name, if given. Without one, text compares byte-wise.
+    pub collation: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Query {
-    Select(Select),
+    Select(Box<Select>),
     Insert(Insert),
+    Update(Update),
+    Explain(Box<Query>),
+    /// `BEGIN` or `START TRANSACTION`.
+    Begin,
+    Commit,
+    Rollback,
+    /// `SHOW TABLES`: lists the tables in the catalog.
+    ShowTables,
+    /// `SHOW COLUMNS FROM table`: lists a table's columns.
+    ShowColumns {
+        table: String,
+    },
+    /// `DESCRIBE table`: lists a table's columns, like `SHOW COLUMNS`.
+    Describe {
+        table: String,
+    },
+    /// `CREATE TABLE name (column type [NOT NULL] [DEFAULT value], ...)`.
+    CreateTable {
+        name: String,
+        columns: Vec<ColumnDef>,
+    },
+    /// `DROP TABLE [IF EXISTS] name`.
+    DropTable {
+        name: String,
+        if_exists: bool,
+    },
+}
+
+/// A table named by a query, as listed by `Query::tables_referenced`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    pub name: String,
+    pub access: TableAccess,
+}
+
+/// Whether a query only reads a table or also writes to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableAccess {
+    Read,
+    Write,
+}
+
+/// The kind of statement a query is, as returned by `Query::category`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementCategory {
+    /// Data definition: CREATE, DROP, ALTER, TRUNCATE.
+    Ddl,
+    /// Data manipulation: INSERT, UPDATE, DELETE.
+    Dml,
+    /// Data query: SELECT and statements that only read, such as SHOW and EXPLAIN.
+    Dql,
+    /// Transaction control: BEGIN, COMMIT, ROLLBACK.
+    Tcl,
+}
+
+impl Query {
+    /// Returns the kind of statement this is. EXPLAIN is a query whatever it explains, since it
+    /// does not run the statement.
+    pub fn category(&self) -> StatementCategory {
+        match self {
+            Query::Select(_)
+            | Query::Explain(_)
+            | Query::ShowTables
+            | Query::ShowColumns { .. }
+            | Query::Describe { .. } => StatementCategory::Dql,
+            Query::Insert(_) | Query::Update(_) => StatementCategory::Dml,
+            Query::CreateTable { .. } | Query::DropTable { .. } => StatementCategory::Ddl,
+            Query::Begin | Query::Commit | Query::Rollback => StatementCategory::Tcl,
+        }
+    }
+
+    /// Returns true if the statement changes the schema.
+    pub fn is_ddl(&self) -> bool {
+        self.category() == StatementCategory::Ddl
+    }
+
+    /// Returns every table the query reads or writes, including those in joins and
+    /// subqueries, in the order they first appear. A table that is both read and written is
+    /// listed once for each. EXPLAIN lists the tables of the query it explains, and SHOW
+    /// COLUMNS and DESCRIBE read the table they describe.
+    pub fn tables_referenced(&self) -> Vec<TableRef> {
+        let mut tables = Vec::new();
+        self.collect_tables(&mut tables);
+        tables
+    }
+
+    fn collect_tables(&self, tables: &mut Vec<TableRef>) {
+        match self {
+            Query::Select(select) => select.collect_tables(tables),
+            Query::Insert(insert) => {
+                add_table(tables, &insert.table.name, TableAccess::Write);
+                if let Some(select) = &insert.select {
+                    select.collect_tables(tables);
+                }
+            }
+            Query::Update(update) => {
+                add_table(tables, &update.table.name, TableAccess::Write);
+                let expressions = update
+                    .assignments
+                    .iter()
+                    .map(|(_, expression)| expression)
+                    .chain(&update.where_clause);
+                for expression in expressions {
+                    expression.collect_tables(tables);
+                }
+            }
+            Query::Explain(query) => query.collect_tables(tables),
+            Query::ShowColumns { table } | Query::Describe { table } => {
+                add_table(tables, table, TableAccess::Read)
+            }
+            Query::CreateTable { name, .. } | Query::DropTable { name, .. } => {
+                add_table(tables, name, TableAccess::Write)
+            }
+            Query::Begin | Query::Commit | Query::Rollback | Query::ShowTables => {}
+        }
+    }
+}
+
+/// Adds a table reference unless the same table, compared case-insensitively, is already
+/// listed with the same access.
+fn add_table(tables: &mut Vec<TableRef>, name: &str, access: TableAccess) {
+    let listed = tables
+        .iter()
+        .any(|table| table.access == access && table.name.eq_ignore_ascii_case(name));
+    if !listed {
+        tables.push(TableRef {
+            name: name.to_string(),
+            access,
+        });
+    }
+}
+
+/// An item of a select list, `expression [[AS] alias]`.
+#[derive(Debug, PartialEq)]
+pub struct SelectItem {
+    pub expression: Expression,
+    pub alias: Option<String>,
 }
 
-#[derive(Debug)]
+impl From<Expression> for SelectItem {
+    fn from(expression: Expression) -> Self {
+        SelectItem {
+            expression,
+            alias: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Select {
-    pub columns: Vec<Expression>,
+    pub with: Option<With>,
+    pub distinct: bool,
+    pub columns: Vec<SelectItem>,
     pub table: Table,
     pub joins: Vec<Join>,
     pub where_clause: Option<Expression>,
     pub group_by: Option<Vec<Expression>>,
     pub having: Option<Expression>,
     pub order_by: Option<Vec<Ordering>>,
+    pub limit: Option<Expression>,
+    pub offset: Option<Expression>,
+}
+
+impl Select {
+    /// Returns whether the SELECT aggregates its rows: it has a GROUP BY or HAVING clause,
+    /// or its select list calls an aggregate function.
+    pub fn is_aggregate(&self) -> bool {
+        self.group_by.is_some()
+            || self.having.is_some()
+            || self
+                .columns
+                .iter()
+                .any(|item| item.expression.contains_aggregate())
+    }
+
+    /// Adds the tables this SELECT and its CTEs read, then those its subqueries read.
+    /// References to a name its WITH clause binds are not tables and are left out.
+    fn collect_tables(&self, tables: &mut Vec<TableRef>) {
+        let Some(with) = &self.with else {
+            return self.collect_body_tables(tables);
+        };
+        let mut read = Vec::new();
+        for cte in &with.ctes {
+            cte.query.collect_tables(&mut read);
+        }
+        self.collect_body_tables(&mut read);
+        for table in read {
+            if !with.binds(&table.name) {
+                add_table(tables, &table.name, table.access);
+            }
+        }
+    }
+
+    /// Adds the tables read by the SELECT apart from its WITH clause.
+    fn collect_body_tables(&self, tables: &mut Vec<TableRef>) {
+        for table in std::iter::once(&self.table).chain(self.joins.iter().map(|j| &j.table)) {
+            match &table.subquery {
+                Some(subquery) => subquery.collect_tables(tables),
+                None => add_table(tables, &table.name, TableAccess::Read),
+            }
+        }
+
+        let expressions = self
+            .columns
+            .iter()
+            .map(|item| &item.expression)
+            .chain(self.joins.iter().filter_map(|join| join.condition.as_ref()))
+            .chain(&self.where_clause)
+            .chain(self.group_by.iter().flatten())
+            .chain(&self.having)
+            .chain(self.order_by.iter().flatten().map(|o| &o.expression))
+            .chain(&self.limit)
+            .chain(&self.offset);
+        for expression in expressions {
+            expression.collect_tables(tables);
+        }
+    }
+}
+
+/// A `WITH [RECURSIVE]` clause: the named queries a SELECT can read like tables.
+#[derive(Debug, PartialEq)]
+pub struct With {
+    pub recursive: bool,
+    pub ctes: Vec<Cte>,
+}
+
+impl With {
+    /// Returns whether one of the clause's CTEs has the given name, compared case-insensitively.
+    pub fn binds(&self, name: &str) -> bool {
+        self.ctes
+            .iter()
+            .any(|cte| cte.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A common table expression, `name [(column, ...)] AS (query)`.
+#[derive(Debug, PartialEq)]
+pub struct Cte {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub query: SetQuery,
+}
+
+/// A SELECT or SELECTs combined with UNION, as in the body of a CTE.
+#[derive(Debug, PartialEq)]
+pub enum SetQuery {
+    Select(Box<Select>),
+    /// `left UNION [ALL] right`. UNION is left-associative, so `right` is never a union.
+    Union {
+        left: Box<SetQuery>,
+        right: Box<SetQuery>,
+        all: bool,
+    },
+}
+
+impl SetQuery {
+    /// Returns whether the query reads the named table, directly or through a subquery.
+    pub fn reads_table(&self, name: &str) -> bool {
+        let mut tables = Vec::new();
+        self.collect_tables(&mut tables);
+        tables
+            .iter()
+            .any(|table| table.name.eq_ignore_ascii_case(name))
+    }
+
+    fn collect_tables(&self, tables: &mut Vec<TableRef>) {
+        match self {
+            SetQuery::Select(select) => select.collect_tables(tables),
+            SetQuery::Union { left, right, .. } => {
+                left.collect_tables(tables);
+                right.collect_tables(tables);
+            }
+        }
+    }
+}
+
+impl Expression {
+    /// Returns the expressions directly nested in this one. A subquery's expressions are not
+    /// children of the EXISTS around it.
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Or(left, right)
+            | Expression::And(left, right)
+            | Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::Subscript { base, index } => vec![base, index],
+            Expression::AtTimeZone { expr, zone } => vec![expr, zone],
+            Expression::Like { expr, pattern, .. } => vec![expr, pattern],
+            Expression::Between {
+                expr, low, high, ..
+            } => vec![expr, low, high],
+            Expression::Not(inner)
+            | Expression::Negate(inner)
+            | Expression::Cast { expr: inner, .. } => vec![inner],
+            Expression::Function {
+                args,
+                named_args,
+                filter,
+                order_by,
+                ..
+            } => args
+                .iter()
+                .chain(named_args.iter().map(|(_, arg)| arg))
+                .chain(filter.as_deref())
+                .chain(order_by.iter().map(|ordering| &ordering.expression))
+                .collect(),
+            Expression::Array(elements) | Expression::Row(elements) => elements.iter().collect(),
+            Expression::InList { expr, list } => {
+                std::iter::once(expr.as_ref()).chain(list).collect()
+            }
+            Expression::Wildcard { replace, .. } => {
+                replace.iter().map(|item| &item.expression).collect()
+            }
+            Expression::Quantified { left, right, .. } => match right {
+                QuantifiedOperand::Array(array) => vec![left, array],
+                QuantifiedOperand::Subquery(_) => vec![left],
+            },
+            Expression::Exists(_)
+            | Expression::Identifier(_)
+            | Expression::Asterisk
+            | Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::Text(_)
+            | Expression::Boolean(_)
+            | Expression::Blob(_)
+            | Expression::Default
+            | Expression::Interval(_) => Vec::new(),
+        }
+    }
+
+    /// Adds the tables read by subqueries anywhere in the expression.
+    fn collect_tables(&self, tables: &mut Vec<TableRef>) {
+        match self {
+            Expression::Exists(subquery) => subquery.collect_tables(tables),
+            Expression::Quantified {
+                left,
+                right: QuantifiedOperand::Subquery(subquery),
+                ..
+            } => {
+                left.collect_tables(tables);
+                subquery.collect_tables(tables);
+            }
+            _ => {
+                for child in self.children() {
+                    child.collect_tables(tables);
+                }
+            }
+        }
+    }
+
+    /// Returns whether the expression calls an aggregate function outside of any subquery.
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Expression::Function { name, .. } if is_aggregate_function(name) => true,
+            _ => self
+                .children()
+                .into_iter()
+                .any(Expression::contains_aggregate),
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Returns whether `name` is one of the aggregate functions COUNT, SUM, AVG, MIN and MAX.
+pub fn is_aggregate_function(name: &str) -> bool {
+    [
+        "COUNT",
+        "SUM",
+        "AVG",
+        "MIN",
+        "MAX",
+        "STRING_AGG",
+        "GROUP_CONCAT",
+    ]
+    .iter()
+    .any(|aggregate| name.eq_ignore_ascii_case(aggregate))
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Table {
+    /// The table's name, or for a subquery in FROM the alias it must be given.
     pub name: String,
+    /// The name given with `AS`, which qualified columns use instead of the table name.
+    pub alias: Option<String>,
+    pub sample: Option<TableSample>,
+    /// The subquery of a derived table, as in `FROM (SELECT ...) AS s`.
+    pub subquery: Option<Box<Select>>,
+    /// Whether the subquery was marked LATERAL, letting it refer to the tables before it.
+    pub lateral: bool,
+}
+
+/// A `TABLESAMPLE method (percent)` clause on a table reference.
+#[derive(Debug, PartialEq)]
+pub struct TableSample {
+    pub method: SampleMethod,
+    pub percent: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+pub enum SampleMethod {
+    /// Each row is kept with the given probability.
+    Bernoulli,
+    /// Each page is kept with the given probability.
+    System,
+}
+
+#[derive(Debug, PartialEq)]
 pub enum SortOrder {
     Ascending,
     Descending,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Integer(i64),
     Float(f64),
     Text(String),
     Boolean(bool),
+    Blob(Vec<u8>),
+    /// `DEFAULT` in an INSERT value list, replaced by the column's default when executed.
+    Default,
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+    /// A UUID, written as `UUID '550e8400-e29b-41d4-a716-446655440000'`.
+    #[cfg(feature = "uuid")]
+    Uuid([u8; 16]),
     Null,
 }
+
+impl Value {
+    /// Converts the value to `data_type`, as CAST does and as INSERT does for each column.
+    ///
+    /// Integers and floats convert both ways, a float only when it is a whole number. Text is
+    /// parsed as a number or as `true`/`false`. Booleans become 1 or 0, and an integer becomes
+    /// a boolean that is true unless it is 0. With the `json` feature, text is parsed as JSON
+    /// and JSON renders as text. NULL stays NULL.
+    pub fn coerce_to(&self, data_type: &DataType) -> Result<Value, String> {
+        let converted = match (self, data_type) {
+            (Value::Null, _) => Some(Value::Null),
+            (Value::Integer(_), DataType::Integer)
+            | (Value::Float(_), DataType::Float)
+            | (Value::Text(_), DataType::Text)
+            | (Value::Boolean(_), DataType::Boolean) => Some(self.clone()),
+            (Value::Integer(i), DataType::Float) => Some(Value::Float(*i as f64)),
+            // i64::MAX rounds up to 2^63 as a float, so the upper bound is exclusive
+            (Value::Float(f), DataType::Integer)
+                if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64 =>
+            {
+                Some(Value::Integer(*f as i64))
+            }
+            (Value::Integer(i), DataType::Boolean) => Some(Value::Boolean(*i != 0)),
+            (Value::Boolean(b), DataType::Integer) => Some(Value::Integer(i64::from(*b))),
+            (Value::Text(text), DataType::Integer) => text.trim().parse().ok().map(Value::Integer),
+            (Value::Text(text), DataType::Float) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(Value::Float),
+            (Value::Text(text), DataType::Boolean) => match text.trim().to_lowercase().as_str() {
+                "true" => Some(Value::Boolean(true)),
+                "false" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            #[cfg(feature = "json")]
+            (Value::Json(_), DataType::Json) => Some(self.clone()),
+            #[cfg(feature = "json")]
+            (Value::Text(text), DataType::Json) => serde_json::from_str(text).ok().map(Value::Json),
+            #[cfg(feature = "json")]
+            (Value::Json(json), DataType::Text) => Some(Value::Text(json.to_string())),
+            _ => None,
+        };
+        converted
+            .ok_or_else(|| format!("Cannot convert {} to {}", self.to_sql(), data_type.to_sql()))
+    }
+}
+
+/// Parses a UUID in its canonical form: 32 hex digits in groups of 8, 4, 4, 4 and 12 separated
+/// by hyphens. Either case is accepted.
+#[cfg(feature = "uuid")]
+pub(crate) fn parse_uuid(text: &str) -> Option<[u8; 16]> {
+    let groups: Vec<&str> = text.split('-').collect();
+    let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+    if lengths != [8, 4, 4, 4, 12] {
+        return None;
+    }
+    let digits = groups.concat();
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+/// Formats a UUID in its canonical lowercase form.
+#[cfg(feature = "uuid")]
+pub(crate) fn uuid_string(bytes: &[u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn select(sql: &str) -> Select {
+        match Parser::new(sql).unwrap().parse().unwrap() {
+            Query::Select(select) => *select,
+            other => panic!("Expected SELECT query, got {:?}", other),
+        }
+    }
+
+    /// Tests that aggregate functions, GROUP BY and HAVING make a SELECT an aggregate.
+    #[test]
+    fn test_is_aggregate() {
+        assert!(select("SELECT COUNT(*) FROM t").is_aggregate());
+        assert!(select("SELECT max(a) FROM t").is_aggregate());
+        assert!(select("SELECT SUM(a) > 10 FROM t").is_aggregate());
+        assert!(select("SELECT a FROM t GROUP BY a").is_aggregate());
+        assert!(select("SELECT a FROM t GROUP BY a HAVING a > 1").is_aggregate());
+    }
+
+    /// Tests that plain selects, scalar functions and aggregates inside subqueries do not.
+    #[test]
+    fn test_is_not_aggregate() {
+        assert!(!select("SELECT a, b FROM t WHERE a > 1").is_aggregate());
+        assert!(!select("SELECT lower(a) FROM t").is_aggregate());
+        assert!(!select("SELECT * FROM t ORDER BY a").is_aggregate());
+        assert!(!select("SELECT EXISTS (SELECT COUNT(*) FROM u) FROM t").is_aggregate());
+    }
+
+    /// Tests that tables are collected from joins, subqueries and INSERT targets, each once
+    /// per kind of access.
+    #[test]
+    fn test_tables_referenced() {
+        let tables = |sql: &str| -> Vec<(String, TableAccess)> {
+            Parser::new(sql)
+                .unwrap()
+                .parse()
+                .unwrap()
+                .tables_referenced()
+                .into_iter()
+                .map(|table| (table.name, table.access))
+                .collect()
+        };
+        let read = |name: &str| (name.to_string(), TableAccess::Read);
+
+        assert_eq!(
+            tables(
+                "SELECT a FROM users JOIN orders ON users.id = orders.user_id \
+                 WHERE EXISTS (SELECT b FROM items WHERE EXISTS (SELECT c FROM USERS)) \
+                 ORDER BY a"
+            ),
+            vec![read("users"), read("orders"), read("items")]
+        );
+        assert_eq!(
+            tables("INSERT INTO archive (id) SELECT id FROM archive"),
+            vec![("archive".to_string(), TableAccess::Write), read("archive")]
+        );
+        assert_eq!(
+            tables("UPDATE users SET n = 1 WHERE EXISTS (SELECT a FROM orders)"),
+            vec![("users".to_string(), TableAccess::Write), read("orders")]
+        );
+        assert_eq!(tables("EXPLAIN DESCRIBE users"), vec![read("users")]);
+        assert!(tables("SHOW TABLES").is_empty());
+    }
+
+    /// Tests every conversion `coerce_to` supports, and that the rest are errors.
+    #[test]
+    fn test_coerce_to() {
+        use DataType::{Boolean, Float, Integer, Text as TextType};
+        let text = |s: &str| Value::Text(s.to_string());
+
+        let converted = [
+            (Value::Integer(3), Integer, Value::Integer(3)),
+            (Value::Integer(3), Float, Value::Float(3.0)),
+            (Value::Integer(0), Boolean, Value::Boolean(false)),
+            (Value::Integer(-2), Boolean, Value::Boolean(true)),
+            (Value::Float(2.0), Integer, Value::Integer(2)),
+            (Value::Float(2.5), Float, Value::Float(2.5)),
+            (Value::Boolean(true), Integer, Value::Integer(1)),
+            (Value::Boolean(false), Integer, Value::Integer(0)),
+            (Value::Boolean(true), Boolean, Value::Boolean(true)),
+            (text(" 42 "), Integer, Value::Integer(42)),
+            (text("1.5"), Float, Value::Float(1.5)),
+            (text("7"), Float, Value::Float(7.0)),
+            (text("TRUE"), Boolean, Value::Boolean(true)),
+            (text(" false"), Boolean, Value::Boolean(false)),
+            (text("x"), TextType, text("x")),
+            (Value::Null, Integer, Value::Null),
+            (Value::Null, Float, Value::Null),
+            (Value::Null, TextType, Value::Null),
+            (Value::Null, Boolean, Value::Null),
+        ];
+        for (value, data_type, expected) in converted {
+            assert_eq!(value.coerce_to(&data_type), Ok(expected), "{:?}", value);
+        }
+
+        let rejected = [
+            (Value::Float(2.5), Integer, "Cannot convert 2.5 to INTEGER"),
+            (
+                Value::Float(1e19),
+                Integer,
+                "Cannot convert 10000000000000000000.0 to INTEGER",
+            ),
+            (Value::Float(1.0), Boolean, "Cannot convert 1.0 to BOOLEAN"),
+            (Value::Boolean(true), Float, "Cannot convert TRUE to FLOAT"),
+            (Value::Integer(1), TextType, "Cannot convert 1 to TEXT"),
+            (
+                Value::Boolean(false),
+                TextType,
+                "Cannot convert FALSE to TEXT",
+            ),
+            (text("abc"), Integer, "Cannot convert 'abc' to INTEGER"),
+            (text("1.5"), Integer, "Cannot convert '1.5' to INTEGER"),
+            (text("inf"), Float, "Cannot convert 'inf' to FLOAT"),
+            (text("yes"), Boolean, "Cannot convert 'yes' to BOOLEAN"),
+            (
+                Value::Blob(vec![1]),
+                Integer,
+                "Cannot convert X'01' to INTEGER",
+            ),
+        ];
+        for (value, data_type, error) in rejected {
+            assert_eq!(value.coerce_to(&data_type), Err(error.to_string()));
+        }
+    }
+
+    /// Tests that statements are classified by what they do.
+    #[test]
+    fn test_category() {
+        let category = |sql: &str| Parser::new(sql).unwrap().parse().unwrap().category();
+
+        assert_eq!(category("SELECT a FROM t"), StatementCategory::Dql);
+        assert_eq!(
+            category("EXPLAIN INSERT INTO t (a) VALUES (1)"),
+            StatementCategory::Dql
+        );
+        assert_eq!(category("SHOW COLUMNS FROM t"), StatementCategory::Dql);
+        assert_eq!(category("DESCRIBE t"), StatementCategory::Dql);
+        assert_eq!(
+            category("INSERT INTO t (a) VALUES (1)"),
+            StatementCategory::Dml
+        );
+        assert_eq!(
+            category("INSERT INTO t (a) SELECT a FROM u"),
+            StatementCategory::Dml
+        );
+        assert_eq!(
+            category("UPDATE t SET a = 1 WHERE b = 2"),
+            StatementCategory::Dml
+        );
+        assert_eq!(
+            category("CREATE TABLE t (a INTEGER)"),
+            StatementCategory::Ddl
+        );
+        assert_eq!(category("DROP TABLE t"), StatementCategory::Ddl);
+        assert_eq!(category("BEGIN"), StatementCategory::Tcl);
+        assert_eq!(category("START TRANSACTION"), StatementCategory::Tcl);
+        assert_eq!(category("COMMIT"), StatementCategory::Tcl);
+        assert_eq!(category("ROLLBACK"), StatementCategory::Tcl);
+        assert!(!Parser::new("SELECT a FROM t")
+            .unwrap()
+            .parse()
+            .unwrap()
+            .is_ddl());
+    }
+}
@@ -2,16 +2,33 @@
 TODO: I thought I had implemented it with the utmost care so that it wouldn't cause a deadlock, but there are some parts that seem to be causing a deadlock when I run the unit tests.
 */
 
-use crate::storage::{NodeType, Page, StorageEngine};
+use crate::storage::{Durability, NodeType, Page, PageData, Storage, StorageEngine};
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 /// BufferPool manages cached pages with LRU eviction policy.
-pub struct BufferPool {
-    capacity: usize,
+///
+/// Pages are read from and written to any `Storage` backend, the file-backed `StorageEngine` by
+/// default.
+pub struct BufferPool<S: Storage = StorageEngine> {
+    capacity: AtomicUsize,
     // Combined pool and LRU queue under a single Mutex to prevent deadlocks
     pool_and_lru: Mutex<PoolAndLRU>,
-    storage: Mutex<StorageEngine>,
+    storage: Mutex<S>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Counters describing how well the buffer pool is serving requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// Number of `get_page` calls answered from the pool.
+    pub hits: u64,
+    /// Number of `get_page` calls that had to read from storage.
+    pub misses: u64,
+    /// Number of pages currently cached.
+    pub resident_pages: usize,
 }
 
 struct PoolAndLRU {
@@ -19,16 +36,18 @@ struct PoolAndLRU {
     lru_queue: VecDeque<u32>,
 }
 
-impl BufferPool {
-    /// Creates a new BufferPool with specified capacity and storage engine.
-    pub fn new(capacity: usize, storage: StorageEngine) -> Self {
+impl<S: Storage> BufferPool<S> {
+    /// Creates a new BufferPool with specified capacity and storage backend.
+    pub fn new(capacity: usize, storage: S) -> Self {
         BufferPool {
-            capacity,
+            capacity: AtomicUsize::new(capacity),
             pool_and_lru: Mutex::new(PoolAndLRU {
                 pool: HashMap::new(),
                 lru_queue: VecDeque::new(),
             }),
             storage: Mutex::new(storage),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
@@ -49,13 +68,79 @@ impl BufferPool {
                     pool_lru.lru_queue.remove(pos);
                 }
                 pool_lru.lru_queue.push_front(page_id);
+                self.hits.fetch_add(1, Ordering::Relaxed);
                 return Ok(page);
             } else {
                 println!("BufferPool::get_page - Page {} not found in pool.", page_id);
             }
         }
 
-        // If not in pool, load from storage
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        self.load_page(page_id)
+    }
+
+    /// Loads pages into the pool ahead of a scan without returning them.
+    ///
+    /// Pages that are already cached are left alone. At most `capacity` pages are loaded, since
+    /// loading more would only evict the first ones before the scan reaches them.
+    pub fn prefetch(&self, page_ids: &[u32]) -> std::io::Result<()> {
+        for &page_id in page_ids.iter().take(self.capacity()) {
+            let resident = {
+                let pool_lru = self.pool_and_lru.lock().unwrap();
+                pool_lru.pool.contains_key(&page_id)
+            };
+            if !resident {
+                self.load_page(page_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the maximum number of cached pages.
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Changes the maximum number of cached pages.
+    ///
+    /// When shrinking, least recently used pages are written to storage and evicted until the
+    /// pool fits, then storage is synced. Growing only raises the limit.
+    pub fn resize(&self, new_capacity: usize) -> std::io::Result<()> {
+        let mut pool_lru = self.pool_and_lru.lock().unwrap();
+        self.capacity.store(new_capacity, Ordering::Relaxed);
+        if pool_lru.pool.len() <= new_capacity {
+            return Ok(());
+        }
+
+        let mut storage = self.storage.lock().unwrap();
+        while pool_lru.pool.len() > new_capacity {
+            let Some(old_id) = pool_lru.lru_queue.pop_back() else {
+                break;
+            };
+            if let Some(page) = pool_lru.pool.get(&old_id).cloned() {
+                // Written before removal so a failed write leaves the page cached
+                if let Err(e) = storage.write_page(&page.data.read().unwrap()) {
+                    pool_lru.lru_queue.push_back(old_id);
+                    return Err(e);
+                }
+                pool_lru.pool.remove(&old_id);
+            }
+        }
+        storage.sync()
+    }
+
+    /// Returns the hit/miss counters and the number of cached pages.
+    pub fn stats(&self) -> BufferPoolStats {
+        let resident_pages = self.pool_and_lru.lock().unwrap().pool.len();
+        BufferPoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            resident_pages,
+        }
+    }
+
+    /// Reads a page from storage and inserts it into the pool, evicting if necessary.
+    fn load_page(&self, page_id: u32) -> std::io::Result<Arc<Page>> {
         println!(
             "BufferPool::get_page - Loading page {} from storage.",
             page_id
@@ -81,7 +166,7 @@ impl BufferPool {
             let mut pool_lru = self.pool_and_lru.lock().unwrap();
 
             // Evict least recently used page if capacity is exceeded
-            if pool_lru.pool.len() >= self.capacity {
+            if pool_lru.pool.len() >= self.capacity() {
                 if let Some(old_id) = pool_lru.lru_queue.pop_back() {
                     println!(
                         "BufferPool::get_page - Evicting least recently used page {}.",
@@ -110,6 +195,51 @@ impl BufferPool {
         storage.write_page(&page_data)
     }
 
+    /// Stores a new version of a page: the cached copy, if any, is replaced and the page is
+    /// written to storage.
+    pub fn put_page(&self, page_data: PageData) -> std::io::Result<()> {
+        let pool_lru = self.pool_and_lru.lock().unwrap();
+        let mut storage = self.storage.lock().unwrap();
+        storage.write_page(&page_data)?;
+        if let Some(page) = pool_lru.pool.get(&page_data.id) {
+            *page.data.write().unwrap() = page_data;
+        }
+        Ok(())
+    }
+
+    /// Stores new versions of several pages as one unit with `Storage::write_pages`. The
+    /// cached copies are replaced only once every page is stored.
+    pub fn put_pages(&self, pages: &[PageData]) -> std::io::Result<()> {
+        let pool_lru = self.pool_and_lru.lock().unwrap();
+        let mut storage = self.storage.lock().unwrap();
+        storage.write_pages(pages)?;
+        for page_data in pages {
+            if let Some(page) = pool_lru.pool.get(&page_data.id) {
+                *page.data.write().unwrap() = page_data.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes every cached page to storage and syncs the file to disk.
+    pub fn flush_all(&self) -> std::io::Result<()> {
+        let pool_lru = self.pool_and_lru.lock().unwrap();
+        let mut storage = self.storage.lock().unwrap();
+        for page in pool_lru.pool.values() {
+            storage.write_page(&page.data.read().unwrap())?;
+        }
+        storage.sync()
+    }
+
+    /// Switches the storage durability mode. Returning to `Durability::Full` first flushes every
+    /// cached page, so all data written in bulk mode is on disk once this returns.
+    pub fn set_durability(&self, durability: Durability) -> std::io::Result<()> {
+        if durability == Durability::Full {
+            self.flush_all()?;
+        }
+        self.storage.lock().unwrap().set_durability(durability)
+    }
+
     /// Allocates a new page and inserts it into the pool.
     pub fn allocate_page(&self, node_type: NodeType) -> std::io::Result<Arc<Page>> {
         println!(
@@ -138,7 +268,7 @@ impl BufferPool {
         // Insert the new page into the pool
 
         // Evict least recently used page if capacity is exceeded
-        if pool_lru.pool.len() >= self.capacity {
+        if pool_lru.pool.len() >= self.capacity() {
             if let Some(old_id) = pool_lru.lru_queue.pop_back() {
                 println!(
                     "BufferPool::allocate_page - Evicting least recently used page {}.",
@@ -154,3 +284,219 @@ impl BufferPool {
         Ok(page)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Tests that prefetched pages are served from the pool afterwards.
+    #[test]
+    fn test_prefetch_makes_pages_resident() {
+        let test_db = "test_prefetch.db";
+        let _ = fs::remove_file(test_db);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        let page_ids: Vec<u32> = (0..4)
+            .map(|_| storage.allocate_page(NodeType::Leaf).unwrap().id)
+            .collect();
+
+        let buffer_pool = BufferPool::new(3, storage);
+        buffer_pool.prefetch(&page_ids).unwrap();
+        assert_eq!(buffer_pool.stats().resident_pages, 3);
+
+        for &page_id in &page_ids[..3] {
+            buffer_pool.get_page(page_id).unwrap();
+        }
+        let stats = buffer_pool.stats();
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 0);
+
+        // Pages beyond the capacity were not prefetched
+        buffer_pool.get_page(page_ids[3]).unwrap();
+        assert_eq!(buffer_pool.stats().misses, 1);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that pages bulk-loaded without per-write syncs are all on disk after leaving
+    /// bulk mode.
+    #[test]
+    fn test_bulk_load_then_flush() {
+        let test_db = "test_bulk_load.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = BufferPool::new(8, StorageEngine::new(test_db).unwrap());
+        buffer_pool.set_durability(Durability::Bulk).unwrap();
+        let mut page_ids = Vec::new();
+        for key in 0..32 {
+            let page = buffer_pool.allocate_page(NodeType::Leaf).unwrap();
+            let mut page_data = page.data.read().unwrap().clone();
+            page_data.keys.push(key);
+            page_data.values.push(key as u64);
+            page_ids.push(page_data.id);
+            buffer_pool.put_page(page_data).unwrap();
+        }
+        buffer_pool.set_durability(Durability::Full).unwrap();
+        drop(buffer_pool);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert_eq!(storage.durability(), Durability::Full);
+        assert_eq!(storage.header().page_count, 32);
+        for (key, page_id) in page_ids.into_iter().enumerate() {
+            assert_eq!(storage.read_page(page_id).unwrap().keys, vec![key as i32]);
+        }
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that shrinking the pool evicts the least recently used pages and writes their
+    /// latest contents to storage.
+    #[test]
+    fn test_resize_evicts_and_persists() {
+        let buffer_pool = BufferPool::new(4, MemoryStorage::default());
+        let mut page_ids = Vec::new();
+        for key in 0..4 {
+            let page = buffer_pool.allocate_page(NodeType::Leaf).unwrap();
+            let mut page_data = page.data.write().unwrap();
+            // Changed only in the cached copy, so eviction has to write it
+            page_data.keys.push(key);
+            page_ids.push(page_data.id);
+        }
+        // Touch the first page so the second and third are the least recently used
+        buffer_pool.get_page(page_ids[0]).unwrap();
+
+        buffer_pool.resize(2).unwrap();
+        assert_eq!(buffer_pool.capacity(), 2);
+        assert_eq!(buffer_pool.stats().resident_pages, 2);
+        {
+            let storage = buffer_pool.storage.lock().unwrap();
+            assert_eq!(storage.pages[&page_ids[1]].keys, vec![1]);
+            assert_eq!(storage.pages[&page_ids[2]].keys, vec![2]);
+            assert!(storage.pages[&page_ids[0]].keys.is_empty());
+        }
+
+        let misses = buffer_pool.stats().misses;
+        buffer_pool.get_page(page_ids[0]).unwrap();
+        buffer_pool.get_page(page_ids[3]).unwrap();
+        assert_eq!(buffer_pool.stats().misses, misses);
+        let page = buffer_pool.get_page(page_ids[1]).unwrap();
+        assert_eq!(page.data.read().unwrap().keys, vec![1]);
+        assert_eq!(buffer_pool.stats().misses, misses + 1);
+        assert_eq!(buffer_pool.stats().resident_pages, 2);
+
+        // Growing keeps every cached page
+        buffer_pool.resize(8).unwrap();
+        buffer_pool.get_page(page_ids[2]).unwrap();
+        assert_eq!(buffer_pool.stats().resident_pages, 3);
+    }
+
+    /// In-memory storage backend used to check that the pool works over any `Storage`.
+    #[derive(Default)]
+    struct MemoryStorage {
+        pages: HashMap<u32, PageData>,
+        reads: usize,
+        /// Page whose writes fail, to exercise error paths.
+        failing_page: Option<u32>,
+    }
+
+    impl Storage for MemoryStorage {
+        fn read_page(&mut self, page_id: u32) -> std::io::Result<PageData> {
+            self.reads += 1;
+            self.pages
+                .get(&page_id)
+                .cloned()
+                .ok_or_else(|| std::io::Error::other("Page not found"))
+        }
+
+        fn write_page(&mut self, page_data: &PageData) -> std::io::Result<()> {
+            if self.failing_page == Some(page_data.id) {
+                return Err(std::io::Error::other("Write failed"));
+            }
+            self.pages.insert(page_data.id, page_data.clone());
+            Ok(())
+        }
+
+        fn allocate_page(&mut self, node_type: NodeType) -> std::io::Result<PageData> {
+            let page_data = PageData::new(self.pages.len() as u32 + 1, node_type);
+            self.write_page(&page_data)?;
+            Ok(page_data)
+        }
+
+        fn sync(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Tests that the pool caches and writes through a non-file storage backend.
+    #[test]
+    fn test_custom_storage_backend() {
+        let buffer_pool = BufferPool::new(2, MemoryStorage::default());
+        let page_id = buffer_pool
+            .allocate_page(NodeType::Leaf)
+            .unwrap()
+            .data
+            .read()
+            .unwrap()
+            .id;
+        let mut page_data = PageData::new(page_id, NodeType::Leaf);
+        page_data.keys.push(7);
+        buffer_pool.put_page(page_data).unwrap();
+
+        assert_eq!(
+            buffer_pool
+                .get_page(page_id)
+                .unwrap()
+                .data
+                .read()
+                .unwrap()
+                .keys,
+            vec![7]
+        );
+        let storage = buffer_pool.storage.lock().unwrap();
+        assert_eq!(storage.reads, 0);
+        assert_eq!(storage.pages[&page_id].keys, vec![7]);
+    }
+
+    /// Tests that a failed batch write leaves both storage and the cached pages unchanged.
+    #[test]
+    fn test_put_pages_is_all_or_nothing() {
+        let buffer_pool = BufferPool::new(4, MemoryStorage::default());
+        let page_ids: Vec<u32> = (0..2)
+            .map(|_| {
+                let page = buffer_pool.allocate_page(NodeType::Leaf).unwrap();
+                let id = page.data.read().unwrap().id;
+                id
+            })
+            .collect();
+        let pages: Vec<PageData> = page_ids
+            .iter()
+            .map(|&id| {
+                let mut page_data = PageData::new(id, NodeType::Leaf);
+                page_data.keys.push(id as i32);
+                page_data
+            })
+            .collect();
+
+        buffer_pool.storage.lock().unwrap().failing_page = Some(page_ids[1]);
+        assert!(buffer_pool.put_pages(&pages).is_err());
+        for &id in &page_ids {
+            assert!(buffer_pool.storage.lock().unwrap().pages[&id]
+                .keys
+                .is_empty());
+            let page = buffer_pool.get_page(id).unwrap();
+            assert!(page.data.read().unwrap().keys.is_empty());
+        }
+
+        buffer_pool.storage.lock().unwrap().failing_page = None;
+        buffer_pool.put_pages(&pages).unwrap();
+        for &id in &page_ids {
+            assert_eq!(
+                buffer_pool.storage.lock().unwrap().pages[&id].keys,
+                vec![id as i32]
+            );
+            let page = buffer_pool.get_page(id).unwrap();
+            assert_eq!(page.data.read().unwrap().keys, vec![id as i32]);
+        }
+    }
+}
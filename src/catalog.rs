@@ -0,0 +1,66 @@
+use crate::ast::ColumnDef;
+use std::collections::HashMap;
+
+/// The schema of a single table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnDef>,
+}
+
+impl TableSchema {
+    /// Creates a schema for `name` with the given columns.
+    pub fn new(name: &str, columns: Vec<ColumnDef>) -> Self {
+        TableSchema {
+            name: name.to_string(),
+            columns,
+        }
+    }
+
+    /// Returns the position of the named column, if it exists.
+    pub fn column_index(&self, column: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(column))
+    }
+}
+
+/// Catalog keeps track of the tables that exist and their schemas.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    tables: HashMap<String, TableSchema>,
+}
+
+impl Catalog {
+    /// Creates an empty catalog.
+    pub fn new() -> Self {
+        Catalog::default()
+    }
+
+    /// Registers a table. Table names are case-insensitive and must be unique.
+    pub fn add_table(&mut self, schema: TableSchema) -> Result<(), String> {
+        let key = schema.name.to_lowercase();
+        if self.tables.contains_key(&key) {
+            return Err(format!("Table '{}' already exists", schema.name));
+        }
+        self.tables.insert(key, schema);
+        Ok(())
+    }
+
+    /// Unregisters a table and returns its schema, if it was registered.
+    pub fn remove_table(&mut self, name: &str) -> Option<TableSchema> {
+        self.tables.remove(&name.to_lowercase())
+    }
+
+    /// Returns the schemas of every table, ordered by name.
+    pub fn tables(&self) -> Vec<&TableSchema> {
+        let mut tables: Vec<&TableSchema> = self.tables.values().collect();
+        tables.sort_by_key(|schema| schema.name.to_lowercase());
+        tables
+    }
+
+    /// Looks up a table by name.
+    pub fn get_table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables.get(&name.to_lowercase())
+    }
+}
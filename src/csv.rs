@@ -0,0 +1,348 @@
+//! CSV export of query results and import into tables, following RFC 4180.
+
+#[cfg(feature = "uuid")]
+use crate::ast::uuid_string;
+use crate::ast::Value;
+use crate::catalog::TableSchema;
+use crate::executor::{Executor, QueryResult};
+use std::io::{self, Read, Write};
+
+impl QueryResult {
+    /// Writes the result as CSV: a header row of column names, then one record per row.
+    ///
+    /// Records end in CRLF. Fields containing a comma, quote or line break are quoted, with
+    /// quotes doubled. NULL is written as an empty field.
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_record(w, self.columns.iter().map(String::as_str))?;
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(csv_field).collect();
+            write_record(w, fields.iter().map(String::as_str))?;
+        }
+        Ok(())
+    }
+}
+
+fn write_record<'a, W: Write>(w: &mut W, fields: impl Iterator<Item = &'a str>) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            w.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            w.write_all(field.as_bytes())?;
+        }
+    }
+    w.write_all(b"\r\n")
+}
+
+impl Executor {
+    /// Inserts the records of a CSV document into `table`, returning how many were inserted.
+    ///
+    /// The first record is a header that must name the table's columns in table order. Fields
+    /// are converted to the column types; an unquoted empty field is NULL, while `""` is an
+    /// empty string. Every record is converted before any is inserted, but an insert that fails
+    /// (such as a duplicate key) leaves the records before it in the table. Errors name the line
+    /// the offending record starts on.
+    pub fn copy_from_csv<R: Read>(&mut self, table: &str, mut reader: R) -> Result<usize, String> {
+        let mut input = String::new();
+        reader
+            .read_to_string(&mut input)
+            .map_err(|e| format!("Failed to read CSV: {}", e))?;
+        let mut records = parse_records(&input)?.into_iter();
+
+        let schema = self
+            .catalog()
+            .get_table(table)
+            .ok_or_else(|| format!("Table '{}' does not exist", table))?;
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        let header_matches = records.next().is_some_and(|(_, header)| {
+            header.len() == names.len()
+                && header
+                    .iter()
+                    .zip(&names)
+                    .all(|(field, name)| field.text.eq_ignore_ascii_case(name))
+        });
+        if !header_matches {
+            return Err(format!(
+                "CSV header must list the columns of '{}' in table order: {}",
+                schema.name,
+                names.join(", ")
+            ));
+        }
+
+        let rows = records
+            .map(|(line, fields)| {
+                convert_record(schema, &fields)
+                    .map(|row| (line, row))
+                    .map_err(|e| format!("Line {}: {}", line, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count = rows.len();
+        for (line, row) in rows {
+            self.insert_row(table, row)
+                .map_err(|e| format!("Line {}: {}", line, e))?;
+        }
+        Ok(count)
+    }
+}
+
+/// One field of a CSV record.
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+/// Splits a CSV document into records, each with the line number it starts on.
+fn parse_records(input: &str) -> Result<Vec<(usize, Vec<Field>)>, String> {
+    let mut records = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start_line = line;
+        let mut fields = Vec::new();
+        loop {
+            let mut field = Field {
+                text: String::new(),
+                quoted: false,
+            };
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                field.quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.text.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.text.push(c);
+                        }
+                        None => {
+                            return Err(format!("Line {}: unterminated quoted field", start_line))
+                        }
+                    }
+                }
+            } else {
+                while let Some(&c) = chars.peek() {
+                    if matches!(c, ',' | '\r' | '\n') {
+                        break;
+                    }
+                    if c == '"' {
+                        return Err(format!("Line {}: unexpected quote in unquoted field", line));
+                    }
+                    field.text.push(c);
+                    chars.next();
+                }
+            }
+            fields.push(field);
+
+            match chars.next() {
+                Some(',') => continue,
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some('\n') | None => {}
+                Some(c) => {
+                    return Err(format!(
+                        "Line {}: unexpected '{}' after quoted field",
+                        line, c
+                    ))
+                }
+            }
+            line += 1;
+            break;
+        }
+        records.push((start_line, fields));
+    }
+    Ok(records)
+}
+
+/// Converts the fields of a record to the column types of `schema`.
+fn convert_record(schema: &TableSchema, fields: &[Field]) -> Result<Vec<Value>, String> {
+    if fields.len() != schema.columns.len() {
+        return Err(format!(
+            "expected {} fields, got {}",
+            schema.columns.len(),
+            fields.len()
+        ));
+    }
+    schema
+        .columns
+        .iter()
+        .zip(fields)
+        .map(|(column, field)| {
+            if field.text.is_empty() && !field.quoted {
+                return Ok(Value::Null);
+            }
+            let text = field.text.as_str();
+            Value::Text(text.to_string())
+                .coerce_to(&column.data_type)
+                .map_err(|_| {
+                    format!(
+                        "cannot convert '{}' to {} for column '{}'",
+                        text,
+                        column.data_type.to_sql(),
+                        column.name
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Renders a value as an unquoted CSV field.
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Text(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Blob(bytes) => bytes.iter().map(|b| format!("{:02X}", b)).collect(),
+        #[cfg(feature = "json")]
+        Value::Json(json) => json.to_string(),
+        #[cfg(feature = "uuid")]
+        Value::Uuid(bytes) => uuid_string(bytes),
+        Value::Null | Value::Default => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::DataType;
+
+    /// Tests that the header and rows are written with RFC 4180 quoting and NULL as empty.
+    #[test]
+    fn test_write_csv() {
+        let result = QueryResult {
+            columns: vec!["id".to_string(), "note, text".to_string(), "ok".to_string()],
+            rows: vec![
+                vec![
+                    Value::Integer(1),
+                    Value::Text("plain".to_string()),
+                    Value::Boolean(true),
+                ],
+                vec![
+                    Value::Integer(2),
+                    Value::Text("say \"hi\"\nbye".to_string()),
+                    Value::Null,
+                ],
+            ],
+            rows_affected: 0,
+        };
+
+        let mut csv = Vec::new();
+        result.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,\"note, text\",ok\r\n1,plain,true\r\n2,\"say \"\"hi\"\"\nbye\",\r\n"
+        );
+    }
+
+    fn executor(test_db: &str) -> Executor {
+        use crate::ast::ColumnDef;
+        use crate::buffer_pool::BufferPool;
+        use crate::storage::StorageEngine;
+        use std::sync::Arc;
+
+        let _ = std::fs::remove_file(test_db);
+        let buffer_pool = Arc::new(BufferPool::new(10, StorageEngine::new(test_db).unwrap()));
+        let mut executor = Executor::new(buffer_pool);
+        let column = |name: &str, data_type| ColumnDef {
+            name: name.to_string(),
+            data_type,
+            not_null: false,
+            default: None,
+        };
+        executor
+            .create_table(TableSchema::new(
+                "items",
+                vec![
+                    column("id", DataType::Integer),
+                    column("name", DataType::Text),
+                    column("price", DataType::Float),
+                ],
+            ))
+            .unwrap();
+        executor
+    }
+
+    /// Tests that imported records are converted like any text value, inserted and queryable.
+    #[test]
+    fn test_copy_from_csv() {
+        let test_db = "test_copy_from_csv.db";
+        let mut executor = executor(test_db);
+
+        let csv = "id,name,price\r\n2,\"pen, blue\",1.5\n1,\"\",\n3,\"multi\nline\", 2 \n";
+        assert_eq!(executor.copy_from_csv("items", csv.as_bytes()), Ok(3));
+
+        let query = crate::parser::Parser::new("SELECT * FROM items")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let result = executor.execute(&query).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(1), Value::Text(String::new()), Value::Null],
+                vec![
+                    Value::Integer(2),
+                    Value::Text("pen, blue".to_string()),
+                    Value::Float(1.5)
+                ],
+                vec![
+                    Value::Integer(3),
+                    Value::Text("multi\nline".to_string()),
+                    Value::Float(2.0)
+                ],
+            ]
+        );
+
+        let _ = std::fs::remove_file(test_db);
+    }
+
+    /// Tests that conversion errors name the line the record starts on and insert nothing.
+    #[test]
+    fn test_copy_from_csv_errors() {
+        let test_db = "test_copy_from_csv_errors.db";
+        let mut executor = executor(test_db);
+
+        let csv = "id,name,price\n1,\"two\nlines\",1\nx,b,2\n";
+        assert_eq!(
+            executor.copy_from_csv("items", csv.as_bytes()),
+            Err("Line 4: cannot convert 'x' to INTEGER for column 'id'".to_string())
+        );
+        let query = crate::parser::Parser::new("SELECT * FROM items")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(executor.execute(&query).unwrap().rows.is_empty());
+        assert_eq!(
+            executor.copy_from_csv("items", "id,price,name\n".as_bytes()),
+            Err(
+                "CSV header must list the columns of 'items' in table order: id, name, price"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            executor.copy_from_csv("items", "id,name,price\n1,\"open,2\n".as_bytes()),
+            Err("Line 2: unterminated quoted field".to_string())
+        );
+        assert_eq!(
+            executor.copy_from_csv("items", "id,name,price\n1,a,NaN\n".as_bytes()),
+            Err("Line 2: cannot convert 'NaN' to FLOAT for column 'price'".to_string())
+        );
+        assert_eq!(
+            executor.copy_from_csv("items", "id,name,price\n".as_bytes()),
+            Ok(0)
+        );
+
+        let _ = std::fs::remove_file(test_db);
+    }
+}
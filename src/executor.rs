@@ -1,45 +1,2227 @@
-// A file for preparing for the future. I hope you can get this far.
+//! Executes parsed queries against tables held by the executor.
+//!
+//! Each table keeps its rows in an in-memory heap and indexes them with a B+ Tree keyed by the
+//! table's first column, which must be an INTEGER. The tree maps each key to the row's slot in
+//! the heap.
 
-use crate::ast::{ASTNode, Statement};
-use crate::storage::Storage;
-use crate::transaction::TransactionManager;
+use crate::analyzer::{Analyzer, DEFAULT_OUTSIDE_TARGET};
+use crate::ast::{
+    BinaryOperator, DataType, Expression, Insert, Ordering, Query, Select, SelectItem, SortOrder,
+    Value,
+};
+use crate::buffer_pool::BufferPool;
+use crate::catalog::{Catalog, TableSchema};
+use crate::index::{BPlusTree, BPlusTreeIter, ORDER};
+use crate::plan::{sort_expression, PlanNode, Planner};
+use crate::storage::Key;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
 
-// Query execution engine
+/// The rows produced by a query, or the number of rows a statement changed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub rows_affected: usize,
+}
+
+/// How comparisons treat a text value compared with a number, as in `'5' = 5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoercionPolicy {
+    /// Comparing text with a number is a type error.
+    #[default]
+    Strict,
+    /// Text holding a number is compared as that number; other text is still an error.
+    Lenient,
+}
+
+/// The rows flowing out of a plan node, borrowed from the table where possible.
+type Rows<'a> = Box<dyn Iterator<Item = Result<Cow<'a, [Value]>, String>> + 'a>;
+
+/// A table's rows and the index over its key column.
+struct TableData {
+    schema: TableSchema,
+    index: BPlusTree,
+    rows: Vec<Vec<Value>>,
+    // Index entries read by scans, so tests can check that a scan stopped early
+    rows_scanned: AtomicU64,
+}
+
+/// The changes made so far by an `Executor::execute_all` batch, kept so they can be undone.
+#[derive(Default)]
+struct Batch {
+    // Lowercased names of the tables the batch created
+    created_tables: Vec<String>,
+    // Row counts by lowercased table name, taken before the batch first inserted into the table
+    row_counts: HashMap<String, usize>,
+}
+
+/// Query execution engine
 pub struct Executor {
-    storage: Storage,
-    tx_manager: TransactionManager,
+    catalog: Catalog,
+    tables: HashMap<String, TableData>,
+    buffer_pool: Arc<BufferPool>,
+    coercion: CoercionPolicy,
+    batch: Option<Batch>,
 }
 
 impl Executor {
-    pub fn new(storage: Storage, tx_manager: TransactionManager) -> Self {
+    /// Creates an executor with no tables.
+    pub fn new(buffer_pool: Arc<BufferPool>) -> Self {
         Executor {
-            storage,
-            tx_manager,
+            catalog: Catalog::new(),
+            tables: HashMap::new(),
+            buffer_pool,
+            coercion: CoercionPolicy::default(),
+            batch: None,
+        }
+    }
+
+    /// Returns how comparisons between text and numbers are handled.
+    pub fn coercion_policy(&self) -> CoercionPolicy {
+        self.coercion
+    }
+
+    /// Sets how comparisons between text and numbers are handled.
+    pub fn set_coercion_policy(&mut self, coercion: CoercionPolicy) {
+        self.coercion = coercion;
+    }
+
+    /// Returns the catalog describing the executor's tables.
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Creates an empty table. Its first column is the key and must be an INTEGER.
+    pub fn create_table(&mut self, schema: TableSchema) -> Result<(), String> {
+        match schema.columns.first() {
+            Some(column) if column.data_type == DataType::Integer => {}
+            _ => {
+                return Err(format!(
+                    "The first column of table '{}' must be an INTEGER key",
+                    schema.name
+                ))
+            }
+        }
+
+        self.catalog.add_table(schema.clone())?;
+        let index = BPlusTree::new(Arc::clone(&self.buffer_pool), ORDER)?;
+        if let Some(batch) = &mut self.batch {
+            batch.created_tables.push(schema.name.to_lowercase());
+        }
+        self.tables.insert(
+            schema.name.to_lowercase(),
+            TableData {
+                schema,
+                index,
+                rows: Vec::new(),
+                rows_scanned: AtomicU64::new(0),
+            },
+        );
+        Ok(())
+    }
+
+    /// Executes a single query.
+    pub fn execute(&mut self, query: &Query) -> Result<QueryResult, String> {
+        match query {
+            Query::Select(select) => self.execute_select(select),
+            Query::Insert(insert) => self.execute_insert(insert),
+            Query::Update(_) => Err("UPDATE is not supported by the executor yet".to_string()),
+            Query::DropTable { .. } => {
+                Err("DROP TABLE is not supported by the executor yet".to_string())
+            }
+            Query::Explain(query) => self.explain(query),
+            Query::ShowTables => Ok(self.show_tables()),
+            Query::ShowColumns { table } | Query::Describe { table } => self.show_columns(table),
+            Query::CreateTable { name, columns } => {
+                self.create_table(TableSchema::new(name, columns.clone()))?;
+                Ok(QueryResult::default())
+            }
+            Query::Begin | Query::Commit | Query::Rollback => {
+                Err(format!("{} is not supported yet", query.to_sql()))
+            }
+        }
+    }
+
+    /// Executes the queries in order as one transaction and returns their results.
+    ///
+    /// If a query fails, the tables created and rows inserted by the queries before it are
+    /// removed again, and its error is returned with its position in the batch. Only those
+    /// changes can be undone, so a batch holding a statement that could change rows or tables
+    /// some other way, such as UPDATE or DROP TABLE, is rejected before any of it runs.
+    pub fn execute_all(&mut self, queries: Vec<Query>) -> Result<Vec<QueryResult>, String> {
+        if let Some(i) = queries.iter().position(|query| !Self::can_undo(query)) {
+            return Err(format!(
+                "Statement {} cannot be undone, so it cannot run in a batch",
+                i + 1
+            ));
+        }
+
+        self.batch = Some(Batch::default());
+        let results: Result<Vec<QueryResult>, String> = queries
+            .iter()
+            .enumerate()
+            .map(|(i, query)| {
+                self.execute(query)
+                    .map_err(|e| format!("Statement {} failed: {}", i + 1, e))
+            })
+            .collect();
+        let batch = self.batch.take().unwrap_or_default();
+        if results.is_err() {
+            self.undo(batch)?;
+        }
+        results
+    }
+
+    /// Returns whether `undo` can revert what `query` does. It only drops created tables and
+    /// cuts tables back to their earlier row counts, so besides reads it handles CREATE TABLE
+    /// and INSERT, which only ever appends rows.
+    fn can_undo(query: &Query) -> bool {
+        match query {
+            Query::Select(_)
+            | Query::Insert(_)
+            | Query::CreateTable { .. }
+            | Query::Explain(_)
+            | Query::ShowTables
+            | Query::ShowColumns { .. }
+            | Query::Describe { .. } => true,
+            Query::Update(_)
+            | Query::DropTable { .. }
+            | Query::Begin
+            | Query::Commit
+            | Query::Rollback => false,
+        }
+    }
+
+    /// Reverts the changes recorded in a batch. Rows are only ever appended and keys are
+    /// unique, so cutting each table back to its earlier row count undoes its inserts.
+    /// `execute_all` only lets through statements for which that holds; see `can_undo`.
+    fn undo(&mut self, batch: Batch) -> Result<(), String> {
+        for (name, count) in batch.row_counts {
+            if let Some(table) = self.tables.get_mut(&name) {
+                table.rows.truncate(count);
+                table
+                    .index
+                    .retain(|_, &slot| slot < count as u64)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        for name in batch.created_tables {
+            self.tables.remove(&name);
+            self.catalog.remove_table(&name);
+        }
+        Ok(())
+    }
+
+    /// Lists the catalog's tables by name.
+    fn show_tables(&self) -> QueryResult {
+        QueryResult {
+            columns: vec!["table".to_string()],
+            rows: self
+                .catalog
+                .tables()
+                .into_iter()
+                .map(|schema| vec![Value::Text(schema.name.clone())])
+                .collect(),
+            rows_affected: 0,
+        }
+    }
+
+    /// Describes each column of a table: its name, type, whether it accepts NULL and its
+    /// default.
+    fn show_columns(&self, table: &str) -> Result<QueryResult, String> {
+        let schema = &self.table(table)?.schema;
+        Ok(QueryResult {
+            columns: ["column", "type", "nullable", "default"]
+                .map(String::from)
+                .to_vec(),
+            rows: schema
+                .columns
+                .iter()
+                .map(|column| {
+                    vec![
+                        Value::Text(column.name.clone()),
+                        Value::Text(column.data_type.to_sql().to_string()),
+                        Value::Boolean(!column.not_null),
+                        column.default.clone().unwrap_or(Value::Null),
+                    ]
+                })
+                .collect(),
+            rows_affected: 0,
+        })
+    }
+
+    /// Describes how a query would be executed, one operator per row with the outermost first.
+    fn explain(&self, query: &Query) -> Result<QueryResult, String> {
+        let lines = match query {
+            Query::Select(select) => Planner::new(&self.catalog).plan(select)?.explain(),
+            Query::Insert(insert) => {
+                self.table(&insert.table.name)?;
+                let mut lines = vec![format!("Insert {}", insert.table.name)];
+                if let Some(select) = &insert.select {
+                    let plan = Planner::new(&self.catalog).plan(select)?;
+                    lines.extend(plan.explain().into_iter().map(|line| format!("  {}", line)));
+                }
+                lines
+            }
+            Query::Explain(_) => return Err("EXPLAIN cannot be nested".to_string()),
+            Query::Update(_)
+            | Query::Begin
+            | Query::Commit
+            | Query::Rollback
+            | Query::ShowTables
+            | Query::ShowColumns { .. }
+            | Query::Describe { .. }
+            | Query::CreateTable { .. }
+            | Query::DropTable { .. } => return Err(format!("Cannot EXPLAIN {}", query.to_sql())),
+        };
+
+        Ok(QueryResult {
+            columns: vec!["plan".to_string()],
+            rows: lines
+                .into_iter()
+                .map(|line| vec![Value::Text(line)])
+                .collect(),
+            rows_affected: 0,
+        })
+    }
+
+    fn table(&self, name: &str) -> Result<&TableData, String> {
+        self.tables
+            .get(&name.to_lowercase())
+            .ok_or_else(|| format!("Table '{}' does not exist", name))
+    }
+
+    /// Type-checks a row and inserts it into `table`.
+    pub(crate) fn insert_row(&mut self, table: &str, row: Vec<Value>) -> Result<(), String> {
+        self.table_mut(table)?.insert_row(row)
+    }
+
+    fn table_mut(&mut self, name: &str) -> Result<&mut TableData, String> {
+        self.tables
+            .get_mut(&name.to_lowercase())
+            .ok_or_else(|| format!("Table '{}' does not exist", name))
+    }
+
+    // Executing an INSERT statement
+    fn execute_insert(&mut self, insert: &Insert) -> Result<QueryResult, String> {
+        let rows = match (&insert.values, &insert.select) {
+            (Some(values), _) => vec![values.clone()],
+            (None, Some(select)) => {
+                Analyzer::new(&self.catalog).check_insert(insert)?;
+                self.execute_select(select)?.rows
+            }
+            (None, None) => return Err("INSERT has neither VALUES nor SELECT".to_string()),
+        };
+
+        let name = insert.table.name.to_lowercase();
+        if let (Some(batch), Some(table)) = (&mut self.batch, self.tables.get(&name)) {
+            batch.row_counts.entry(name).or_insert(table.rows.len());
+        }
+        let table = self.table_mut(&insert.table.name)?;
+        // The listed columns may come in any order; each value goes to its column by name
+        let mut positions = Vec::with_capacity(insert.columns.len());
+        for name in &insert.columns {
+            let position = table.schema.column_index(name).ok_or_else(|| {
+                format!(
+                    "Column '{}' does not exist in table '{}'",
+                    name, table.schema.name
+                )
+            })?;
+            if positions.contains(&position) {
+                return Err(format!("Column '{}' is listed more than once", name));
+            }
+            positions.push(position);
+        }
+
+        let rows_affected = rows.len();
+        for values in rows {
+            if values.len() != positions.len() {
+                return Err(format!(
+                    "INSERT has {} columns but {} values",
+                    positions.len(),
+                    values.len()
+                ));
+            }
+            // Columns left out, like those given DEFAULT, get their default or NULL
+            let mut row: Vec<Value> = table
+                .schema
+                .columns
+                .iter()
+                .map(|column| column.default.clone().unwrap_or(Value::Null))
+                .collect();
+            for (value, &position) in values.into_iter().zip(&positions) {
+                if value != Value::Default {
+                    row[position] = value;
+                }
+            }
+            table.insert_row(row)?;
+        }
+        Ok(QueryResult {
+            rows_affected,
+            ..QueryResult::default()
+        })
+    }
+
+    // Executing a SELECT statement
+    fn execute_select(&self, select: &Select) -> Result<QueryResult, String> {
+        if select.with.is_some() {
+            return Err("WITH is not supported by the executor yet".to_string());
+        }
+        if select.distinct {
+            return Err("SELECT DISTINCT is not supported by the executor yet".to_string());
+        }
+        if !select.joins.is_empty() {
+            return Err("JOIN is not supported by the executor yet".to_string());
+        }
+        if select.table.subquery.is_some() {
+            return Err("Subqueries in FROM are not supported by the executor yet".to_string());
+        }
+        if select.table.sample.is_some() {
+            return Err("TABLESAMPLE is not supported by the executor yet".to_string());
+        }
+        if select.group_by.is_some() || select.having.is_some() {
+            return Err("GROUP BY is not supported by the executor yet".to_string());
+        }
+
+        let table = self.table(&select.table.name)?;
+        // Qualified columns name the table by its alias when it has one, which hides its name
+        let schema = match &select.table.alias {
+            Some(alias) => Cow::Owned(TableSchema {
+                name: alias.clone(),
+                ..table.schema.clone()
+            }),
+            None => Cow::Borrowed(&table.schema),
+        };
+        // Checked up front, since the planner may answer a condition from the index without
+        // evaluating it, and an empty table evaluates nothing at all
+        let expressions = select
+            .columns
+            .iter()
+            .map(|item| &item.expression)
+            .chain(&select.where_clause)
+            .chain(
+                select
+                    .order_by
+                    .iter()
+                    .flatten()
+                    .map(|ordering| sort_expression(ordering, &select.columns)),
+            );
+        for expression in expressions {
+            check_expression(expression, &schema)?;
+        }
+        let outputs = output_columns(&select.columns, &schema)?;
+        let columns = outputs.iter().map(|(name, _)| name.clone()).collect();
+
+        let plan = Planner::new(&self.catalog).plan(select)?;
+        Ok(QueryResult {
+            columns,
+            rows: table.output_rows(&plan, &schema, &outputs, self.coercion)?,
+            rows_affected: 0,
+        })
+    }
+
+    /// Returns how many rows table scans have read since the executor was created.
+    pub fn rows_scanned(&self) -> u64 {
+        self.tables
+            .values()
+            .map(|table| table.rows_scanned.load(AtomicOrdering::Relaxed))
+            .sum()
+    }
+}
+
+impl TableData {
+    /// Produces the result rows of a plan's root node.
+    fn output_rows(
+        &self,
+        node: &PlanNode,
+        schema: &TableSchema,
+        outputs: &[(String, Output)],
+        coercion: CoercionPolicy,
+    ) -> Result<Vec<Vec<Value>>, String> {
+        Ok(match node {
+            PlanNode::IndexCount { range: None, .. } => {
+                vec![vec![Value::Integer(self.index.len() as i64)]]
+            }
+            PlanNode::IndexCount {
+                range: Some(range), ..
+            } => {
+                let count = self
+                    .index
+                    .count_range(*range.start(), *range.end())
+                    .map_err(|e| e.to_string())?;
+                vec![vec![Value::Integer(count as i64)]]
+            }
+            PlanNode::Aggregate { input, aggregates } => {
+                let rows = self.rows(input, schema, coercion)?;
+                let value =
+                    match aggregates {
+                        [SelectItem {
+                            expression: column, ..
+                        }] if is_count(column) => {
+                            Value::Integer(self.count(column, schema, rows, coercion)? as i64)
+                        }
+                        [SelectItem {
+                            expression: column, ..
+                        }] if is_string_agg(column) => {
+                            self.string_agg(column, schema, rows, coercion)?
+                        }
+                        _ => return Err(
+                            "Only a single COUNT or STRING_AGG is supported by the executor yet"
+                                .to_string(),
+                        ),
+                    };
+                vec![vec![value]]
+            }
+            PlanNode::Project { input, .. } => {
+                let mut rows = Vec::new();
+                for row in self.rows(input, schema, coercion)? {
+                    rows.push(project(outputs, schema, &row?, coercion)?);
+                }
+                rows
+            }
+            // The planner only limits above an aggregate, whose single row is already built
+            PlanNode::Limit {
+                input,
+                limit,
+                offset,
+            } => self
+                .output_rows(input, schema, outputs, coercion)?
+                .into_iter()
+                .skip(*offset as usize)
+                .take(limit.map_or(usize::MAX, |limit| limit as usize))
+                .collect(),
+            other => return Err(format!("Cannot execute a plan rooted at {:?}", other)),
+        })
+    }
+
+    /// Converts a row's values to their column types and stores it, indexing it by its key
+    /// column.
+    fn insert_row(&mut self, mut row: Vec<Value>) -> Result<(), String> {
+        for (column, value) in self.schema.columns.iter().zip(&mut row) {
+            *value = value
+                .coerce_to(&column.data_type)
+                .map_err(|e| format!("{} for column '{}'", e, column.name))?;
+            if column.not_null && *value == Value::Null {
+                return Err(format!("Column '{}' cannot be NULL", column.name));
+            }
+        }
+
+        let key = match row.first() {
+            Some(Value::Integer(i)) => {
+                Key::try_from(*i).map_err(|_| format!("Key {} is out of range for the index", i))?
+            }
+            _ => {
+                return Err(format!(
+                    "Column '{}' requires a key",
+                    self.schema.columns[0].name
+                ))
+            }
+        };
+
+        self.index.insert(key, self.rows.len() as u64)?;
+        self.rows.push(row);
+        Ok(())
+    }
+
+    /// Iterates over the index entries in key order, within `range` if one is given.
+    fn entries(
+        &self,
+        range: Option<RangeInclusive<Key>>,
+    ) -> impl Iterator<Item = Result<(Key, u64), String>> + '_ {
+        let entries: BPlusTreeIter = match range {
+            Some(range) => self.index.range_iter(*range.start(), *range.end()),
+            None => self.index.iter(),
+        };
+        entries.inspect(|_| {
+            self.rows_scanned.fetch_add(1, AtomicOrdering::Relaxed);
+        })
+    }
+
+    /// Iterates over the rows in key order, within `range` if one is given.
+    fn scan(
+        &self,
+        range: Option<RangeInclusive<Key>>,
+    ) -> impl Iterator<Item = Result<&Vec<Value>, String>> + '_ {
+        self.entries(range)
+            .map(move |entry| entry.map(|(_, slot)| &self.rows[slot as usize]))
+    }
+
+    /// Produces the rows of a plan node that passes table rows through, such as a scan, filter
+    /// or sort.
+    fn rows<'a>(
+        &'a self,
+        node: &'a PlanNode,
+        schema: &'a TableSchema,
+        coercion: CoercionPolicy,
+    ) -> Result<Rows<'a>, String> {
+        Ok(match node {
+            PlanNode::Scan { range, .. } => Box::new(
+                self.scan(range.clone())
+                    .map(|row| row.map(|row| Cow::Borrowed(row.as_slice()))),
+            ),
+            // A query that reads nothing but the key is answered from the index alone
+            PlanNode::IndexScan { range, .. } => {
+                Box::new(self.key_scan(range.clone()).map(|row| row.map(Cow::Owned)))
+            }
+            PlanNode::Filter { input, conditions } => {
+                Box::new(self.rows(input, schema, coercion)?.filter_map(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(error) => return Some(Err(error)),
+                    };
+                    match matches(conditions, schema, &row, coercion) {
+                        Ok(true) => Some(Ok(row)),
+                        Ok(false) => None,
+                        Err(error) => Some(Err(error)),
+                    }
+                }))
+            }
+            PlanNode::Sort {
+                input,
+                order_by,
+                columns,
+            } => {
+                for ordering in order_by.iter() {
+                    if let Some(collation) = &ordering.collation {
+                        check_collation(collation)?;
+                    }
+                }
+                let mut rows = Vec::new();
+                let mut sort_keys = Vec::new();
+                for row in self.rows(input, schema, coercion)? {
+                    let row = row?;
+                    let key = order_by
+                        .iter()
+                        .map(|ordering| {
+                            evaluate(sort_expression(ordering, columns), schema, &row, coercion)
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    sort_keys.push(key);
+                    rows.push(row.into_owned());
+                }
+                let rows = sort_rows(rows, sort_keys, order_by, coercion)?;
+                Box::new(rows.into_iter().map(|row| Ok(Cow::Owned(row))))
+            }
+            PlanNode::Limit {
+                input,
+                limit,
+                offset,
+            } => {
+                let rows = self.rows(input, schema, coercion)?.skip(*offset as usize);
+                match limit {
+                    Some(limit) => Box::new(rows.take(*limit as usize)),
+                    None => Box::new(rows),
+                }
+            }
+            PlanNode::Join { .. } => {
+                return Err("JOIN is not supported by the executor yet".to_string())
+            }
+            PlanNode::IndexCount { .. } | PlanNode::Project { .. } | PlanNode::Aggregate { .. } => {
+                return Err(format!("{:?} does not produce table rows", node))
+            }
+        })
+    }
+
+    /// Counts the rows for a COUNT aggregate.
+    fn count(
+        &self,
+        count: &Expression,
+        schema: &TableSchema,
+        rows: Rows,
+        coercion: CoercionPolicy,
+    ) -> Result<usize, String> {
+        let mut total = 0;
+        for row in rows {
+            if counts_row(count, schema, &row?, coercion)? {
+                total += 1;
+            }
+        }
+        Ok(total)
+    }
+
+    /// Evaluates `STRING_AGG(value [, separator] [ORDER BY ...])` over `rows`. NULL values are
+    /// skipped, and the result is NULL if every value is.
+    fn string_agg(
+        &self,
+        aggregate: &Expression,
+        schema: &TableSchema,
+        rows: Rows,
+        coercion: CoercionPolicy,
+    ) -> Result<Value, String> {
+        let Expression::Function {
+            args,
+            filter,
+            order_by,
+            ..
+        } = aggregate
+        else {
+            return Err("STRING_AGG must be a function call".to_string());
+        };
+        for ordering in order_by {
+            if let Some(collation) = &ordering.collation {
+                check_collation(collation)?;
+            }
+        }
+
+        let mut parts = Vec::new();
+        let mut sort_keys = Vec::new();
+        for row in rows {
+            let row = row?;
+            if !is_true(filter.as_deref(), schema, &row, coercion, "FILTER")? {
+                continue;
+            }
+            let value = match evaluate(&args[0], schema, &row, coercion)? {
+                Value::Null => continue,
+                Value::Text(text) => text,
+                other => other.to_sql(),
+            };
+            let separator = match args.get(1) {
+                Some(separator) => match evaluate(separator, schema, &row, coercion)? {
+                    Value::Text(separator) => separator,
+                    other => {
+                        return Err(format!(
+                            "STRING_AGG separator must be text, got {}",
+                            other.to_sql()
+                        ))
+                    }
+                },
+                None => ",".to_string(),
+            };
+            if !order_by.is_empty() {
+                let key = order_by
+                    .iter()
+                    .map(|ordering| evaluate(&ordering.expression, schema, &row, coercion))
+                    .collect::<Result<Vec<_>, _>>()?;
+                sort_keys.push(key);
+            }
+            parts.push(vec![Value::Text(separator), Value::Text(value)]);
+        }
+        if !order_by.is_empty() {
+            parts = sort_rows(parts, sort_keys, order_by, coercion)?;
+        }
+
+        // Each value after the first is preceded by the separator evaluated for its row
+        let mut result: Option<String> = None;
+        for part in parts {
+            let [Value::Text(separator), Value::Text(value)] = part.as_slice() else {
+                unreachable!("STRING_AGG parts are text pairs");
+            };
+            match &mut result {
+                Some(result) => {
+                    result.push_str(separator);
+                    result.push_str(value);
+                }
+                None => result = Some(value.clone()),
+            }
+        }
+        Ok(result.map_or(Value::Null, Value::Text))
+    }
+
+    /// Iterates over the keys in order, as rows holding only the key. The other columns are
+    /// NULL placeholders, so this is only for queries that read nothing but the key.
+    fn key_scan(
+        &self,
+        range: Option<RangeInclusive<Key>>,
+    ) -> impl Iterator<Item = Result<Vec<Value>, String>> + '_ {
+        self.entries(range).map(move |entry| {
+            entry.map(|(key, _)| {
+                let mut row = vec![Value::Null; self.schema.columns.len()];
+                row[0] = Value::Integer(key as i64);
+                row
+            })
+        })
+    }
+}
+
+/// Evaluates the ANDed conditions of a WHERE clause. Only rows for which every condition
+/// is TRUE match. As with AND, a FALSE condition decides without evaluating the rest, but
+/// a NULL one does not, so a later condition that fails still aborts the query.
+fn matches(
+    conditions: &[&Expression],
+    schema: &TableSchema,
+    row: &[Value],
+    coercion: CoercionPolicy,
+) -> Result<bool, String> {
+    let mut matched = true;
+    for condition in conditions {
+        match evaluate(condition, schema, row, coercion)? {
+            Value::Boolean(true) => {}
+            Value::Boolean(false) => return Ok(false),
+            Value::Null => matched = false,
+            other => {
+                return Err(format!(
+                    "WHERE clause must be a boolean expression, got {}",
+                    other.to_sql()
+                ))
+            }
+        }
+    }
+    Ok(matched)
+}
+
+/// Evaluates an optional condition, treating a missing one as TRUE and NULL as not TRUE.
+fn is_true(
+    condition: Option<&Expression>,
+    schema: &TableSchema,
+    row: &[Value],
+    coercion: CoercionPolicy,
+    clause: &str,
+) -> Result<bool, String> {
+    let Some(condition) = condition else {
+        return Ok(true);
+    };
+    match evaluate(condition, schema, row, coercion)? {
+        Value::Boolean(b) => Ok(b),
+        Value::Null => Ok(false),
+        other => Err(format!(
+            "{} clause must be a boolean expression, got {}",
+            clause,
+            other.to_sql()
+        )),
+    }
+}
+
+fn is_count(expr: &Expression) -> bool {
+    matches!(expr, Expression::Function { name, .. } if name.eq_ignore_ascii_case("COUNT"))
+}
+
+fn is_string_agg(expr: &Expression) -> bool {
+    matches!(expr, Expression::Function { name, .. }
+        if name.eq_ignore_ascii_case("STRING_AGG") || name.eq_ignore_ascii_case("GROUP_CONCAT"))
+}
+
+/// Checks that the executor can sort by `collation`. Only byte-wise collations are built in,
+/// and they are what text comparison already does.
+fn check_collation(collation: &str) -> Result<(), String> {
+    if collation.eq_ignore_ascii_case("C") || collation.eq_ignore_ascii_case("BINARY") {
+        Ok(())
+    } else {
+        Err(format!("Unsupported collation '{}'", collation))
+    }
+}
+
+/// Sorts rows by their precomputed sort keys. NULLs sort after every other value.
+fn sort_rows(
+    rows: Vec<Vec<Value>>,
+    sort_keys: Vec<Vec<Value>>,
+    order_by: &[Ordering],
+    coercion: CoercionPolicy,
+) -> Result<Vec<Vec<Value>>, String> {
+    let mut keyed: Vec<_> = sort_keys.into_iter().zip(rows).collect();
+    let mut error = None;
+    keyed.sort_by(|(a, _), (b, _)| {
+        for ((left, right), ordering) in a.iter().zip(b).zip(order_by) {
+            let result = match compare_values(left, right, coercion) {
+                Ok(Some(result)) => result,
+                Ok(None) => match (left, right) {
+                    (Value::Null, Value::Null) => cmp::Ordering::Equal,
+                    (Value::Null, _) => cmp::Ordering::Greater,
+                    _ => cmp::Ordering::Less,
+                },
+                Err(e) => {
+                    error.get_or_insert(e);
+                    cmp::Ordering::Equal
+                }
+            };
+            let result = match ordering.direction {
+                SortOrder::Ascending => result,
+                SortOrder::Descending => result.reverse(),
+            };
+            if result != cmp::Ordering::Equal {
+                return result;
+            }
         }
+        cmp::Ordering::Equal
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(keyed.into_iter().map(|(_, row)| row).collect()),
     }
+}
 
-    // Execute AST node
-    pub fn execute(&mut self, ast: ASTNode) -> Result<(), String> {
-        match ast {
-            ASTNode::Statement(stmt) => self.execute_statement(stmt),
+/// Returns whether COUNT counts `row`: COUNT(*) counts every row, COUNT(expr) only non-NULL values.
+/// A FILTER clause skips rows for which its condition is not TRUE.
+fn counts_row(
+    count: &Expression,
+    schema: &TableSchema,
+    row: &[Value],
+    coercion: CoercionPolicy,
+) -> Result<bool, String> {
+    match count {
+        Expression::Function { args, filter, .. } => {
+            if !is_true(filter.as_deref(), schema, row, coercion, "FILTER")? {
+                return Ok(false);
+            }
+            match args.as_slice() {
+                [Expression::Asterisk] => Ok(true),
+                [arg] => Ok(evaluate(arg, schema, row, coercion)? != Value::Null),
+                _ => Err("COUNT takes exactly one argument".to_string()),
+            }
         }
+        _ => Ok(true),
     }
+}
+
+/// Where an output column's values come from.
+pub(crate) enum Output<'a> {
+    Column(usize),
+    Expression(&'a Expression),
+}
 
-    // Executing a statement
-    fn execute_statement(&mut self, stmt: Statement) -> Result<(), String> {
-        match stmt {
-            Statement::CreateTable { name, columns } => {
-                println!("Creates a table '{}'.", name);
-                Ok(())
+/// Expands a select list into the name and expression of each output column. `*` expands
+/// to the table's columns, minus any EXCEPT columns and with any REPLACE expressions swapped in.
+/// Other items are named by their alias, or else by their SQL.
+pub(crate) fn output_columns<'a>(
+    columns: &'a [SelectItem],
+    schema: &TableSchema,
+) -> Result<Vec<(String, Output<'a>)>, String> {
+    let mut outputs = Vec::new();
+    for SelectItem { expression, alias } in columns {
+        if let Some(alias) = alias {
+            outputs.push((alias.clone(), Output::Expression(expression)));
+            continue;
+        }
+        match expression {
+            Expression::Asterisk => outputs.extend(
+                schema
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (c.name.clone(), Output::Column(i))),
+            ),
+            Expression::Wildcard { except, replace } => {
+                for name in except.iter().chain(replace.iter().map(|r| &r.column)) {
+                    if schema.column_index(name).is_none() {
+                        return Err(format!("Unknown column '{}'", name));
+                    }
+                }
+                for (i, c) in schema.columns.iter().enumerate() {
+                    if except.iter().any(|name| name.eq_ignore_ascii_case(&c.name)) {
+                        continue;
+                    }
+                    let output = match replace
+                        .iter()
+                        .find(|r| r.column.eq_ignore_ascii_case(&c.name))
+                    {
+                        Some(replacement) => Output::Expression(&replacement.expression),
+                        None => Output::Column(i),
+                    };
+                    outputs.push((c.name.clone(), output));
+                }
             }
-            Statement::Insert { table, values } => {
-                println!("Insert data into table '{}'", table);
-                Ok(())
+            Expression::Identifier(name) => {
+                outputs.push((name.clone(), Output::Expression(expression)))
             }
-            Statement::Select { table, columns } => {
-                println!("Select data from table '{}'", table);
-                Ok(())
-            } // other statements
+            other => outputs.push((other.to_sql(), Output::Expression(expression))),
+        }
+    }
+    Ok(outputs)
+}
+
+fn project(
+    outputs: &[(String, Output)],
+    schema: &TableSchema,
+    row: &[Value],
+    coercion: CoercionPolicy,
+) -> Result<Vec<Value>, String> {
+    outputs
+        .iter()
+        .map(|(_, output)| match output {
+            Output::Column(i) => Ok(row[*i].clone()),
+            Output::Expression(expr) => evaluate(expr, schema, row, coercion),
+        })
+        .collect()
+}
+
+/// Checks that every column `expr` names exists in `schema`, and that it holds no DEFAULT,
+/// which only stands for a value as an INSERT or UPDATE target.
+fn check_expression(expr: &Expression, schema: &TableSchema) -> Result<(), String> {
+    match expr {
+        Expression::Identifier(name)
+            if name != "NULL" && resolve_column(name, schema).is_none() =>
+        {
+            Err(format!("Unknown column '{}'", name))
         }
+        Expression::Default => Err(DEFAULT_OUTSIDE_TARGET.to_string()),
+        _ => expr
+            .children()
+            .into_iter()
+            .try_for_each(|child| check_expression(child, schema)),
+    }
+}
+
+/// Finds the position of a column, which may be qualified with the table's name, or with
+/// its alias when `schema` is named after the alias instead.
+pub(crate) fn resolve_column(name: &str, schema: &TableSchema) -> Option<usize> {
+    match name.split_once('.') {
+        Some((table, column)) if table.eq_ignore_ascii_case(&schema.name) => {
+            schema.column_index(column)
+        }
+        Some(_) => None,
+        None => schema.column_index(name),
+    }
+}
+
+/// Evaluates an expression against a row, using SQL's three-valued logic for NULL.
+fn evaluate(
+    expr: &Expression,
+    schema: &TableSchema,
+    row: &[Value],
+    coercion: CoercionPolicy,
+) -> Result<Value, String> {
+    match expr {
+        Expression::Identifier(name) if name == "NULL" => Ok(Value::Null),
+        Expression::Identifier(name) => resolve_column(name, schema)
+            .map(|i| row[i].clone())
+            .ok_or_else(|| format!("Unknown column '{}'", name)),
+        Expression::Integer(i) => Ok(Value::Integer(*i)),
+        Expression::Float(f) => Ok(Value::Float(*f)),
+        Expression::Text(s) => Ok(Value::Text(s.clone())),
+        Expression::Boolean(b) => Ok(Value::Boolean(*b)),
+        Expression::Blob(bytes) => Ok(Value::Blob(bytes.clone())),
+        Expression::Not(inner) => match evaluate(inner, schema, row, coercion)? {
+            Value::Boolean(b) => Ok(Value::Boolean(!b)),
+            Value::Null => Ok(Value::Null),
+            other => Err(format!("NOT expects a boolean, got {}", other.to_sql())),
+        },
+        Expression::And(left, right) => {
+            let left = as_boolean(evaluate(left, schema, row, coercion)?, "AND")?;
+            if left == Some(false) {
+                return Ok(Value::Boolean(false));
+            }
+            let right = as_boolean(evaluate(right, schema, row, coercion)?, "AND")?;
+            Ok(match (left, right) {
+                (_, Some(false)) => Value::Boolean(false),
+                (Some(true), Some(true)) => Value::Boolean(true),
+                _ => Value::Null,
+            })
+        }
+        Expression::Or(left, right) => {
+            let left = as_boolean(evaluate(left, schema, row, coercion)?, "OR")?;
+            if left == Some(true) {
+                return Ok(Value::Boolean(true));
+            }
+            let right = as_boolean(evaluate(right, schema, row, coercion)?, "OR")?;
+            Ok(match (left, right) {
+                (_, Some(true)) => Value::Boolean(true),
+                (Some(false), Some(false)) => Value::Boolean(false),
+                _ => Value::Null,
+            })
+        }
+        Expression::Function { name, args, .. }
+            if name.eq_ignore_ascii_case("GREATEST") || name.eq_ignore_ascii_case("LEAST") =>
+        {
+            // NULL arguments are ignored; the result is NULL only if every argument is
+            let wanted = if name.eq_ignore_ascii_case("GREATEST") {
+                cmp::Ordering::Greater
+            } else {
+                cmp::Ordering::Less
+            };
+            let mut result = Value::Null;
+            for arg in args {
+                let value = evaluate(arg, schema, row, coercion)?;
+                if value == Value::Null {
+                    continue;
+                }
+                if result == Value::Null
+                    || compare_values(&value, &result, coercion)? == Some(wanted)
+                {
+                    result = value;
+                }
+            }
+            Ok(result)
+        }
+        Expression::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = evaluate(left, schema, row, coercion)?;
+            let right = evaluate(right, schema, row, coercion)?;
+            compare(&left, operator, &right, coercion)
+        }
+        Expression::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => {
+            // `low <= expr AND expr <= high`; both sides are compared so that a type mismatch
+            // in either bound is reported
+            let value = evaluate(expr, schema, row, coercion)?;
+            let low = evaluate(low, schema, row, coercion)?;
+            let high = evaluate(high, schema, row, coercion)?;
+            let above_low = compare(&value, &BinaryOperator::GreaterThanOrEqual, &low, coercion)?;
+            let below_high = compare(&value, &BinaryOperator::LessThanOrEqual, &high, coercion)?;
+            let between = match (above_low, below_high) {
+                (Value::Boolean(false), _) | (_, Value::Boolean(false)) => Value::Boolean(false),
+                (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+                _ => Value::Null,
+            };
+            match between {
+                Value::Boolean(b) => Ok(Value::Boolean(b != *negated)),
+                null => Ok(null),
+            }
+        }
+        Expression::Cast { expr, data_type } => {
+            evaluate(expr, schema, row, coercion)?.coerce_to(data_type)
+        }
+        Expression::Negate(inner) => match evaluate(inner, schema, row, coercion)? {
+            Value::Integer(i) => i
+                .checked_neg()
+                .map(Value::Integer)
+                .ok_or_else(|| format!("Integer overflow negating {}", i)),
+            Value::Float(f) => Ok(Value::Float(-f)),
+            Value::Null => Ok(Value::Null),
+            other => Err(format!(
+                "Unary minus expects a number, got {}",
+                other.to_sql()
+            )),
+        },
+        other => Err(format!("Cannot evaluate '{}' yet", other.to_sql())),
+    }
+}
+
+fn as_boolean(value: Value, operator: &str) -> Result<Option<bool>, String> {
+    match value {
+        Value::Boolean(b) => Ok(Some(b)),
+        Value::Null => Ok(None),
+        other => Err(format!(
+            "{} expects booleans, got {}",
+            operator,
+            other.to_sql()
+        )),
+    }
+}
+
+/// Applies a comparison operator. Comparing with NULL yields NULL.
+fn compare(
+    left: &Value,
+    operator: &BinaryOperator,
+    right: &Value,
+    coercion: CoercionPolicy,
+) -> Result<Value, String> {
+    let Some(ordering) = compare_values(left, right, coercion)? else {
+        return Ok(Value::Null);
+    };
+
+    let result = match operator {
+        BinaryOperator::Equal => ordering == cmp::Ordering::Equal,
+        BinaryOperator::NotEqual => ordering != cmp::Ordering::Equal,
+        BinaryOperator::LessThan => ordering == cmp::Ordering::Less,
+        BinaryOperator::LessThanOrEqual => ordering != cmp::Ordering::Greater,
+        BinaryOperator::GreaterThan => ordering == cmp::Ordering::Greater,
+        BinaryOperator::GreaterThanOrEqual => ordering != cmp::Ordering::Less,
+        other => {
+            return Err(format!(
+                "Operator '{}' is not supported yet",
+                other.to_sql()
+            ))
+        }
+    };
+    Ok(Value::Boolean(result))
+}
+
+/// Orders two non-NULL values, or returns `None` if either is NULL.
+fn compare_values(
+    left: &Value,
+    right: &Value,
+    coercion: CoercionPolicy,
+) -> Result<Option<cmp::Ordering>, String> {
+    let ordering = match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => return Ok(None),
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        (Value::Integer(a), Value::Float(b)) => {
+            (*a as f64).partial_cmp(b).unwrap_or(cmp::Ordering::Equal)
+        }
+        (Value::Float(a), Value::Integer(b)) => {
+            a.partial_cmp(&(*b as f64)).unwrap_or(cmp::Ordering::Equal)
+        }
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b).unwrap_or(cmp::Ordering::Equal),
+        (Value::Text(text), Value::Integer(_) | Value::Float(_))
+            if coercion == CoercionPolicy::Lenient =>
+        {
+            return compare_values(&parse_number(text)?, right, coercion)
+        }
+        (Value::Integer(_) | Value::Float(_), Value::Text(text))
+            if coercion == CoercionPolicy::Lenient =>
+        {
+            return compare_values(left, &parse_number(text)?, coercion)
+        }
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+        (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+        _ => {
+            return Err(format!(
+                "Cannot compare {} with {}",
+                left.to_sql(),
+                right.to_sql()
+            ))
+        }
+    };
+    Ok(Some(ordering))
+}
+
+/// Reads text as the number it holds, for lenient comparisons with numbers.
+fn parse_number(text: &str) -> Result<Value, String> {
+    let trimmed = text.trim();
+    if let Ok(i) = trimmed.parse::<i64>() {
+        Ok(Value::Integer(i))
+    } else if let Some(f) = trimmed.parse::<f64>().ok().filter(|f| f.is_finite()) {
+        Ok(Value::Float(f))
+    } else {
+        Err(format!(
+            "Cannot compare {} with a number",
+            Value::Text(text.to_string()).to_sql()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ColumnDef;
+    use crate::parser::Parser;
+    use crate::storage::StorageEngine;
+    use std::fs;
+
+    fn column(name: &str, data_type: DataType) -> ColumnDef {
+        ColumnDef {
+            name: name.to_string(),
+            data_type,
+            not_null: false,
+            default: None,
+        }
+    }
+
+    /// Creates an executor with a `users (id, name, age)` table holding five rows.
+    fn executor(test_db: &str) -> Executor {
+        let _ = fs::remove_file(test_db);
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let mut executor = Executor::new(buffer_pool);
+        executor
+            .create_table(TableSchema::new(
+                "users",
+                vec![
+                    column("id", DataType::Integer),
+                    column("name", DataType::Text),
+                    column("age", DataType::Integer),
+                ],
+            ))
+            .unwrap();
+        for (id, name, age) in [
+            (3, "carol", Some(41)),
+            (1, "alice", Some(30)),
+            (5, "eve", None),
+            (2, "bob", Some(25)),
+            (4, "dave", Some(35)),
+        ] {
+            let age = age.map_or("NULL".to_string(), |a: i64| a.to_string());
+            run(
+                &mut executor,
+                &format!(
+                    "INSERT INTO users (id, name, age) VALUES ({}, '{}', {})",
+                    id, name, age
+                ),
+            )
+            .unwrap();
+        }
+        executor
+    }
+
+    fn run(executor: &mut Executor, sql: &str) -> Result<QueryResult, String> {
+        let query = Parser::new(sql)?.parse()?;
+        executor.execute(&query)
+    }
+
+    /// Tests that the COUNT(*) fast path agrees with counting through a scan.
+    #[test]
+    fn test_count_star_fast_path_matches_scan() {
+        let test_db = "test_executor_count.db";
+        let mut executor = executor(test_db);
+
+        let fast = run(&mut executor, "SELECT COUNT(*) FROM users").unwrap();
+        let scanned = run(&mut executor, "SELECT COUNT(*) FROM users WHERE id >= 0").unwrap();
+        assert_eq!(fast.columns, vec!["COUNT(*)"]);
+        assert_eq!(fast.rows, vec![vec![Value::Integer(5)]]);
+        assert_eq!(fast.rows, scanned.rows);
+
+        let filtered = run(&mut executor, "SELECT COUNT(*) FROM users WHERE age > 30").unwrap();
+        assert_eq!(filtered.rows, vec![vec![Value::Integer(2)]]);
+        let non_null = run(&mut executor, "SELECT COUNT(age) FROM users").unwrap();
+        assert_eq!(non_null.rows, vec![vec![Value::Integer(4)]]);
+        let filtered = run(
+            &mut executor,
+            "SELECT COUNT(*) FILTER (WHERE age < 40) FROM users",
+        )
+        .unwrap();
+        assert_eq!(filtered.rows, vec![vec![Value::Integer(3)]]);
+
+        // A key range is counted in the index without reading any rows
+        let before = executor.rows_scanned();
+        let counted = run(
+            &mut executor,
+            "SELECT COUNT(*) FROM users WHERE id BETWEEN 2 AND 4",
+        )
+        .unwrap();
+        assert_eq!(executor.rows_scanned(), before);
+        let scanned = run(
+            &mut executor,
+            "SELECT COUNT(id) FROM users WHERE id BETWEEN 2 AND 4",
+        )
+        .unwrap();
+        assert_eq!(counted.rows, vec![vec![Value::Integer(3)]]);
+        assert_eq!(counted.rows, scanned.rows);
+
+        let aliased = run(&mut executor, "SELECT COUNT(*) AS total FROM users").unwrap();
+        assert_eq!(aliased.columns, vec!["total"]);
+        assert_eq!(aliased.rows, fast.rows);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that SELECT filters rows and projects the requested columns in key order.
+    #[test]
+    fn test_select_with_where() {
+        let test_db = "test_executor_select.db";
+        let mut executor = executor(test_db);
+
+        let result = run(&mut executor, "SELECT name, id FROM users WHERE age < 40").unwrap();
+        assert_eq!(result.columns, vec!["name", "id"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("alice".to_string()), Value::Integer(1)],
+                vec![Value::Text("bob".to_string()), Value::Integer(2)],
+                vec![Value::Text("dave".to_string()), Value::Integer(4)],
+            ]
+        );
+
+        let result = run(&mut executor, "SELECT * FROM users WHERE id = 5").unwrap();
+        assert_eq!(result.columns, vec!["id", "name", "age"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(5),
+                Value::Text("eve".to_string()),
+                Value::Null
+            ]]
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that an alias replaces the table name as the qualifier of its columns, in every
+    /// clause and even when the condition could be answered from the index alone.
+    #[test]
+    fn test_table_alias_qualifiers() {
+        let test_db = "test_executor_alias.db";
+        let mut executor = executor(test_db);
+
+        let result = run(
+            &mut executor,
+            "SELECT q.name FROM users AS q WHERE q.id = 2 ORDER BY q.age",
+        )
+        .unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Text("bob".to_string())]]);
+        let result = run(
+            &mut executor,
+            "SELECT users.name FROM users WHERE users.id = 2",
+        )
+        .unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Text("bob".to_string())]]);
+
+        assert_eq!(
+            run(&mut executor, "SELECT users.name FROM users AS q").unwrap_err(),
+            "Unknown column 'users.name'"
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                "SELECT q.name FROM users AS q WHERE users.id = 2"
+            )
+            .unwrap_err(),
+            "Unknown column 'users.id'"
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that inserting a duplicate key, a mistyped value or the wrong number of selected
+    /// columns fails.
+    #[test]
+    fn test_insert_errors() {
+        let test_db = "test_executor_insert.db";
+        let mut executor = executor(test_db);
+
+        assert!(run(
+            &mut executor,
+            "INSERT INTO users (id, name, age) VALUES (1, 'x', 1)"
+        )
+        .is_err());
+        assert_eq!(
+            run(
+                &mut executor,
+                "INSERT INTO users (id, name, age) VALUES (9, 10, 1)"
+            ),
+            Err("Cannot convert 10 to TEXT for column 'name'".to_string())
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                "INSERT INTO users (id, name) SELECT * FROM users"
+            ),
+            Err("INSERT has 2 target columns, but SELECT produces 3 columns".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that a batch whose second statement fails leaves no trace of its first, that a
+    /// batch that succeeds keeps every change, and that a batch it could not undo is refused.
+    #[test]
+    fn test_execute_all_rolls_back() {
+        let test_db = "test_executor_execute_all.db";
+        let mut executor = executor(test_db);
+        let parse = |sql: &str| Parser::new(sql).unwrap().parse().unwrap();
+        let ids = |executor: &mut Executor| run(executor, "SELECT id FROM users").unwrap().rows;
+        let before = ids(&mut executor);
+
+        let batch = vec![
+            parse("INSERT INTO users (id, name, age) VALUES (6, 'frank', 50)"),
+            parse("INSERT INTO users (id, name, age) VALUES (1, 'alice', 30)"),
+        ];
+        assert_eq!(
+            executor.execute_all(batch),
+            Err("Statement 2 failed: Duplicate key insertion is not allowed".to_string())
+        );
+        assert_eq!(ids(&mut executor), before);
+        // The key freed by the rollback can be inserted again
+        run(
+            &mut executor,
+            "INSERT INTO users (id, name, age) VALUES (6, 'frank', 50)",
+        )
+        .unwrap();
+
+        let batch = vec![
+            parse("CREATE TABLE logs (id INTEGER, message TEXT)"),
+            parse("INSERT INTO logs (id, message) VALUES (1, 'created')"),
+            parse("SELECT message FROM logs"),
+        ];
+        let results = executor.execute_all(batch).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].rows_affected, 1);
+        assert_eq!(
+            results[2].rows,
+            vec![vec![Value::Text("created".to_string())]]
+        );
+
+        let batch = vec![
+            parse("CREATE TABLE events (id INTEGER)"),
+            parse("INSERT INTO events (id) VALUES (1)"),
+            parse("SELECT missing FROM events"),
+        ];
+        assert!(executor.execute_all(batch).is_err());
+        assert!(executor.catalog().get_table("events").is_none());
+        assert!(run(&mut executor, "SELECT id FROM events").is_err());
+
+        // A statement the batch could not undo stops it before anything runs
+        let before = ids(&mut executor);
+        let batch = vec![
+            parse("INSERT INTO users (id, name, age) VALUES (7, 'grace', 40)"),
+            parse("DROP TABLE users"),
+        ];
+        assert_eq!(
+            executor.execute_all(batch),
+            Err("Statement 2 cannot be undone, so it cannot run in a batch".to_string())
+        );
+        assert_eq!(ids(&mut executor), before);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that INSERT places values by column name, whatever order the columns are listed
+    /// in, and fills the columns it leaves out.
+    #[test]
+    fn test_insert_column_order() {
+        let test_db = "test_executor_insert_order.db";
+        let mut executor = executor(test_db);
+
+        run(
+            &mut executor,
+            "INSERT INTO users (age, ID, name) VALUES (50, 6, 'frank')",
+        )
+        .unwrap();
+        run(
+            &mut executor,
+            "INSERT INTO users (name, id) VALUES ('gina', 7)",
+        )
+        .unwrap();
+        let result = run(&mut executor, "SELECT * FROM users WHERE id >= 6").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![
+                    Value::Integer(6),
+                    Value::Text("frank".to_string()),
+                    Value::Integer(50)
+                ],
+                vec![
+                    Value::Integer(7),
+                    Value::Text("gina".to_string()),
+                    Value::Null
+                ],
+            ]
+        );
+
+        assert_eq!(
+            run(
+                &mut executor,
+                "INSERT INTO users (id, email) VALUES (8, 'x')"
+            ),
+            Err("Column 'email' does not exist in table 'users'".to_string())
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                "INSERT INTO users (id, name, id) VALUES (8, 'x', 9)"
+            ),
+            Err("Column 'id' is listed more than once".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, "INSERT INTO users (id, name) VALUES (8)"),
+            Err("INSERT has 2 columns but 1 values".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, "INSERT INTO users (name) VALUES ('x')"),
+            Err("Column 'id' requires a key".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that INSERT converts values to their column types and reports values that can't
+    /// be converted, and that CAST uses the same conversions.
+    #[test]
+    fn test_insert_coercion() {
+        let test_db = "test_executor_insert_coercion.db";
+        let mut executor = executor(test_db);
+        executor
+            .create_table(TableSchema::new(
+                "readings",
+                vec![
+                    column("id", DataType::Integer),
+                    column("value", DataType::Float),
+                    column("valid", DataType::Boolean),
+                ],
+            ))
+            .unwrap();
+
+        run(
+            &mut executor,
+            "INSERT INTO readings (id, value, valid) VALUES (' 1 ', '2.5', 'TRUE')",
+        )
+        .unwrap();
+        run(
+            &mut executor,
+            "INSERT INTO readings (id, value, valid) VALUES (2.0, 3, NULL)",
+        )
+        .unwrap();
+        let result = run(&mut executor, "SELECT * FROM readings").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(1), Value::Float(2.5), Value::Boolean(true)],
+                vec![Value::Integer(2), Value::Float(3.0), Value::Null],
+            ]
+        );
+
+        for (values, error) in [
+            (
+                "('abc', 1, true)",
+                "Cannot convert 'abc' to INTEGER for column 'id'",
+            ),
+            (
+                "(2.5, 1, true)",
+                "Cannot convert 2.5 to INTEGER for column 'id'",
+            ),
+            (
+                "(3, 'high', true)",
+                "Cannot convert 'high' to FLOAT for column 'value'",
+            ),
+            (
+                "(3, 1, 'yes')",
+                "Cannot convert 'yes' to BOOLEAN for column 'valid'",
+            ),
+            (
+                "(3, 1, 2.5)",
+                "Cannot convert 2.5 to BOOLEAN for column 'valid'",
+            ),
+        ] {
+            let sql = format!("INSERT INTO readings (id, value, valid) VALUES {}", values);
+            assert_eq!(run(&mut executor, &sql), Err(error.to_string()));
+        }
+
+        let result = run(
+            &mut executor,
+            "SELECT '7'::INTEGER, id::FLOAT, NULL::TEXT FROM readings WHERE id = 1",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(7), Value::Float(1.0), Value::Null]]
+        );
+        assert_eq!(
+            run(&mut executor, "SELECT name::INTEGER FROM users"),
+            Err("Cannot convert 'alice' to INTEGER".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that a bare boolean column filters rows on its own, and that a non-boolean one is
+    /// rejected rather than treated as truthy.
+    #[test]
+    fn test_where_boolean_column() {
+        let test_db = "test_executor_where_boolean.db";
+        let mut executor = executor(test_db);
+        executor
+            .create_table(TableSchema::new(
+                "accounts",
+                vec![
+                    column("id", DataType::Integer),
+                    column("is_active", DataType::Boolean),
+                ],
+            ))
+            .unwrap();
+        run(
+            &mut executor,
+            "INSERT INTO accounts (id, is_active) VALUES (1, true)",
+        )
+        .unwrap();
+        run(
+            &mut executor,
+            "INSERT INTO accounts (id, is_active) VALUES (2, false)",
+        )
+        .unwrap();
+        run(&mut executor, "INSERT INTO accounts (id) VALUES (3)").unwrap();
+
+        let result = run(&mut executor, "SELECT id FROM accounts WHERE is_active").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(1)]]);
+        let result = run(
+            &mut executor,
+            "SELECT id FROM accounts WHERE NOT is_active AND id > 0",
+        )
+        .unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+
+        assert_eq!(
+            run(&mut executor, "SELECT id FROM accounts WHERE id"),
+            Err("WHERE clause must be a boolean expression, got 1".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, "SELECT COUNT(*) FROM users WHERE name"),
+            Err("WHERE clause must be a boolean expression, got 'alice'".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    fn plan(executor: &mut Executor, sql: &str) -> Vec<String> {
+        run(executor, sql)
+            .unwrap()
+            .rows
+            .into_iter()
+            .map(|row| match &row[0] {
+                Value::Text(line) => line.trim().to_string(),
+                other => panic!("Unexpected plan row {:?}", other),
+            })
+            .collect()
+    }
+
+    /// Tests that queries reading only the key are answered from the index keys alone.
+    #[test]
+    fn test_index_only_scan() {
+        let test_db = "test_executor_index_only.db";
+        let mut executor = executor(test_db);
+
+        let result = run(
+            &mut executor,
+            "SELECT id FROM users WHERE id > 2 ORDER BY id DESC",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(5)],
+                vec![Value::Integer(4)],
+                vec![Value::Integer(3)]
+            ]
+        );
+        assert_eq!(
+            plan(
+                &mut executor,
+                "EXPLAIN SELECT users.id FROM users WHERE id > 2"
+            ),
+            vec![
+                "Project users.id",
+                "Index Only Scan users (key order, id >= 3)"
+            ]
+        );
+
+        // Reading any other column needs the rows
+        assert_eq!(
+            plan(&mut executor, "EXPLAIN SELECT id FROM users WHERE age > 2").last(),
+            Some(&"Scan users (key order)".to_string())
+        );
+        assert_eq!(
+            plan(&mut executor, "EXPLAIN SELECT * FROM users").last(),
+            Some(&"Scan users (key order)".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that key bounds narrow the scan while the rest of the WHERE clause is evaluated per
+    /// row: NULL excludes a row and a type error aborts the query.
+    #[test]
+    fn test_key_range_with_residual_filter() {
+        let test_db = "test_executor_key_range.db";
+        let mut executor = executor(test_db);
+
+        let names = |result: QueryResult| -> Vec<Value> {
+            result
+                .rows
+                .into_iter()
+                .map(|mut row| row.remove(0))
+                .collect()
+        };
+        let text = |name: &str| Value::Text(name.to_string());
+
+        let result = run(
+            &mut executor,
+            "SELECT name FROM users WHERE id >= 2 AND age > 30",
+        )
+        .unwrap();
+        assert_eq!(names(result), vec![text("carol"), text("dave")]);
+        // eve's NULL age makes NOT (age < 30) NULL, which excludes the row
+        let result = run(
+            &mut executor,
+            "SELECT name FROM users WHERE 1 < id AND NOT (age < 30)",
+        )
+        .unwrap();
+        assert_eq!(names(result), vec![text("carol"), text("dave")]);
+        let result = run(
+            &mut executor,
+            "SELECT name FROM users WHERE id <= 4 AND id > 2 OR age = 25",
+        )
+        .unwrap();
+        assert_eq!(
+            names(result),
+            vec![text("bob"), text("carol"), text("dave")]
+        );
+        let result = run(
+            &mut executor,
+            "SELECT name FROM users WHERE id > 3 AND id < 3",
+        )
+        .unwrap();
+        assert!(result.rows.is_empty());
+        let result = run(&mut executor, "SELECT COUNT(age) FROM users WHERE id >= 4").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(1)]]);
+
+        assert_eq!(
+            run(
+                &mut executor,
+                "SELECT name FROM users WHERE id >= 4 AND age = 'x'"
+            ),
+            Err("Cannot compare 35 with 'x'".to_string())
+        );
+        assert_eq!(
+            plan(
+                &mut executor,
+                "EXPLAIN SELECT name FROM users WHERE id > 1 AND id <= 4 AND age > 30"
+            )
+            .last(),
+            Some(&"Scan users (key order, id >= 2 AND id <= 4)".to_string())
+        );
+        assert_eq!(
+            plan(&mut executor, "EXPLAIN SELECT name FROM users WHERE 3 = id").last(),
+            Some(&"Scan users (key order, id = 3)".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that byte-wise collations sort like the default and other collations are rejected.
+    #[test]
+    fn test_order_by_collation() {
+        let test_db = "test_executor_collation.db";
+        let mut executor = executor(test_db);
+
+        let result = run(
+            &mut executor,
+            r#"SELECT name FROM users ORDER BY name COLLATE "C" DESC"#,
+        )
+        .unwrap();
+        let names: Vec<Vec<Value>> = ["eve", "dave", "carol", "bob", "alice"]
+            .into_iter()
+            .map(|name| vec![Value::Text(name.to_string())])
+            .collect();
+        assert_eq!(result.rows, names);
+        assert_eq!(
+            run(
+                &mut executor,
+                r#"SELECT name FROM users ORDER BY name COLLATE "en_US""#
+            ),
+            Err("Unsupported collation 'en_US'".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that STRING_AGG and GROUP_CONCAT join non-NULL values in their inner ORDER BY.
+    #[test]
+    fn test_string_agg() {
+        let test_db = "test_executor_string_agg.db";
+        let mut executor = executor(test_db);
+
+        let result = run(
+            &mut executor,
+            "SELECT STRING_AGG(name, ', ' ORDER BY name DESC) FROM users WHERE id > 1",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Text("eve, dave, carol, bob".to_string())]]
+        );
+        let result = run(
+            &mut executor,
+            "SELECT group_concat(name) FILTER (WHERE id < 3) FROM users",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Text("alice,bob".to_string())]]
+        );
+        let result = run(
+            &mut executor,
+            "SELECT STRING_AGG(name) FROM users WHERE id > 99",
+        )
+        .unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Null]]);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that SHOW TABLES lists tables by name and SHOW COLUMNS/DESCRIBE describe a
+    /// table's columns from the catalog.
+    #[test]
+    fn test_show_metadata() {
+        let test_db = "test_executor_show.db";
+        let mut executor = executor(test_db);
+        executor
+            .create_table(TableSchema::new(
+                "accounts",
+                vec![ColumnDef {
+                    not_null: true,
+                    default: Some(Value::Integer(0)),
+                    ..column("id", DataType::Integer)
+                }],
+            ))
+            .unwrap();
+
+        let result = run(&mut executor, "SHOW TABLES").unwrap();
+        assert_eq!(result.columns, vec!["table"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("accounts".to_string())],
+                vec![Value::Text("users".to_string())]
+            ]
+        );
+
+        let result = run(&mut executor, "DESCRIBE accounts").unwrap();
+        assert_eq!(
+            result.columns,
+            vec!["column", "type", "nullable", "default"]
+        );
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Text("id".to_string()),
+                Value::Text("INTEGER".to_string()),
+                Value::Boolean(false),
+                Value::Integer(0)
+            ]]
+        );
+        let result = run(&mut executor, "SHOW COLUMNS FROM users").unwrap();
+        let names: Vec<Value> = result.rows.iter().map(|row| row[0].clone()).collect();
+        let expected = ["id", "name", "age"].map(|name| Value::Text(name.to_string()));
+        assert_eq!(names, expected);
+        assert_eq!(result.rows[1][3], Value::Null);
+        assert_eq!(
+            run(&mut executor, "DESCRIBE missing"),
+            Err("Table 'missing' does not exist".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that BETWEEN keeps inclusive numeric and text ranges, excludes NULLs either way
+    /// and rejects bounds of another type.
+    #[test]
+    fn test_between() {
+        let test_db = "test_executor_between.db";
+        let mut executor = executor(test_db);
+        let mut names = |sql: &str| -> Result<Vec<Value>, String> {
+            let result = run(&mut executor, sql)?;
+            Ok(result
+                .rows
+                .into_iter()
+                .map(|mut row| row.remove(0))
+                .collect())
+        };
+        let text = |names: &[&str]| -> Vec<Value> {
+            names
+                .iter()
+                .map(|name| Value::Text(name.to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            names("SELECT name FROM users WHERE age BETWEEN 25 AND 35"),
+            Ok(text(&["alice", "bob", "dave"]))
+        );
+        // eve's NULL age is neither between nor outside the range
+        assert_eq!(
+            names("SELECT name FROM users WHERE age NOT BETWEEN 25 AND 35"),
+            Ok(text(&["carol"]))
+        );
+        assert_eq!(
+            names("SELECT name FROM users WHERE name BETWEEN 'b' AND 'd' AND id > 1"),
+            Ok(text(&["bob", "carol"]))
+        );
+        assert_eq!(
+            names("SELECT name FROM users WHERE id BETWEEN NULL AND 3"),
+            Ok(Vec::new())
+        );
+        assert_eq!(
+            names("SELECT name FROM users WHERE name BETWEEN 'a' AND 5"),
+            Err("Cannot compare 'alice' with 5".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that DEFAULT inserts the column's default, or NULL for a column without one.
+    #[test]
+    fn test_insert_default() {
+        let test_db = "test_executor_default.db";
+        let mut executor = executor(test_db);
+        executor
+            .create_table(TableSchema::new(
+                "settings",
+                vec![
+                    column("id", DataType::Integer),
+                    ColumnDef {
+                        default: Some(Value::Text("dark".to_string())),
+                        ..column("theme", DataType::Text)
+                    },
+                    column("size", DataType::Integer),
+                ],
+            ))
+            .unwrap();
+
+        run(
+            &mut executor,
+            "INSERT INTO settings (id, theme, size) VALUES (1, DEFAULT, DEFAULT)",
+        )
+        .unwrap();
+        let result = run(&mut executor, "SELECT * FROM settings").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Integer(1),
+                Value::Text("dark".to_string()),
+                Value::Null
+            ]]
+        );
+
+        // Rejected before any row is read, so also over an empty table
+        for sql in [
+            "SELECT DEFAULT FROM settings",
+            "SELECT id FROM settings WHERE size = DEFAULT",
+            "SELECT id FROM settings WHERE id = 1 AND theme = DEFAULT",
+        ] {
+            assert_eq!(
+                run(&mut executor, sql),
+                Err("DEFAULT is only allowed as an INSERT or UPDATE value".to_string()),
+                "{}",
+                sql
+            );
+        }
+        run(&mut executor, "CREATE TABLE empty (id INTEGER)").unwrap();
+        assert_eq!(
+            run(&mut executor, "SELECT DEFAULT FROM empty"),
+            Err("DEFAULT is only allowed as an INSERT or UPDATE value".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that ordering by the key ascending streams from the index without a sort,
+    /// while other orderings sort.
+    #[test]
+    fn test_order_by_key_skips_sort() {
+        let test_db = "test_executor_order_by.db";
+        let mut executor = executor(test_db);
+
+        let result = run(&mut executor, "SELECT id FROM users ORDER BY id").unwrap();
+        let ids: Vec<Value> = (1..=5).map(Value::Integer).collect();
+        assert_eq!(
+            result.rows,
+            ids.iter().map(|id| vec![id.clone()]).collect::<Vec<_>>()
+        );
+        assert!(!plan(
+            &mut executor,
+            "EXPLAIN SELECT id FROM users ORDER BY users.id ASC"
+        )
+        .iter()
+        .any(|operator| operator.starts_with("Sort")));
+
+        let result = run(&mut executor, "SELECT id FROM users ORDER BY id DESC").unwrap();
+        let descending: Vec<Vec<Value>> = ids.iter().rev().map(|id| vec![id.clone()]).collect();
+        assert_eq!(result.rows, descending);
+        assert_eq!(
+            plan(
+                &mut executor,
+                "EXPLAIN SELECT name FROM users WHERE id > 1 ORDER BY age DESC"
+            ),
+            vec![
+                "Project name",
+                "Sort age DESC",
+                "Scan users (key order, id >= 2)"
+            ]
+        );
+
+        // NULLs sort last ascending
+        let result = run(&mut executor, "SELECT id FROM users ORDER BY age").unwrap();
+        let by_age: Vec<Vec<Value>> = [2, 1, 4, 3, 5]
+            .into_iter()
+            .map(|id| vec![Value::Integer(id)])
+            .collect();
+        assert_eq!(result.rows, by_age);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that ORDER BY can name a select list alias, which wins over a column of the same
+    /// name, so ordering by an alias that shadows the key still sorts.
+    #[test]
+    fn test_order_by_alias() {
+        let test_db = "test_executor_order_by_alias.db";
+        let mut executor = executor(test_db);
+        let names = |result: QueryResult| -> Vec<Value> {
+            result
+                .rows
+                .into_iter()
+                .map(|mut row| row.remove(0))
+                .collect()
+        };
+        let text = |names: &[&str]| -> Vec<Value> {
+            names
+                .iter()
+                .map(|name| Value::Text(name.to_string()))
+                .collect()
+        };
+
+        let result = run(&mut executor, "SELECT name AS k FROM users ORDER BY k DESC").unwrap();
+        assert_eq!(
+            names(result),
+            text(&["eve", "dave", "carol", "bob", "alice"])
+        );
+
+        let sql = "SELECT name AS id FROM users ORDER BY id";
+        assert_eq!(
+            plan(&mut executor, &format!("EXPLAIN {}", sql)),
+            vec!["Project name AS id", "Sort id", "Scan users (key order)"]
+        );
+        let result = run(&mut executor, sql).unwrap();
+        assert_eq!(
+            names(result),
+            text(&["alice", "bob", "carol", "dave", "eve"])
+        );
+
+        assert_eq!(
+            run(
+                &mut executor,
+                "SELECT name AS k FROM users ORDER BY users.k"
+            )
+            .unwrap_err(),
+            "Unknown column 'users.k'"
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that GREATEST and LEAST ignore NULL arguments.
+    #[test]
+    fn test_greatest_and_least() {
+        let test_db = "test_executor_greatest.db";
+        let mut executor = executor(test_db);
+
+        let result = run(
+            &mut executor,
+            "SELECT GREATEST(id, age, 31), LEAST(age, id) FROM users WHERE id >= 4",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(35), Value::Integer(4)],
+                vec![Value::Integer(31), Value::Integer(5)],
+            ]
+        );
+
+        let result = run(&mut executor, "SELECT LEAST(age) FROM users WHERE id = 5").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Null]]);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that `* EXCEPT` drops columns and `* REPLACE` swaps in expressions in place.
+    #[test]
+    fn test_wildcard_modifiers() {
+        let test_db = "test_executor_wildcard.db";
+        let mut executor = executor(test_db);
+
+        let result = run(
+            &mut executor,
+            "SELECT * EXCEPT (name) REPLACE (GREATEST(age, 40) AS age) FROM users WHERE id < 3",
+        )
+        .unwrap();
+        assert_eq!(result.columns, vec!["id", "age"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Integer(1), Value::Integer(40)],
+                vec![Value::Integer(2), Value::Integer(40)],
+            ]
+        );
+
+        assert_eq!(
+            run(&mut executor, "SELECT * EXCEPT (missing) FROM users"),
+            Err("Unknown column 'missing'".to_string())
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that LIMIT and OFFSET stop a key-ordered scan once enough rows have been produced,
+    /// while a sort still reads every row first.
+    #[test]
+    fn test_limit_stops_scan_early() {
+        let test_db = "test_executor_limit.db";
+        let mut executor = executor(test_db);
+        for id in 6..=1005 {
+            let row = vec![
+                Value::Integer(id),
+                Value::Text(format!("user{}", id)),
+                Value::Integer(20),
+            ];
+            executor.insert_row("users", row).unwrap();
+        }
+
+        let before = executor.rows_scanned();
+        let result = run(&mut executor, "SELECT id FROM users LIMIT 10").unwrap();
+        let ids: Vec<Value> = (1..=10).map(Value::Integer).collect();
+        assert_eq!(
+            result.rows,
+            ids.into_iter().map(|id| vec![id]).collect::<Vec<_>>()
+        );
+        assert_eq!(executor.rows_scanned() - before, 10);
+
+        let before = executor.rows_scanned();
+        let result = run(&mut executor, "SELECT name FROM users LIMIT 2 OFFSET 2").unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("carol".to_string())],
+                vec![Value::Text("dave".to_string())]
+            ]
+        );
+        assert_eq!(executor.rows_scanned() - before, 4);
+
+        // Rows that fail the filter are read but not counted toward the limit
+        let before = executor.rows_scanned();
+        let result = run(&mut executor, "SELECT id FROM users WHERE age = 20 LIMIT 1").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(6)]]);
+        assert_eq!(executor.rows_scanned() - before, 6);
+
+        // eve's NULL age sorts first, and the sort has to see every row before the limit
+        let before = executor.rows_scanned();
+        let result = run(
+            &mut executor,
+            "SELECT name FROM users ORDER BY age DESC LIMIT 2 OFFSET 1",
+        )
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![Value::Text("carol".to_string())],
+                vec![Value::Text("dave".to_string())]
+            ]
+        );
+        assert_eq!(executor.rows_scanned() - before, 1005);
+
+        let result = run(&mut executor, "SELECT id FROM users OFFSET 1004").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(1005)]]);
+        let result = run(&mut executor, "SELECT COUNT(*) FROM users LIMIT 0").unwrap();
+        assert!(result.rows.is_empty());
+        assert_eq!(
+            run(&mut executor, "SELECT id FROM users LIMIT 'ten'").unwrap_err(),
+            "LIMIT must be a non-negative integer, got 'ten'"
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that `'5' = 5` is a type error under the strict policy and compares as numbers
+    /// under the lenient one.
+    #[test]
+    fn test_coercion_policy() {
+        let test_db = "test_executor_coercion.db";
+        let mut executor = executor(test_db);
+        assert_eq!(executor.coercion_policy(), CoercionPolicy::Strict);
+
+        let sql = "SELECT id FROM users WHERE '5' = 5 AND age >= '30.5'";
+        assert_eq!(
+            run(&mut executor, sql).unwrap_err(),
+            "Cannot compare '5' with 5"
+        );
+
+        executor.set_coercion_policy(CoercionPolicy::Lenient);
+        let result = run(&mut executor, sql).unwrap();
+        assert_eq!(
+            result.rows,
+            vec![vec![Value::Integer(3)], vec![Value::Integer(4)]]
+        );
+        let result = run(&mut executor, "SELECT id FROM users WHERE ' 2 ' = id").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+        // Text still compares with text, and text that is not a number still fails
+        let result = run(&mut executor, "SELECT id FROM users WHERE name = 'bob'").unwrap();
+        assert_eq!(result.rows, vec![vec![Value::Integer(2)]]);
+        assert_eq!(
+            run(&mut executor, "SELECT id FROM users WHERE age = 'old'").unwrap_err(),
+            "Cannot compare 'old' with a number"
+        );
+
+        let _ = fs::remove_file(test_db);
     }
 }
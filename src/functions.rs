@@ -0,0 +1,210 @@
+//! SQL functions known to the parser, analyzer and executor.
+//!
+//! The built-in functions are always known. A [`FunctionRegistry`] adds custom functions on top
+//! of them so the analyzer can validate calls to those too.
+
+use crate::ast::DataType;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// How many arguments a function accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// Between the two counts, inclusive.
+    Between(usize, usize),
+}
+
+impl Arity {
+    /// Returns whether a call with `count` arguments is valid.
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+            Arity::Between(min, max) => (min..=max).contains(&count),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let (qualifier, n) = match *self {
+            Arity::Exact(n) => (String::new(), n),
+            Arity::AtLeast(n) => ("at least ".to_string(), n),
+            Arity::Between(min, max) => (format!("{} to ", min), max),
+        };
+        let plural = if n == 1 { "" } else { "s" };
+        format!("{}{} argument{}", qualifier, n, plural)
+    }
+}
+
+/// What a function returns, as a hint for type checking.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnType {
+    Fixed(DataType),
+    /// The type of the first argument, as for MIN or COALESCE.
+    FirstArgument,
+}
+
+/// The name, arity and return type of a function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSignature {
+    pub name: String,
+    pub arity: Arity,
+    pub return_type: ReturnType,
+}
+
+impl FunctionSignature {
+    /// Creates a signature. Names are stored uppercase so lookups can ignore case.
+    pub fn new(name: &str, arity: Arity, return_type: ReturnType) -> Self {
+        FunctionSignature {
+            name: name.to_uppercase(),
+            arity,
+            return_type,
+        }
+    }
+
+    /// Checks the number of arguments of a call to this function.
+    pub fn check_arity(&self, count: usize) -> Result<(), String> {
+        if self.arity.accepts(count) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Function '{}' expects {}, got {}",
+                self.name,
+                self.arity.describe(),
+                count
+            ))
+        }
+    }
+}
+
+/// Functions by name, looked up ignoring case.
+#[derive(Debug, Clone)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, FunctionSignature>,
+}
+
+impl FunctionRegistry {
+    /// Creates a registry holding the built-in functions.
+    pub fn new() -> Self {
+        use ReturnType::{FirstArgument, Fixed};
+
+        let builtins = [
+            ("COUNT", Arity::Exact(1), Fixed(DataType::Integer)),
+            ("SUM", Arity::Exact(1), FirstArgument),
+            ("AVG", Arity::Exact(1), Fixed(DataType::Float)),
+            ("MIN", Arity::Exact(1), FirstArgument),
+            ("MAX", Arity::Exact(1), FirstArgument),
+            // The separator is optional and defaults to a comma
+            ("STRING_AGG", Arity::Between(1, 2), Fixed(DataType::Text)),
+            ("GROUP_CONCAT", Arity::Between(1, 2), Fixed(DataType::Text)),
+            ("GREATEST", Arity::AtLeast(1), FirstArgument),
+            ("LEAST", Arity::AtLeast(1), FirstArgument),
+            ("COALESCE", Arity::AtLeast(1), FirstArgument),
+            ("NULLIF", Arity::Exact(2), FirstArgument),
+            // A bit per argument, set when that GROUP BY expression is rolled up in the row
+            ("GROUPING", Arity::AtLeast(1), Fixed(DataType::Integer)),
+        ];
+
+        let mut registry = FunctionRegistry {
+            functions: HashMap::new(),
+        };
+        for (name, arity, return_type) in builtins {
+            registry.functions.insert(
+                name.to_string(),
+                FunctionSignature::new(name, arity, return_type),
+            );
+        }
+        registry
+    }
+
+    /// Returns the shared registry of built-in functions.
+    pub fn builtin() -> &'static FunctionRegistry {
+        static BUILTIN: OnceLock<FunctionRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(FunctionRegistry::new)
+    }
+
+    /// Adds a custom function. Fails if a function with the same name is already registered.
+    pub fn register(&mut self, signature: FunctionSignature) -> Result<(), String> {
+        if self.functions.contains_key(&signature.name) {
+            return Err(format!("Function '{}' already exists", signature.name));
+        }
+        self.functions.insert(signature.name.clone(), signature);
+        Ok(())
+    }
+
+    /// Looks up a function by name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&FunctionSignature> {
+        self.functions.get(&name.to_uppercase())
+    }
+
+    /// Checks that `name` is a known function that accepts `count` arguments.
+    pub fn check_call(&self, name: &str, count: usize) -> Result<&FunctionSignature, String> {
+        let function = self
+            .get(name)
+            .ok_or_else(|| format!("Unknown function '{}'", name))?;
+        function.check_arity(count)?;
+        Ok(function)
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Looks up a built-in function by name, ignoring case.
+pub fn lookup(name: &str) -> Option<&'static FunctionSignature> {
+    FunctionRegistry::builtin().get(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that lookups ignore case and arity errors describe the expected count.
+    #[test]
+    fn test_lookup_and_arity() {
+        let greatest = lookup("greatest").unwrap();
+        assert_eq!(greatest.name, "GREATEST");
+        assert!(greatest.check_arity(3).is_ok());
+        assert_eq!(
+            greatest.check_arity(0),
+            Err("Function 'GREATEST' expects at least 1 argument, got 0".to_string())
+        );
+        assert_eq!(
+            lookup("NULLIF").unwrap().check_arity(1),
+            Err("Function 'NULLIF' expects 2 arguments, got 1".to_string())
+        );
+        assert_eq!(
+            lookup("string_agg").unwrap().check_arity(3),
+            Err("Function 'STRING_AGG' expects 1 to 2 arguments, got 3".to_string())
+        );
+        assert!(lookup("no_such_function").is_none());
+    }
+
+    /// Tests that custom functions can be registered once and are then checked like built-ins.
+    #[test]
+    fn test_register_custom_function() {
+        let mut registry = FunctionRegistry::new();
+        assert_eq!(
+            registry.check_call("slugify", 1).unwrap_err(),
+            "Unknown function 'slugify'"
+        );
+
+        let slugify = FunctionSignature::new(
+            "slugify",
+            Arity::Exact(1),
+            ReturnType::Fixed(DataType::Text),
+        );
+        registry.register(slugify.clone()).unwrap();
+        assert_eq!(registry.check_call("SLUGIFY", 1), Ok(&slugify));
+        assert!(registry.check_call("slugify", 2).is_err());
+        assert_eq!(
+            registry.register(slugify),
+            Err("Function 'SLUGIFY' already exists".to_string())
+        );
+        assert!(lookup("slugify").is_none());
+    }
+}
@@ -1,5 +1,10 @@
+use crate::ast;
 use crate::buffer_pool::BufferPool;
+use crate::cancellation::CancellationToken;
 use crate::storage::{Key, Value};
+use std::cmp;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 
 /// Represents the B+ Tree order (degree).
@@ -7,20 +12,142 @@ pub const ORDER: usize = 4;
 
 /// Represents a node in the B+ Tree.
 #[derive(Debug)]
-struct BPlusTreeNode {
-    keys: Vec<Key>,
-    children: Vec<Arc<RwLock<BPlusTreeNode>>>,
+struct BPlusTreeNode<K> {
+    keys: Vec<K>,
+    children: Vec<Arc<RwLock<BPlusTreeNode<K>>>>,
+    values: Vec<Value>, // Only populated in leaf nodes, parallel to `keys`
     is_leaf: bool,
 }
 
+impl<K: Ord> BPlusTreeNode<K> {
+    /// Returns the index of the child an internal node routes `key` to.
+    /// Insert and search both route through here so they always agree on the target leaf.
+    ///
+    /// A separator key is the smallest key of the subtree to its right, so a key equal to a
+    /// separator is routed to the right child.
+    fn child_index(&self, key: &K) -> usize {
+        self.keys.partition_point(|k| k <= key)
+    }
+}
+
+/// A multi-column key, such as `(user_id, order_id)`, compared column by column.
+///
+/// Within a column, NULL sorts first, then booleans, numbers (integers and floats compare by
+/// value), text and blobs. A key that is a prefix of another sorts before it, so a shorter key
+/// can serve as the low bound of a range over every key starting with it.
+#[derive(Debug, Clone)]
+pub struct CompositeKey(pub Vec<ast::Value>);
+
+impl CompositeKey {
+    fn compare_components(left: &ast::Value, right: &ast::Value) -> cmp::Ordering {
+        use ast::Value::*;
+        match (left, right) {
+            (Integer(a), Integer(b)) => a.cmp(b),
+            (Integer(a), Float(b)) => (*a as f64).total_cmp(b).then(cmp::Ordering::Less),
+            (Float(a), Integer(b)) => a.total_cmp(&(*b as f64)).then(cmp::Ordering::Greater),
+            (Float(a), Float(b)) => a.total_cmp(b),
+            (Text(a), Text(b)) => a.cmp(b),
+            (Boolean(a), Boolean(b)) => a.cmp(b),
+            (Blob(a), Blob(b)) => a.cmp(b),
+            #[cfg(feature = "json")]
+            (Json(a), Json(b)) => a.to_string().cmp(&b.to_string()),
+            #[cfg(feature = "uuid")]
+            (Uuid(a), Uuid(b)) => a.cmp(b),
+            _ => Self::rank(left).cmp(&Self::rank(right)),
+        }
+    }
+
+    /// Orders values of different types.
+    fn rank(value: &ast::Value) -> u8 {
+        match value {
+            ast::Value::Null | ast::Value::Default => 0,
+            ast::Value::Boolean(_) => 1,
+            ast::Value::Integer(_) | ast::Value::Float(_) => 2,
+            ast::Value::Text(_) => 3,
+            ast::Value::Blob(_) => 4,
+            #[cfg(feature = "json")]
+            ast::Value::Json(_) => 5,
+            #[cfg(feature = "uuid")]
+            ast::Value::Uuid(_) => 6,
+        }
+    }
+}
+
+impl Ord for CompositeKey {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        for (left, right) in self.0.iter().zip(&other.0) {
+            let ordering = Self::compare_components(left, right);
+            if ordering != cmp::Ordering::Equal {
+                return ordering;
+            }
+        }
+        self.0.len().cmp(&other.0.len())
+    }
+}
+
+impl PartialOrd for CompositeKey {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for CompositeKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for CompositeKey {}
+
+/// One entry of a JSON export.
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ExportedEntry {
+    key: Key,
+    value: Value,
+}
+
+/// Structural metrics of a tree, as reported by [`BPlusTree::stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    /// Number of levels, counting the leaves; an empty tree has height 1.
+    pub height: usize,
+    pub internal_nodes: usize,
+    pub leaf_nodes: usize,
+    pub keys: usize,
+    /// Average fraction of each leaf's key capacity in use, from 0.0 to 1.0.
+    pub average_leaf_fill: f64,
+}
+
+/// The separator key and new right sibling produced when a node splits.
+type SplitResult<K> = Option<(K, Arc<RwLock<BPlusTreeNode<K>>>)>;
+
+/// The modified copy of a node and the sibling split off from it, if any.
+type ModifyResult<K> = (Arc<RwLock<BPlusTreeNode<K>>>, SplitResult<K>);
+
 /// Represents the B+ Tree structure.
-pub struct BPlusTree {
-    root: Arc<RwLock<Option<Arc<RwLock<BPlusTreeNode>>>>>,
+///
+/// Keys are `storage::Key` integers by default; any `Ord` type works, such as [`CompositeKey`]
+/// for multi-column keys.
+///
+/// Writes are copy-on-write: a node reachable from a root is never modified, so every read
+/// works on a [`TreeSnapshot`] of the root it started from and sees the tree exactly as it was
+/// at that point, however long it runs and whatever writers do meanwhile.
+///
+/// A tree built with [`BPlusTree::with_structural_latch`] runs in concurrent-reader /
+/// single-writer mode: writes hold a tree-wide latch exclusively, and taking a snapshot holds it
+/// shared, so no snapshot is taken while a write is in progress.
+pub struct BPlusTree<K = Key> {
+    root: RwLock<Option<Arc<RwLock<BPlusTreeNode<K>>>>>,
     _buffer_pool: Arc<BufferPool>,
     order: usize,
+    len: AtomicUsize,
+    structural_latch: Option<Arc<RwLock<()>>>,
+    // Bumped by every write that replaces the root
+    version: AtomicU64,
 }
 
-impl BPlusTree {
+impl<K: Ord + Clone> BPlusTree<K> {
     /// Initializes a new B+ Tree with the given buffer pool and order.
     pub fn new(buffer_pool: Arc<BufferPool>, order: usize) -> Result<Self, String> {
         if order < 3 {
@@ -31,166 +158,646 @@ impl BPlusTree {
         let root_node = Arc::new(RwLock::new(BPlusTreeNode {
             keys: Vec::new(),
             children: Vec::new(),
+            values: Vec::new(),
             is_leaf: true,
         }));
 
         Ok(BPlusTree {
-            root: Arc::new(RwLock::new(Some(Arc::clone(&root_node)))),
+            root: RwLock::new(Some(root_node)),
             _buffer_pool: buffer_pool,
             order,
+            len: AtomicUsize::new(0),
+            structural_latch: None,
+            version: AtomicU64::new(0),
         })
     }
 
+    /// Like `new`, but serializes writers against readers with a tree-wide latch.
+    ///
+    /// While a write runs, no reader makes progress. Reads are consistent without the latch,
+    /// since writes are copy-on-write; it is for callers that want writes to exclude readers.
+    pub fn with_structural_latch(
+        buffer_pool: Arc<BufferPool>,
+        order: usize,
+    ) -> Result<Self, String> {
+        let mut tree = Self::new(buffer_pool, order)?;
+        tree.structural_latch = Some(Arc::new(RwLock::new(())));
+        Ok(tree)
+    }
+
     /// Inserts a key into the B+ Tree.
-    pub fn insert(&self, key: Key, value: Value) -> Result<(), String> {
+    ///
+    /// Keys are unique: inserting a key that is already present fails and leaves the
+    /// stored value unchanged.
+    pub fn insert(&self, key: K, value: Value) -> Result<(), String> {
+        self.modify(key, |current| match current {
+            Some(_) => Err("Duplicate key insertion is not allowed".to_string()),
+            None => Ok(Some(value)),
+        })
+    }
+
+    /// Reads, modifies and writes the value stored under `key` in one descent.
+    ///
+    /// `f` receives the current value, or `None` if the key is absent. Returning `Some` inserts
+    /// or overwrites the entry; returning `None` deletes it. Writers are serialized while `f`
+    /// runs, so concurrent callers never lose each other's updates. Deleting does not merge
+    /// underfull leaves.
+    pub fn update_with(
+        &self,
+        key: K,
+        f: impl FnOnce(Option<Value>) -> Option<Value>,
+    ) -> Result<(), String> {
+        self.modify(key, |current| Ok(f(current)))
+    }
+
+    /// Applies `f` to the entry for `key` under the root's write lock, splitting nodes as needed.
+    ///
+    /// Published nodes are never changed: the nodes on the path to the key's leaf are copied,
+    /// the copies are modified, and the new root replaces the old one in a single step.
+    fn modify(
+        &self,
+        key: K,
+        f: impl FnOnce(Option<Value>) -> Result<Option<Value>, String>,
+    ) -> Result<(), String> {
+        let _latch_guard = self.structural_latch.as_ref().map(|l| l.write().unwrap());
         let mut root_guard = self.root.write().unwrap();
 
-        if root_guard.is_none() {
+        let Some(root) = root_guard.as_ref().map(Arc::clone) else {
             // Tree is empty, create a new leaf node
-            let new_leaf = Arc::new(RwLock::new(BPlusTreeNode {
-                keys: vec![key],
-                children: Vec::new(),
-                is_leaf: true,
-            }));
-            *root_guard = Some(Arc::clone(&new_leaf));
+            if let Some(value) = f(None)? {
+                let new_leaf = Arc::new(RwLock::new(BPlusTreeNode {
+                    keys: vec![key],
+                    children: Vec::new(),
+                    values: vec![value],
+                    is_leaf: true,
+                }));
+                *root_guard = Some(new_leaf);
+                self.len.fetch_add(1, Ordering::SeqCst);
+                self.version.fetch_add(1, Ordering::SeqCst);
+            }
             return Ok(());
-        }
-
-        let split = self.insert_recursive(Arc::clone(root_guard.as_ref().unwrap()), key, value)?;
+        };
 
-        if let Some((new_key, new_child)) = split {
+        let Some((new_root, split)) = self.modify_recursive(&root, key, f)? else {
+            return Ok(());
+        };
+        let new_root = match split {
             // Create a new root
-            let new_root = Arc::new(RwLock::new(BPlusTreeNode {
+            Some((new_key, new_child)) => Arc::new(RwLock::new(BPlusTreeNode {
                 keys: vec![new_key],
-                children: vec![Arc::clone(root_guard.as_ref().unwrap()), new_child],
+                children: vec![new_root, new_child],
+                values: Vec::new(),
                 is_leaf: false,
+            })),
+            None => new_root,
+        };
+        *root_guard = Some(new_root);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Removes every entry for which `f` returns false and returns how many were removed.
+    ///
+    /// The leaves are filtered in one pass under the root's write lock and the entries kept are
+    /// packed into freshly built nodes, so unlike deleting them one at a time with
+    /// `update_with`, removing many entries leaves no underfull leaves behind.
+    pub fn retain(&self, f: impl Fn(&K, &Value) -> bool) -> std::io::Result<usize> {
+        let _latch_guard = self.structural_latch.as_ref().map(|l| l.write().unwrap());
+        let mut root_guard = self.root.write().unwrap();
+        let Some(root) = root_guard.as_ref() else {
+            return Ok(0);
+        };
+
+        let mut kept = Vec::new();
+        let mut removed = 0;
+        // Children are pushed right to left, so leaves are popped in ascending key order
+        let mut pending = vec![Arc::clone(root)];
+        while let Some(node) = pending.pop() {
+            let node_guard = node.read().unwrap();
+            if node_guard.is_leaf {
+                for (key, value) in node_guard.keys.iter().zip(&node_guard.values) {
+                    if f(key, value) {
+                        kept.push((key.clone(), *value));
+                    } else {
+                        removed += 1;
+                    }
+                }
+            } else {
+                pending.extend(node_guard.children.iter().rev().map(Arc::clone));
+            }
+        }
+
+        if removed > 0 {
+            // Scans that started earlier keep reading the old nodes
+            *root_guard = Some(self.build(kept));
+            self.len.fetch_sub(removed, Ordering::SeqCst);
+            self.version.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(removed)
+    }
+
+    /// Builds a tree from entries sorted by key and returns its root. Every level is split into
+    /// as few nodes as fit, filled as evenly as possible, so all leaves are at the same depth.
+    fn build(&self, entries: Vec<(K, Value)>) -> Arc<RwLock<BPlusTreeNode<K>>> {
+        if entries.is_empty() {
+            return Arc::new(RwLock::new(BPlusTreeNode {
+                keys: Vec::new(),
+                children: Vec::new(),
+                values: Vec::new(),
+                is_leaf: true,
             }));
-            *root_guard = Some(Arc::clone(&new_root));
         }
 
-        Ok(())
+        // Each node is paired with the smallest key below it, which is its separator
+        let mut level: Vec<(K, Arc<RwLock<BPlusTreeNode<K>>>)> =
+            even_chunks(entries, self.order - 1)
+                .into_iter()
+                .map(|chunk| {
+                    let (keys, values): (Vec<K>, Vec<Value>) = chunk.into_iter().unzip();
+                    let node = BPlusTreeNode {
+                        keys,
+                        children: Vec::new(),
+                        values,
+                        is_leaf: true,
+                    };
+                    (node.keys[0].clone(), Arc::new(RwLock::new(node)))
+                })
+                .collect();
+        while level.len() > 1 {
+            level = even_chunks(level, self.order)
+                .into_iter()
+                .map(|chunk| {
+                    let first_key = chunk[0].0.clone();
+                    let (mut keys, children): (Vec<K>, Vec<_>) = chunk.into_iter().unzip();
+                    keys.remove(0);
+                    let node = BPlusTreeNode {
+                        keys,
+                        children,
+                        values: Vec::new(),
+                        is_leaf: false,
+                    };
+                    (first_key, Arc::new(RwLock::new(node)))
+                })
+                .collect();
+        }
+        level.pop().unwrap().1
+    }
+
+    /// Returns the number of entries in the tree without scanning it.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the tree has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    /// Recursively inserts a key-value pair and handles node splits.
-    fn insert_recursive(
+    /// Recursively applies `f` to the entry for `key` in the subtree under `node`.
+    ///
+    /// Returns a modified copy of `node` and the sibling split off from it, if any, or `None`
+    /// if nothing changed. `node` itself is left as it was.
+    fn modify_recursive(
         &self,
-        node: Arc<RwLock<BPlusTreeNode>>,
-        key: Key,
-        value: Value,
-    ) -> Result<Option<(Key, Arc<RwLock<BPlusTreeNode>>)>, String> {
-        let mut node_guard = node.write().unwrap();
+        node: &Arc<RwLock<BPlusTreeNode<K>>>,
+        key: K,
+        f: impl FnOnce(Option<Value>) -> Result<Option<Value>, String>,
+    ) -> Result<Option<ModifyResult<K>>, String> {
+        let node_guard = node.read().unwrap();
 
         if node_guard.is_leaf {
-            // Insert the key in the leaf node
-            if node_guard.keys.contains(&key) {
-                return Err("Duplicate key insertion is not allowed".to_string());
+            let found = node_guard.keys.binary_search(&key);
+            let new_value = f(found.ok().map(|pos| node_guard.values[pos]))?;
+            let mut leaf = BPlusTreeNode {
+                keys: node_guard.keys.clone(),
+                children: Vec::new(),
+                values: node_guard.values.clone(),
+                is_leaf: true,
+            };
+            match (found, new_value) {
+                (Ok(pos), Some(value)) => leaf.values[pos] = value,
+                (Ok(pos), None) => {
+                    leaf.keys.remove(pos);
+                    leaf.values.remove(pos);
+                    self.len.fetch_sub(1, Ordering::SeqCst);
+                }
+                (Err(pos), Some(value)) => {
+                    leaf.keys.insert(pos, key);
+                    leaf.values.insert(pos, value);
+                    self.len.fetch_add(1, Ordering::SeqCst);
+                }
+                (Err(_), None) => return Ok(None),
             }
-            node_guard.keys.push(key);
-            node_guard.keys.sort();
 
-            if node_guard.keys.len() > self.order - 1 {
+            let mut split = None;
+            if leaf.keys.len() > self.order - 1 {
                 // Split the leaf node
                 let mid = self.order / 2;
-                let split_key = node_guard.keys[mid].clone();
+                let split_key = leaf.keys[mid].clone();
 
                 let new_leaf = Arc::new(RwLock::new(BPlusTreeNode {
-                    keys: node_guard.keys.split_off(mid),
+                    keys: leaf.keys.split_off(mid),
                     children: Vec::new(),
+                    values: leaf.values.split_off(mid),
                     is_leaf: true,
                 }));
-
-                return Ok(Some((split_key, new_leaf)));
+                split = Some((split_key, new_leaf));
             }
 
-            Ok(None)
+            Ok(Some((Arc::new(RwLock::new(leaf)), split)))
         } else {
             // Internal node: find the child to descend
-            let pos = node_guard
-                .keys
-                .iter()
-                .position(|k| k >= &key)
-                .unwrap_or(node_guard.keys.len());
-
-            if pos < node_guard.children.len() {
-                let child = Arc::clone(&node_guard.children[pos]);
-                drop(node_guard); // Release the lock before recursive call
-
-                let split = self.insert_recursive(child, key, value)?;
-
-                if let Some((new_key, new_child)) = split {
-                    // Insert the new key and child into the current node
-                    let mut node_guard = node.write().unwrap();
-                    node_guard.keys.push(new_key);
-                    node_guard.keys.sort();
-                    node_guard.children.push(new_child);
-                    node_guard.children.sort_by_key(|c| {
-                        let c_guard = c.read().unwrap();
-                        c_guard.keys.first().cloned().unwrap_or(new_key.clone())
-                    });
-
-                    if node_guard.keys.len() > self.order - 1 {
-                        // Split the internal node
-                        let mid = self.order / 2;
-                        let split_key = node_guard.keys[mid].clone();
-
-                        let new_internal = Arc::new(RwLock::new(BPlusTreeNode {
-                            keys: node_guard.keys.split_off(mid + 1),
-                            children: node_guard.children.split_off(mid + 1),
-                            is_leaf: false,
-                        }));
-
-                        return Ok(Some((split_key, new_internal)));
-                    }
+            let pos = node_guard.child_index(&key);
+            let Some(child) = node_guard.children.get(pos) else {
+                return Ok(None);
+            };
+            let Some((new_child, split)) = self.modify_recursive(child, key, f)? else {
+                return Ok(None);
+            };
+
+            let mut internal = BPlusTreeNode {
+                keys: node_guard.keys.clone(),
+                children: node_guard.children.clone(),
+                values: Vec::new(),
+                is_leaf: false,
+            };
+            internal.children[pos] = new_child;
+
+            let mut split_internal = None;
+            if let Some((new_key, new_child)) = split {
+                // The new sibling holds keys >= new_key, so it goes right of the split child
+                internal.keys.insert(pos, new_key);
+                internal.children.insert(pos + 1, new_child);
+
+                if internal.keys.len() > self.order - 1 {
+                    // Split the internal node, moving the middle key up to the parent
+                    let mid = self.order / 2;
+                    let split_key = internal.keys[mid].clone();
+
+                    let new_internal = Arc::new(RwLock::new(BPlusTreeNode {
+                        keys: internal.keys.split_off(mid + 1),
+                        children: internal.children.split_off(mid + 1),
+                        values: Vec::new(),
+                        is_leaf: false,
+                    }));
+                    internal.keys.truncate(mid);
+                    split_internal = Some((split_key, new_internal));
                 }
             }
 
-            Ok(None)
+            Ok(Some((Arc::new(RwLock::new(internal)), split_internal)))
         }
     }
 
     /// Searches for a value by its key in the B+ Tree.
-    pub fn search(&self, key: Key) -> Result<Option<Value>, String> {
+    pub fn search(&self, key: K) -> Result<Option<Value>, String> {
+        Ok(self.snapshot().search(&key))
+    }
+
+    /// Pins the current root, giving a read-only view of the tree that later writes don't
+    /// change.
+    pub fn snapshot(&self) -> TreeSnapshot<K> {
+        let _latch_guard = self.structural_latch.as_ref().map(|l| l.read().unwrap());
+        let root_guard = self.root.read().unwrap();
+        TreeSnapshot {
+            root: root_guard.as_ref().map(Arc::clone),
+            // Writers bump the version while holding the root's write lock, so it matches the root
+            version: self.version.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Returns how many writes have changed the tree; a snapshot reports the version it saw.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// Returns an iterator over all key-value pairs in ascending key order.
+    pub fn iter(&self) -> BPlusTreeIter<K> {
+        self.scan(None, None, None)
+    }
+
+    /// Like `iter`, but stops with an error once `cancellation` is cancelled.
+    pub fn iter_with_cancellation(&self, cancellation: CancellationToken) -> BPlusTreeIter<K> {
+        self.scan(None, None, Some(cancellation))
+    }
+
+    /// Returns a lazy iterator over the key-value pairs with `low <= key <= high`.
+    pub fn range_iter(&self, low: K, high: K) -> BPlusTreeIter<K> {
+        self.scan(Some(low), Some(high), None)
+    }
+
+    /// Collects all key-value pairs with `low <= key <= high` in ascending key order.
+    pub fn range(&self, low: K, high: K) -> Result<Vec<(K, Value)>, String> {
+        self.scan(Some(low), Some(high), None).collect()
+    }
+
+    /// Counts the keys with `low <= key <= high` without copying any entries.
+    pub fn count_range(&self, low: K, high: K) -> std::io::Result<usize> {
+        Ok(self.snapshot().count_range(&low, &high))
+    }
+
+    /// Like `range`, but stops with an error once `cancellation` is cancelled.
+    pub fn range_with_cancellation(
+        &self,
+        low: K,
+        high: K,
+        cancellation: CancellationToken,
+    ) -> Result<Vec<(K, Value)>, String> {
+        self.scan(Some(low), Some(high), Some(cancellation))
+            .collect()
+    }
+
+    /// Traverses the whole tree and reports its height, node counts and leaf fill factor.
+    pub fn stats(&self) -> std::io::Result<TreeStats> {
+        let _latch_guard = self.structural_latch.as_ref().map(|l| l.read().unwrap());
         let root_guard = self.root.read().unwrap();
 
-        if root_guard.is_none() {
-            return Ok(None);
+        let mut stats = TreeStats {
+            height: 0,
+            internal_nodes: 0,
+            leaf_nodes: 0,
+            keys: 0,
+            average_leaf_fill: 0.0,
+        };
+        let mut level = root_guard.iter().map(Arc::clone).collect::<Vec<_>>();
+        while !level.is_empty() {
+            stats.height += 1;
+            let mut next_level = Vec::new();
+            for node in level {
+                let node_guard = node.read().unwrap();
+                if node_guard.is_leaf {
+                    stats.leaf_nodes += 1;
+                    stats.keys += node_guard.keys.len();
+                } else {
+                    stats.internal_nodes += 1;
+                    next_level.extend(node_guard.children.iter().map(Arc::clone));
+                }
+            }
+            level = next_level;
         }
 
-        self.search_recursive(Arc::clone(root_guard.as_ref().unwrap()), key)
+        if stats.leaf_nodes > 0 {
+            let capacity = stats.leaf_nodes * (self.order - 1);
+            stats.average_leaf_fill = stats.keys as f64 / capacity as f64;
+        }
+        Ok(stats)
     }
 
-    /// Recursively searches for a key.
-    fn search_recursive(
+    /// Starts a scan of a snapshot taken now.
+    fn scan(
         &self,
-        node: Arc<RwLock<BPlusTreeNode>>,
-        key: Key,
-    ) -> Result<Option<Value>, String> {
-        let node_guard = node.read().unwrap();
+        low: Option<K>,
+        high: Option<K>,
+        cancellation: Option<CancellationToken>,
+    ) -> BPlusTreeIter<K> {
+        self.snapshot().scan(low, high, cancellation)
+    }
+}
 
-        if node_guard.is_leaf {
-            // Search in the leaf node
-            match node_guard.keys.binary_search(&key) {
-                Ok(_idx) => {
-                    // Assuming values are stored alongside keys. Adjust accordingly.
-                    // Here, for simplicity, returning a dummy Value.
-                    Ok(Some(Value::from(key as u64 * 10)))
+/// Splits `items` into the fewest groups of at most `capacity`, with sizes differing by at most
+/// one.
+fn even_chunks<T>(items: Vec<T>, capacity: usize) -> Vec<Vec<T>> {
+    let groups = items.len().div_ceil(capacity).max(1);
+    let (size, extra) = (items.len() / groups, items.len() % groups);
+    let mut items = items.into_iter();
+    (0..groups)
+        .map(|i| items.by_ref().take(size + usize::from(i < extra)).collect())
+        .collect()
+}
+
+/// The tree as it was when [`BPlusTree::snapshot`] was called.
+///
+/// A snapshot holds its root, and through it every node it can reach, so it stays readable
+/// and unchanged while writers replace the tree's root. Iterators made from it hold the root
+/// too and may outlive it.
+#[derive(Clone)]
+pub struct TreeSnapshot<K = Key> {
+    root: Option<Arc<RwLock<BPlusTreeNode<K>>>>,
+    version: u64,
+}
+
+impl<K: Ord + Clone> TreeSnapshot<K> {
+    /// Returns the tree's version when the snapshot was taken.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Searches for a value by its key.
+    pub fn search(&self, key: &K) -> Option<Value> {
+        let mut node = Arc::clone(self.root.as_ref()?);
+        loop {
+            let child = {
+                let node_guard = node.read().unwrap();
+                if node_guard.is_leaf {
+                    let idx = node_guard.keys.binary_search(key).ok()?;
+                    return Some(node_guard.values[idx]);
+                }
+                Arc::clone(node_guard.children.get(node_guard.child_index(key))?)
+            };
+            node = child;
+        }
+    }
+
+    /// Returns an iterator over all key-value pairs in ascending key order.
+    pub fn iter(&self) -> BPlusTreeIter<K> {
+        self.scan(None, None, None)
+    }
+
+    /// Returns a lazy iterator over the key-value pairs with `low <= key <= high`.
+    pub fn range_iter(&self, low: K, high: K) -> BPlusTreeIter<K> {
+        self.scan(Some(low), Some(high), None)
+    }
+
+    /// Counts the keys with `low <= key <= high`, visiting only the subtrees that overlap the
+    /// range and binary searching the keys of each leaf.
+    pub fn count_range(&self, low: &K, high: &K) -> usize {
+        match &self.root {
+            Some(root) if low <= high => count_in_range(root, low, high),
+            _ => 0,
+        }
+    }
+
+    /// Starts a scan at the first leaf that may contain `low`.
+    fn scan(
+        &self,
+        low: Option<K>,
+        high: Option<K>,
+        cancellation: Option<CancellationToken>,
+    ) -> BPlusTreeIter<K> {
+        let mut iter = BPlusTreeIter {
+            stack: Vec::new(),
+            buffer: VecDeque::new(),
+            low,
+            high,
+            cancellation,
+            finished: false,
+        };
+        match &self.root {
+            Some(root) => iter.descend(Arc::clone(root)),
+            None => iter.finished = true,
+        }
+        iter
+    }
+}
+
+#[cfg(feature = "json")]
+impl BPlusTree {
+    /// Serializes every entry, in key order, as a JSON array of `{"key": k, "value": v}`.
+    pub fn export_json(&self) -> std::io::Result<String> {
+        let entries = self
+            .iter()
+            .map(|entry| entry.map(|(key, value)| ExportedEntry { key, value }))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(std::io::Error::other)?;
+        serde_json::to_string(&entries)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Builds a tree from a dump produced by `export_json`.
+    ///
+    /// The dump must list its keys in strictly ascending order, as `export_json` writes them,
+    /// so the nodes are packed in one pass instead of inserting the entries one at a time.
+    pub fn import_json(
+        buffer_pool: Arc<BufferPool>,
+        order: usize,
+        json: &str,
+    ) -> std::io::Result<Self> {
+        let entries: Vec<ExportedEntry> = serde_json::from_str(json)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].key >= pair[1].key) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Key {} follows key {} out of order",
+                    pair[1].key, pair[0].key
+                ),
+            ));
+        }
+
+        let tree = Self::new(buffer_pool, order).map_err(std::io::Error::other)?;
+        let len = entries.len();
+        let root = tree.build(
+            entries
+                .into_iter()
+                .map(|entry| (entry.key, entry.value))
+                .collect(),
+        );
+        *tree.root.write().unwrap() = Some(root);
+        tree.len.store(len, Ordering::SeqCst);
+        Ok(tree)
+    }
+}
+
+fn count_in_range<K: Ord>(node: &Arc<RwLock<BPlusTreeNode<K>>>, low: &K, high: &K) -> usize {
+    let node_guard = node.read().unwrap();
+    if node_guard.is_leaf {
+        let start = node_guard.keys.partition_point(|key| key < low);
+        let end = node_guard.keys.partition_point(|key| key <= high);
+        return end.saturating_sub(start);
+    }
+    let first = node_guard.child_index(low);
+    let last = node_guard.child_index(high);
+    node_guard
+        .children
+        .iter()
+        .take(last + 1)
+        .skip(first)
+        .map(|child| count_in_range(child, low, high))
+        .sum()
+}
+
+/// Iterator over the entries of a B+ Tree, yielding them in ascending key order.
+///
+/// The iterator reads the snapshot it was made from, so entries written after it started are
+/// not seen. Leaves are visited one at a time and their entries are buffered, so a node is
+/// only read-locked while it is being copied.
+pub struct BPlusTreeIter<K = Key> {
+    // Internal nodes on the path to the current leaf, with the next child index to visit
+    stack: Vec<(Arc<RwLock<BPlusTreeNode<K>>>, usize)>,
+    buffer: VecDeque<(K, Value)>,
+    low: Option<K>,
+    high: Option<K>,
+    cancellation: Option<CancellationToken>,
+    finished: bool,
+}
+
+impl<K: Ord + Clone> BPlusTreeIter<K> {
+    /// Descends from `node` to the leftmost leaf that may hold keys `>= low` and buffers the
+    /// entries that are in range.
+    fn descend(&mut self, node: Arc<RwLock<BPlusTreeNode<K>>>) {
+        let mut current = node;
+        loop {
+            let (child, idx) = {
+                let node_guard = current.read().unwrap();
+                if node_guard.is_leaf {
+                    let low = self.low.as_ref();
+                    let entries = node_guard
+                        .keys
+                        .iter()
+                        .zip(node_guard.values.iter())
+                        .filter(|(key, _)| low.is_none_or(|low| *key >= low))
+                        .map(|(key, value)| (key.clone(), *value));
+                    self.buffer.extend(entries);
+                    return;
                 }
-                Err(_) => Ok(None),
+
+                let idx = self
+                    .low
+                    .as_ref()
+                    .map_or(0, |low| node_guard.child_index(low));
+                match node_guard.children.get(idx) {
+                    Some(child) => (Arc::clone(child), idx),
+                    None => return,
+                }
+            };
+            self.stack.push((current, idx + 1));
+            current = child;
+        }
+    }
+
+    /// Moves to the next leaf in key order. Returns false once every leaf has been visited.
+    fn advance_leaf(&mut self) -> bool {
+        while let Some((node, next_idx)) = self.stack.pop() {
+            let child = {
+                let node_guard = node.read().unwrap();
+                node_guard.children.get(next_idx).map(Arc::clone)
+            };
+            if let Some(child) = child {
+                self.stack.push((node, next_idx + 1));
+                self.descend(child);
+                return true;
             }
-        } else {
-            // Internal node: find the child to descend
-            let pos = node_guard
-                .keys
-                .iter()
-                .position(|k| k >= &key)
-                .unwrap_or(node_guard.keys.len());
-
-            if pos < node_guard.children.len() {
-                let child = Arc::clone(&node_guard.children[pos]);
-                drop(node_guard); // Release the lock before recursive call
-                self.search_recursive(child, key)
-            } else {
-                Ok(None)
+        }
+        false
+    }
+}
+
+impl<K: Ord + Clone> Iterator for BPlusTreeIter<K> {
+    type Item = Result<(K, Value), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.finished {
+                return None;
+            }
+
+            if let Some(cancellation) = &self.cancellation {
+                if cancellation.is_cancelled() {
+                    self.finished = true;
+                    return Some(Err("Operation cancelled".to_string()));
+                }
+            }
+
+            if let Some((key, value)) = self.buffer.pop_front() {
+                if self.high.as_ref().is_some_and(|high| key > *high) {
+                    self.finished = true;
+                    return None;
+                }
+                return Some(Ok((key, value)));
+            }
+
+            if !self.advance_leaf() {
+                self.finished = true;
             }
         }
     }
@@ -201,6 +808,8 @@ mod tests {
     use crate::storage::StorageEngine;
 
     use super::*;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
     use std::fs;
     use std::sync::Arc;
     use std::thread;
@@ -319,4 +928,616 @@ mod tests {
         let _ = fs::remove_file(test_db);
         println!("Multi-threaded test completed successfully.");
     }
+
+    /// Tests that iteration and range scans return entries in key order.
+    #[test]
+    fn test_iter_and_range() {
+        let test_db = "test_iter_and_range.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+        for i in 0..100 {
+            tree.insert(i, Value::from((i * 10) as u64))
+                .expect("Failed to insert key-value pair");
+        }
+
+        let all: Vec<(Key, Value)> = tree
+            .iter()
+            .collect::<Result<_, _>>()
+            .expect("Failed to iterate");
+        let expected: Vec<(Key, Value)> = (0..100).map(|i| (i, (i * 10) as u64)).collect();
+        assert_eq!(all, expected);
+
+        let range = tree.range(10, 20).expect("Failed to scan range");
+        assert_eq!(range, expected[10..=20].to_vec());
+        assert!(tree.range(200, 300).unwrap().is_empty());
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that `count_range` agrees with the length of `range` for every pair of bounds,
+    /// including bounds between, before and after the keys, and inverted ones.
+    #[test]
+    fn test_count_range() {
+        let test_db = "test_count_range.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+        assert_eq!(tree.count_range(0, 10).unwrap(), 0);
+        for i in (0..60).map(|i| i * 3) {
+            tree.insert(i, i as u64).unwrap();
+        }
+
+        for low in 0..190 {
+            for high in (0..190).step_by(7) {
+                assert_eq!(
+                    tree.count_range(low, high).unwrap(),
+                    tree.range(low, high).unwrap().len(),
+                    "{}..={}",
+                    low,
+                    high
+                );
+            }
+        }
+        assert_eq!(tree.count_range(0, Key::MAX).unwrap(), 60);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that cancelling a token stops a scan that is already in progress.
+    #[test]
+    fn test_cancel_mid_scan() {
+        let test_db = "test_cancel_mid_scan.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+        for i in 0..100 {
+            tree.insert(i, Value::from((i * 10) as u64))
+                .expect("Failed to insert key-value pair");
+        }
+
+        let token = CancellationToken::new();
+        let mut iter = tree.iter_with_cancellation(token.clone());
+        for i in 0..10 {
+            assert_eq!(iter.next(), Some(Ok((i, (i * 10) as u64))));
+        }
+
+        token.cancel();
+        assert_eq!(iter.next(), Some(Err("Operation cancelled".to_string())));
+        assert_eq!(iter.next(), None);
+
+        let result = tree.range_with_cancellation(0, 99, token);
+        assert_eq!(result, Err("Operation cancelled".to_string()));
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that inserting an existing key is rejected and keeps the original value.
+    #[test]
+    fn test_duplicate_insert_is_rejected() {
+        let test_db = "test_duplicate_insert.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+
+        tree.insert(7, 70).expect("Failed to insert key-value pair");
+        assert_eq!(
+            tree.insert(7, 700),
+            Err("Duplicate key insertion is not allowed".to_string())
+        );
+        assert_eq!(tree.search(7).unwrap(), Some(70));
+
+        let entries: Vec<(Key, Value)> = tree.iter().collect::<Result<_, _>>().unwrap();
+        assert_eq!(entries, vec![(7, 70)]);
+        assert_eq!(tree.len(), 1);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that update_with inserts, overwrites and deletes, and that concurrent increments
+    /// of the same keys are not lost.
+    #[test]
+    fn test_update_with() {
+        let test_db = "test_update_with.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = Arc::new(
+            BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+                .expect("Failed to initialize BPlusTree"),
+        );
+
+        let mut handles = vec![];
+        for _ in 0..4 {
+            let tree_clone = Arc::clone(&tree);
+            handles.push(thread::spawn(move || {
+                for _ in 0..50 {
+                    for key in 0..10 {
+                        tree_clone
+                            .update_with(key, |count| Some(count.unwrap_or(0) + 1))
+                            .expect("Failed to update counter");
+                    }
+                }
+            }));
+        }
+        for handle in handles {
+            handle.join().expect("Failed to join update thread");
+        }
+        for key in 0..10 {
+            assert_eq!(tree.search(key).unwrap(), Some(200));
+        }
+        assert_eq!(tree.len(), 10);
+
+        tree.update_with(3, |_| None).unwrap();
+        tree.update_with(42, |_| None).unwrap();
+        tree.update_with(4, |count| count.map(|count| count * 2))
+            .unwrap();
+        assert_eq!(tree.search(3).unwrap(), None);
+        assert_eq!(tree.search(42).unwrap(), None);
+        assert_eq!(tree.search(4).unwrap(), Some(400));
+        assert_eq!(tree.len(), 9);
+        let keys: Vec<Key> = tree.iter().map(|entry| entry.unwrap().0).collect();
+        assert_eq!(keys, vec![0, 1, 2, 4, 5, 6, 7, 8, 9]);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Collects every separator key stored in the internal nodes below `node`.
+    fn separator_keys(node: &Arc<RwLock<BPlusTreeNode<Key>>>) -> Vec<Key> {
+        let node_guard = node.read().unwrap();
+        if node_guard.is_leaf {
+            return Vec::new();
+        }
+        let mut keys = node_guard.keys.clone();
+        for child in &node_guard.children {
+            keys.extend(separator_keys(child));
+        }
+        keys
+    }
+
+    /// Tests that keys which became separators during splits are found and stay unique.
+    #[test]
+    fn test_search_separator_keys() {
+        let test_db = "test_search_separator_keys.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+
+        // Interleave ascending and descending inserts so splits happen on both sides
+        let keys: Vec<Key> = (0..50).flat_map(|i| [i, 199 - i]).collect();
+        for &key in &keys {
+            tree.insert(key, (key * 10) as u64)
+                .expect("Failed to insert key-value pair");
+        }
+
+        let root = Arc::clone(tree.root.read().unwrap().as_ref().unwrap());
+        let separators = separator_keys(&root);
+        assert!(!separators.is_empty());
+        for key in separators {
+            assert_eq!(tree.search(key).unwrap(), Some((key * 10) as u64));
+            assert!(tree.insert(key, 0).is_err());
+            assert_eq!(
+                tree.range(key, key).unwrap(),
+                vec![(key, (key * 10) as u64)]
+            );
+        }
+
+        let mut expected = keys.clone();
+        expected.sort();
+        let scanned: Vec<Key> = tree.iter().map(|entry| entry.unwrap().0).collect();
+        assert_eq!(scanned, expected);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that readers running against a writer on a latched tree always see a
+    /// consistent, ascending snapshot that includes every key inserted before they started.
+    #[test]
+    fn test_structural_latch_concurrent_readers() {
+        let test_db = "test_structural_latch.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = Arc::new(
+            BPlusTree::with_structural_latch(Arc::clone(&buffer_pool), ORDER)
+                .expect("Failed to initialize BPlusTree"),
+        );
+        let inserted = Arc::new(AtomicUsize::new(0));
+        const KEYS: Key = 2000;
+
+        let writer = {
+            let tree = Arc::clone(&tree);
+            let inserted = Arc::clone(&inserted);
+            thread::spawn(move || {
+                // 7 is coprime with KEYS, so this visits every key once in a scattered order
+                for i in 0..KEYS {
+                    let key = (i * 7) % KEYS;
+                    tree.insert(key, (key * 10) as u64)
+                        .expect("Failed to insert key-value pair");
+                    inserted.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let tree = Arc::clone(&tree);
+                let inserted = Arc::clone(&inserted);
+                thread::spawn(move || loop {
+                    let before = inserted.load(Ordering::SeqCst);
+                    let entries: Vec<(Key, Value)> = tree.iter().collect::<Result<_, _>>().unwrap();
+                    assert!(entries.windows(2).all(|pair| pair[0].0 < pair[1].0));
+                    assert!(entries
+                        .iter()
+                        .all(|(key, value)| *value == (key * 10) as u64));
+                    assert!(entries.len() >= before);
+                    if before == KEYS as usize {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().expect("Failed to join writer thread");
+        for reader in readers {
+            reader.join().expect("Failed to join reader thread");
+        }
+        assert_eq!(tree.len(), KEYS as usize);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that snapshots and scans started before a concurrent writer see exactly the
+    /// entries present when they started, while new readers see the writes.
+    #[test]
+    fn test_snapshot_isolation() {
+        let test_db = "test_tree_snapshot.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = Arc::new(
+            BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+                .expect("Failed to initialize BPlusTree"),
+        );
+        const KEYS: Key = 1000;
+        for i in 0..KEYS {
+            tree.insert(2 * i, (2 * i) as u64 + 1).unwrap();
+        }
+        let original: Vec<(Key, Value)> = (0..KEYS).map(|i| (2 * i, (2 * i) as u64 + 1)).collect();
+
+        let snapshot = tree.snapshot();
+        assert_eq!(snapshot.version(), tree.version());
+        let early_scan = tree.iter();
+
+        let writer = {
+            let tree = Arc::clone(&tree);
+            thread::spawn(move || {
+                // Insert every odd key and delete or overwrite every even one
+                for i in 0..KEYS {
+                    tree.insert(2 * i + 1, 0).unwrap();
+                    if i % 2 == 0 {
+                        tree.update_with(2 * i, |_| None).unwrap();
+                    } else {
+                        tree.update_with(2 * i, |_| Some(0)).unwrap();
+                    }
+                }
+            })
+        };
+        let scanners: Vec<_> = [snapshot.iter(), snapshot.range_iter(100, 899), early_scan]
+            .into_iter()
+            .map(|scan| {
+                thread::spawn(move || {
+                    scan.map(|entry| {
+                        thread::yield_now();
+                        entry.unwrap()
+                    })
+                    .collect::<Vec<(Key, Value)>>()
+                })
+            })
+            .collect();
+
+        writer.join().expect("Failed to join writer thread");
+        let results: Vec<Vec<(Key, Value)>> = scanners
+            .into_iter()
+            .map(|scanner| scanner.join().expect("Failed to join scanner thread"))
+            .collect();
+        assert_eq!(results[0], original);
+        assert_eq!(results[1], original[50..450].to_vec());
+        assert_eq!(results[2], original);
+
+        assert_eq!(snapshot.search(&4), Some(5));
+        assert_eq!(snapshot.search(&5), None);
+        assert_eq!(tree.search(4).unwrap(), None);
+        assert_eq!(tree.search(5).unwrap(), Some(0));
+        assert_eq!(tree.version() - snapshot.version(), 2 * KEYS as u64);
+        assert_eq!(tree.len(), KEYS as usize + KEYS as usize / 2);
+        assert_eq!(snapshot.iter().count(), KEYS as usize);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that a JSON export imports into a fresh tree with the same entries, and that a dump
+    /// out of key order is rejected.
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_export_import_json() {
+        let test_db = "test_export_json.db";
+        let import_db = "test_import_json.db";
+        let _ = fs::remove_file(test_db);
+        let _ = fs::remove_file(import_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+        for key in [5, -3, 42, 0, 17, 8, 99, 23] {
+            tree.insert(key, (key * 10) as u64).unwrap();
+        }
+
+        let json = tree.export_json().unwrap();
+        assert!(json.starts_with(r#"[{"key":-3,"value":"#), "{}", json);
+
+        let import_pool = Arc::new(BufferPool::new(100, StorageEngine::new(import_db).unwrap()));
+        let imported = BPlusTree::import_json(import_pool, ORDER, &json).unwrap();
+        assert_eq!(imported.len(), tree.len());
+        let original: Vec<(Key, Value)> = tree.iter().collect::<Result<_, _>>().unwrap();
+        let copied: Vec<(Key, Value)> = imported.iter().collect::<Result<_, _>>().unwrap();
+        assert_eq!(original, copied);
+
+        let duplicate = r#"[{"key":1,"value":1},{"key":1,"value":2}]"#;
+        let err = BPlusTree::import_json(Arc::clone(&buffer_pool), ORDER, duplicate)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "Key 1 follows key 1 out of order");
+        let unsorted = r#"[{"key":2,"value":1},{"key":1,"value":2}]"#;
+        let err = BPlusTree::import_json(Arc::clone(&buffer_pool), ORDER, unsorted)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // The packed tree takes further writes like one built by inserting
+        imported.insert(1000, 1).unwrap();
+        assert_eq!(imported.len(), tree.len() + 1);
+        assert_eq!(imported.search(1000), Ok(Some(1)));
+
+        let _ = fs::remove_file(test_db);
+        let _ = fs::remove_file(import_db);
+    }
+
+    /// Tests that the height grows by one level each time the root splits.
+    #[test]
+    fn test_stats() {
+        let test_db = "test_tree_stats.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+
+        let stats = tree.stats().unwrap();
+        assert_eq!((stats.height, stats.leaf_nodes, stats.keys), (1, 1, 0));
+        assert_eq!(stats.average_leaf_fill, 0.0);
+
+        // A leaf of an order-4 tree holds 3 keys, so the 4th insert splits the root
+        for key in 0..3 {
+            tree.insert(key, key as u64).unwrap();
+        }
+        assert_eq!(tree.stats().unwrap().height, 1);
+        tree.insert(3, 3).unwrap();
+        let stats = tree.stats().unwrap();
+        assert_eq!(stats.height, 2);
+        assert_eq!((stats.internal_nodes, stats.leaf_nodes), (1, 2));
+
+        for key in 4..100 {
+            tree.insert(key, key as u64).unwrap();
+        }
+        let stats = tree.stats().unwrap();
+        assert!(stats.height >= 4, "{:?}", stats);
+        assert_eq!(stats.keys, 100);
+        assert!(stats.internal_nodes > 0);
+        assert!(stats.average_leaf_fill > 0.0 && stats.average_leaf_fill <= 1.0);
+        assert_eq!(
+            stats.average_leaf_fill,
+            100.0 / (stats.leaf_nodes * (ORDER - 1)) as f64
+        );
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Records the depth of every leaf below `node` and checks that no leaf is less than half
+    /// full unless it is the root.
+    fn leaf_depths(node: &Arc<RwLock<BPlusTreeNode<Key>>>, depth: usize, depths: &mut Vec<usize>) {
+        let node_guard = node.read().unwrap();
+        if node_guard.is_leaf {
+            assert!(depth == 0 || node_guard.keys.len() >= (ORDER - 1) / 2);
+            depths.push(depth);
+        } else {
+            assert_eq!(node_guard.children.len(), node_guard.keys.len() + 1);
+            for child in &node_guard.children {
+                leaf_depths(child, depth + 1, depths);
+            }
+        }
+    }
+
+    /// Tests that `retain` removes the rejected entries in one pass and leaves a balanced tree
+    /// that still routes searches and inserts correctly.
+    #[test]
+    fn test_retain() {
+        let test_db = "test_tree_retain.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+        for key in (0..100).flat_map(|i| [i, 199 - i]) {
+            tree.insert(key, key as u64 * 10).unwrap();
+        }
+
+        assert_eq!(tree.retain(|key, _| key % 2 == 0).unwrap(), 100);
+        assert_eq!(tree.len(), 100);
+        let entries: Vec<(Key, Value)> = tree.iter().map(Result::unwrap).collect();
+        let expected: Vec<(Key, Value)> = (0..200).step_by(2).map(|k| (k, k as u64 * 10)).collect();
+        assert_eq!(entries, expected);
+        for key in 0..200 {
+            let expected = (key % 2 == 0).then_some(key as u64 * 10);
+            assert_eq!(tree.search(key).unwrap(), expected, "{}", key);
+        }
+
+        let root = Arc::clone(tree.root.read().unwrap().as_ref().unwrap());
+        let mut depths = Vec::new();
+        leaf_depths(&root, 0, &mut depths);
+        assert!(depths.iter().all(|&depth| depth == depths[0]));
+        assert_eq!(tree.stats().unwrap().height, depths[0] + 1);
+
+        // The value is passed too, and nothing is rebuilt when every entry is kept
+        assert_eq!(tree.retain(|_, value| *value < 1000).unwrap(), 50);
+        assert_eq!(tree.retain(|_, _| true).unwrap(), 0);
+        tree.insert(1, 1).unwrap();
+        assert_eq!(
+            tree.range(0, 4).unwrap(),
+            vec![(0, 0), (1, 1), (2, 20), (4, 40)]
+        );
+
+        assert_eq!(tree.retain(|_, _| false).unwrap(), 51);
+        assert!(tree.is_empty());
+        assert_eq!(tree.iter().count(), 0);
+        tree.insert(7, 70).unwrap();
+        assert_eq!(tree.search(7).unwrap(), Some(70));
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that composite keys are ordered column by column and can be range-scanned by prefix.
+    #[test]
+    fn test_composite_keys() {
+        let test_db = "test_composite_keys.db";
+        let _ = fs::remove_file(test_db);
+
+        let buffer_pool = Arc::new(BufferPool::new(100, StorageEngine::new(test_db).unwrap()));
+        let tree = BPlusTree::<CompositeKey>::new(Arc::clone(&buffer_pool), ORDER)
+            .expect("Failed to initialize BPlusTree");
+        let key = |user_id: i64, order_id: i64| {
+            CompositeKey(vec![
+                ast::Value::Integer(user_id),
+                ast::Value::Integer(order_id),
+            ])
+        };
+
+        // Insert (user_id, order_id) pairs out of order
+        let mut slot = 0;
+        for order_id in [30, 10, 20] {
+            for user_id in [3, 1, 2] {
+                tree.insert(key(user_id, order_id), slot).unwrap();
+                slot += 1;
+            }
+        }
+        assert!(tree.insert(key(2, 10), 99).is_err());
+        assert_eq!(tree.search(key(2, 20)).unwrap(), Some(8));
+        assert_eq!(tree.search(key(2, 25)).unwrap(), None);
+
+        let keys: Vec<CompositeKey> = tree.iter().map(|entry| entry.unwrap().0).collect();
+        let expected: Vec<CompositeKey> = (1..=3)
+            .flat_map(|user_id| [10, 20, 30].map(|order_id| key(user_id, order_id)))
+            .collect();
+        assert_eq!(keys, expected);
+
+        // A one-column prefix sorts before every key that starts with it
+        let low = CompositeKey(vec![ast::Value::Integer(2)]);
+        let orders: Vec<CompositeKey> = tree
+            .range(low, key(2, i64::MAX))
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(orders, vec![key(2, 10), key(2, 20), key(2, 30)]);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// An operation applied to both the tree and the reference map.
+    #[derive(Debug, Clone)]
+    enum Op {
+        Insert(Key, Value),
+        Delete(Key),
+        Search(Key),
+        Range(Key, Key),
+        /// Removes the keys divisible by the given number.
+        Retain(Key),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        // A narrow key space makes duplicate inserts, deletes of present keys and splits at
+        // node boundaries likely
+        let key = -8..56i32;
+        prop_oneof![
+            4 => (key.clone(), any::<Value>()).prop_map(|(k, v)| Op::Insert(k, v)),
+            2 => key.clone().prop_map(Op::Delete),
+            1 => key.clone().prop_map(Op::Search),
+            1 => (key.clone(), key).prop_map(|(low, high)| Op::Range(low, high)),
+            1 => (2..5i32).prop_map(Op::Retain),
+        ]
+    }
+
+    proptest! {
+        /// Tests that any sequence of operations gives the same results as a `BTreeMap`, and
+        /// that the tree holds the same entries as the map after each one.
+        #[test]
+        fn test_matches_btreemap(order in 3..8usize, ops in prop::collection::vec(op(), 1..200)) {
+            let test_db = "test_index_proptest.db";
+            let _ = fs::remove_file(test_db);
+            let buffer_pool = Arc::new(BufferPool::new(4, StorageEngine::new(test_db).unwrap()));
+            let tree = BPlusTree::new(buffer_pool, order).unwrap();
+            let mut reference = BTreeMap::new();
+
+            for op in ops {
+                match op {
+                    Op::Insert(key, value) => {
+                        let inserted = tree.insert(key, value).is_ok();
+                        prop_assert_eq!(inserted, !reference.contains_key(&key), "{:?}", op);
+                        reference.entry(key).or_insert(value);
+                    }
+                    Op::Delete(key) => {
+                        tree.update_with(key, |_| None).unwrap();
+                        reference.remove(&key);
+                    }
+                    Op::Search(key) => {
+                        prop_assert_eq!(tree.search(key).unwrap(), reference.get(&key).copied());
+                    }
+                    Op::Range(low, high) => {
+                        let expected: Vec<(Key, Value)> = if low <= high {
+                            reference.range(low..=high).map(|(k, v)| (*k, *v)).collect()
+                        } else {
+                            Vec::new()
+                        };
+                        prop_assert_eq!(tree.count_range(low, high).unwrap(), expected.len());
+                        prop_assert_eq!(tree.range(low, high).unwrap(), expected);
+                    }
+                    Op::Retain(divisor) => {
+                        let before = reference.len();
+                        reference.retain(|key, _| key % divisor != 0);
+                        let removed = tree.retain(|key, _| key % divisor != 0).unwrap();
+                        prop_assert_eq!(removed, before - reference.len());
+                    }
+                }
+
+                let entries: Vec<(Key, Value)> = tree.iter().map(Result::unwrap).collect();
+                let expected: Vec<(Key, Value)> =
+                    reference.iter().map(|(k, v)| (*k, *v)).collect();
+                prop_assert_eq!(entries, expected);
+                prop_assert_eq!(tree.len(), reference.len());
+            }
+
+            let _ = fs::remove_file(test_db);
+        }
+    }
 }
@@ -1,42 +1,208 @@
 use crate::tokens::{is_boolean, is_keyword, Token};
+use std::fmt;
 use std::str::Chars;
 
+/// An error found while splitting SQL text into tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    /// Line and column where the offending text starts.
+    pub position: Position,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Lexes all of `sql` into tokens, stopping at the end of the input.
+pub fn tokenize(sql: &str) -> Result<Vec<Token>, LexError> {
+    let mut lexer = Lexer::new(sql);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token() {
+        tokens.push(token);
+    }
+
+    match lexer.error() {
+        Some(error) => Err(error.clone()),
+        None => Ok(tokens),
+    }
+}
+
+/// How a quote character inside a string literal is escaped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuoteEscape {
+    /// SQL-standard: a quote is written twice, as in `'it''s'`.
+    #[default]
+    Doubled,
+    /// MySQL-style: a backslash escapes the next character, as in `'it\'s'`. `\n`, `\t`,
+    /// `\r` and `\0` stand for control characters, and doubled quotes are still accepted.
+    Backslash,
+}
+
+/// Dialect settings that change how SQL text is split into tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LexerOptions {
+    pub quote_escape: QuoteEscape,
+}
+
+/// A line and column in the input, both starting at 1. Columns count characters, not bytes.
+/// Positions order by line, then column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
 pub struct Lexer<'a> {
+    input: &'a str,
+    options: LexerOptions,
     chars: Chars<'a>,
+    // `current_char` and `peek_char` buffer the next two characters; `chars` is past both
     current_char: Option<char>,
     peek_char: Option<char>,
+    position: usize,          // Byte offset of `current_char`
+    token_start: usize,       // Byte offset where the last returned token starts
+    location: Position,       // Line and column of `current_char`
+    token_location: Position, // Line and column of `token_start`
+    error: Option<LexError>,
+    // A token lexed ahead by `peek_token`, with its start offset and location
+    peeked: Option<(Option<Token>, usize, Position)>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, LexerOptions::default())
+    }
+
+    /// Creates a lexer that follows the given dialect settings.
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Self {
+        let mut chars = input.chars();
+        let peek_char = chars.next();
         let mut l = Lexer {
-            chars: input.chars(),
+            input,
+            options,
+            chars,
             current_char: None,
-            peek_char: None,
+            peek_char,
+            position: 0,
+            token_start: 0,
+            location: Position { line: 1, column: 1 },
+            token_location: Position { line: 1, column: 1 },
+            error: None,
+            peeked: None,
         };
         l.read_char();
-        l.read_char_peek();
         l
     }
 
-    fn read_char(&mut self) {
-        self.current_char = self.chars.next();
+    /// Returns the text the lexer is reading.
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Returns the byte offset where the most recently returned token starts.
+    /// After the end of input is reached this is the input's length.
+    pub fn token_start(&self) -> usize {
+        self.token_start
     }
 
-    fn read_char_peek(&mut self) {
-        self.peek_char = self.chars.clone().next();
+    /// Returns the line and column where the most recently returned token starts, matching
+    /// `token_start`.
+    pub fn token_position(&self) -> Position {
+        self.token_location
+    }
+
+    /// Returns the error that made the lexer stop early, if any. Once set, `next_token`
+    /// returns `None`.
+    pub fn error(&self) -> Option<&LexError> {
+        self.error.as_ref()
+    }
+
+    fn read_char(&mut self) {
+        if let Some(c) = self.current_char {
+            self.position += c.len_utf8();
+            if c == '\n' {
+                self.location.line += 1;
+                self.location.column = 1;
+            } else {
+                self.location.column += 1;
+            }
+        }
+        self.current_char = self.peek_char;
+        self.peek_char = self.chars.next();
     }
 
     pub fn next_token(&mut self) -> Option<Token> {
+        if let Some((token, start, location)) = self.peeked.take() {
+            self.token_start = start;
+            self.token_location = location;
+            return token;
+        }
+        self.lex_token()
+    }
+
+    /// Lexes the rest of the input, returning each token with the position it starts at, or
+    /// the first error.
+    pub fn tokenize_all(&mut self) -> Result<Vec<(Token, Position)>, LexError> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token() {
+            tokens.push((token, self.token_location));
+        }
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(tokens),
+        }
+    }
+
+    /// Returns the token the next `next_token` call will return, without consuming it.
+    /// `token_start` and `token_position` keep referring to the last consumed token.
+    pub fn peek_token(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            let (last_start, last_location) = (self.token_start, self.token_location);
+            let token = self.lex_token();
+            self.peeked = Some((token, self.token_start, self.token_location));
+            self.token_start = last_start;
+            self.token_location = last_location;
+        }
+        self.peeked.as_ref().and_then(|(token, ..)| token.as_ref())
+    }
+
+    fn lex_token(&mut self) -> Option<Token> {
+        if self.error.is_some() {
+            return None;
+        }
         self.skip_whitespace();
+        if self.error.is_some() {
+            return None;
+        }
+        self.token_start = self.position;
+        self.token_location = self.location;
 
-        let token = match self.current_char {
+        match self.current_char {
+            Some('x' | 'X') if self.peek_char == Some('\'') => self.read_blob_literal(),
             Some(c) if c.is_alphabetic() => self.read_identifier(),
-            Some(c) if c.is_digit(10) => self.read_number(),
+            Some(c) if c.is_ascii_digit() => self.read_number(),
             Some('\'') => self.read_string_literal(),
+            Some('"') => self.read_quoted_identifier(),
             Some('=') => {
-                self.read_char();
-                Some(Token::Equal)
+                if self.peek_char == Some('>') {
+                    self.read_char();
+                    self.read_char();
+                    Some(Token::FatArrow)
+                } else {
+                    self.read_char();
+                    Some(Token::Equal)
+                }
             }
             Some('!') => {
                 if self.peek_char == Some('=') {
@@ -44,7 +210,7 @@ impl<'a> Lexer<'a> {
                     self.read_char();
                     Some(Token::NotEqual)
                 } else {
-                    None
+                    self.fail("Unexpected character '!'; use NOT or '!='".to_string())
                 }
             }
             Some('<') => {
@@ -52,6 +218,11 @@ impl<'a> Lexer<'a> {
                     self.read_char();
                     self.read_char();
                     Some(Token::LessThanOrEqual)
+                } else if self.peek_char == Some('>') {
+                    // `<>` is the standard spelling of `!=`
+                    self.read_char();
+                    self.read_char();
+                    Some(Token::NotEqual)
                 } else {
                     self.read_char();
                     Some(Token::LessThan)
@@ -67,10 +238,38 @@ impl<'a> Lexer<'a> {
                     Some(Token::GreaterThan)
                 }
             }
+            Some('+') => {
+                self.read_char();
+                Some(Token::Plus)
+            }
+            Some('-') => {
+                if self.peek_char == Some('>') {
+                    self.read_char();
+                    self.read_char();
+                    if self.current_char == Some('>') {
+                        self.read_char();
+                        Some(Token::LongArrow)
+                    } else {
+                        Some(Token::Arrow)
+                    }
+                } else {
+                    self.read_char();
+                    Some(Token::Minus)
+                }
+            }
             Some('*') => {
                 self.read_char();
                 Some(Token::Asterisk)
             }
+            // A `/` that starts a comment was already skipped with the whitespace
+            Some('/') => {
+                self.read_char();
+                Some(Token::Slash)
+            }
+            Some('%') => {
+                self.read_char();
+                Some(Token::Percent)
+            }
             Some(',') => {
                 self.read_char();
                 Some(Token::Comma)
@@ -83,27 +282,85 @@ impl<'a> Lexer<'a> {
                 self.read_char();
                 Some(Token::RightParen)
             }
+            Some('[') => {
+                self.read_char();
+                Some(Token::LeftBracket)
+            }
+            Some(']') => {
+                self.read_char();
+                Some(Token::RightBracket)
+            }
             Some('.') => {
                 self.read_char();
                 Some(Token::Dot)
             }
-            Some(_c) => {
+            Some(':') => {
+                if self.peek_char == Some(':') {
+                    self.read_char();
+                    self.read_char();
+                    Some(Token::DoubleColon)
+                } else {
+                    self.fail("Unexpected character ':'".to_string())
+                }
+            }
+            Some(';') => {
                 self.read_char();
-                None
+                Some(Token::Semicolon)
             }
+            Some(c) => self.fail(format!("Unexpected character '{}'", c)),
             None => None,
-        };
-
-        token
+        }
     }
 
+    /// Skips whitespace and comments: `--` to the end of the line, and `/* ... */`, which may
+    /// be nested. An unterminated block comment is an error.
     fn skip_whitespace(&mut self) {
-        while let Some(c) = self.current_char {
-            if c.is_whitespace() {
-                self.read_char();
-            } else {
-                break;
+        loop {
+            match (self.current_char, self.peek_char) {
+                (Some(c), _) if c.is_whitespace() => self.read_char(),
+                (Some('-'), Some('-')) => {
+                    while self.current_char.is_some_and(|c| c != '\n') {
+                        self.read_char();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    if !self.skip_block_comment() {
+                        return;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skips a block comment starting at the current `/*`, returning false if it is never
+    /// closed.
+    fn skip_block_comment(&mut self) -> bool {
+        let (start, start_location) = (self.position, self.location);
+        let mut depth = 0;
+        loop {
+            match (self.current_char, self.peek_char) {
+                (Some('/'), Some('*')) => {
+                    depth += 1;
+                    self.read_char();
+                }
+                (Some('*'), Some('/')) => {
+                    depth -= 1;
+                    self.read_char();
+                    if depth == 0 {
+                        self.read_char();
+                        return true;
+                    }
+                }
+                (None, _) => {
+                    self.token_start = start;
+                    self.token_location = start_location;
+                    self.fail("Unterminated block comment".to_string());
+                    return false;
+                }
+                _ => {}
             }
+            self.read_char();
         }
     }
 
@@ -120,6 +377,8 @@ impl<'a> Lexer<'a> {
 
         if is_keyword(&identifier) {
             Some(Token::Keyword(identifier.to_uppercase()))
+        } else if identifier.eq_ignore_ascii_case("NULL") {
+            Some(Token::Null)
         } else if is_boolean(&identifier) {
             Some(Token::Boolean(identifier.eq_ignore_ascii_case("TRUE")))
         } else {
@@ -127,46 +386,555 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Reads an integer, or a float with a fractional part and/or an exponent such as `2E-3`.
     fn read_number(&mut self) -> Option<Token> {
         let mut number = String::new();
+        self.read_digits(&mut number);
+
+        let mut is_float = false;
+        if self.current_char == Some('.') {
+            is_float = true;
+            number.push('.');
+            self.read_char();
+            self.read_digits(&mut number);
+        }
+        if let Some(e @ ('e' | 'E')) = self.current_char {
+            is_float = true;
+            number.push(e);
+            self.read_char();
+            if let Some(sign @ ('+' | '-')) = self.current_char {
+                number.push(sign);
+                self.read_char();
+            }
+            if !self.read_digits(&mut number) {
+                return self.fail(format!("Missing exponent digits in number '{}'", number));
+            }
+        }
+        if self.current_char == Some('.') {
+            return self.fail(format!("Unexpected '.' after number '{}'", number));
+        }
+
+        if !is_float {
+            return match number.parse::<i64>() {
+                Ok(i) => Some(Token::Integer(i)),
+                Err(_) => self.fail(format!("Number '{}' is out of range", number)),
+            };
+        }
+        match number.parse::<f64>() {
+            Ok(f) if f.is_finite() => Some(Token::Float(f)),
+            _ => self.fail(format!("Number '{}' is out of range", number)),
+        }
+    }
+
+    /// Appends the digits at the current position to `number`, returning false if there were
+    /// none.
+    fn read_digits(&mut self, number: &mut String) -> bool {
+        let start = number.len();
         while let Some(c) = self.current_char {
-            if c.is_digit(10) {
+            if c.is_ascii_digit() {
                 number.push(c);
                 self.read_char();
             } else {
                 break;
             }
         }
-
-        if self.current_char == Some('.') {
-            number.push('.');
-            self.read_char();
-            while let Some(c) = self.current_char {
-                if c.is_digit(10) {
-                    number.push(c);
-                    self.read_char();
-                } else {
-                    break;
-                }
-            }
-            number.parse::<f64>().ok().map(Token::Float)
-        } else {
-            number.parse::<i64>().ok().map(Token::Integer)
-        }
+        number.len() > start
     }
 
+    /// Reads a single-quoted string, in which `''` stands for one `'`. A string still open at
+    /// the end of the input is an error.
     fn read_string_literal(&mut self) -> Option<Token> {
         self.read_char(); // Skip opening '
         let mut string = String::new();
-        while let Some(c) = self.current_char {
-            if c == '\'' {
+        loop {
+            let Some(c) = self.current_char else {
+                return self.fail("Unterminated string literal".to_string());
+            };
+            if c == '\'' && self.peek_char == Some('\'') {
+                string.push('\'');
+                self.read_char();
+                self.read_char();
+            } else if c == '\'' {
                 self.read_char(); // Skip closing '
-                break;
+                return Some(Token::StringLiteral(string));
+            } else if c == '\\' && self.options.quote_escape == QuoteEscape::Backslash {
+                self.read_char(); // Skip the backslash
+                let Some(escaped) = self.current_char else {
+                    return self.fail("Unterminated string literal".to_string());
+                };
+                string.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    other => other,
+                });
+                self.read_char();
             } else {
                 string.push(c);
                 self.read_char();
             }
         }
-        Some(Token::StringLiteral(string))
+    }
+
+    /// Reads a double-quoted identifier, in which `""` stands for one `"`. Quoting keeps
+    /// keywords and case as written.
+    fn read_quoted_identifier(&mut self) -> Option<Token> {
+        self.read_char(); // Skip opening "
+        let mut identifier = String::new();
+        loop {
+            match self.current_char {
+                Some('"') if self.peek_char == Some('"') => {
+                    identifier.push('"');
+                    self.read_char();
+                    self.read_char();
+                }
+                Some('"') => {
+                    self.read_char(); // Skip closing "
+                    return Some(Token::Identifier(identifier));
+                }
+                Some(c) => {
+                    identifier.push(c);
+                    self.read_char();
+                }
+                None => return self.fail("Unterminated quoted identifier".to_string()),
+            }
+        }
+    }
+
+    /// Reads a hex blob literal such as `X'DEADBEEF'`.
+    fn read_blob_literal(&mut self) -> Option<Token> {
+        self.read_char(); // Skip X
+        let Some(Token::StringLiteral(hex)) = self.read_string_literal() else {
+            return None;
+        };
+
+        if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return self.fail(format!("Invalid hex digit '{}' in blob literal", c));
+        }
+        if hex.len() % 2 != 0 {
+            return self.fail("Blob literal must have an even number of hex digits".to_string());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        Some(Token::Blob(bytes))
+    }
+
+    /// Records an error at the start of the current token and stops lexing.
+    fn fail(&mut self, message: String) -> Option<Token> {
+        self.error = Some(LexError {
+            message,
+            position: self.token_location,
+        });
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that each quote escape style reads its own spelling of the same string.
+    #[test]
+    fn test_quote_escape_styles() {
+        let doubled = Lexer::new("'it''s'").next_token();
+        assert_eq!(doubled, Some(Token::StringLiteral("it's".to_string())));
+
+        let backslash = LexerOptions {
+            quote_escape: QuoteEscape::Backslash,
+        };
+        let escaped = Lexer::with_options(r"'it\'s'", backslash).next_token();
+        assert_eq!(escaped, doubled);
+        let escaped = Lexer::with_options("'it''s'", backslash).next_token();
+        assert_eq!(escaped, doubled);
+        assert_eq!(
+            Lexer::with_options(r"'a\\b\nc'", backslash).next_token(),
+            Some(Token::StringLiteral("a\\b\nc".to_string()))
+        );
+
+        // Backslashes are plain characters in the standard style
+        assert_eq!(
+            Lexer::new(r"'a\b'").next_token(),
+            Some(Token::StringLiteral("a\\b".to_string()))
+        );
+    }
+
+    /// Tests that doubled quotes inside a string stand for one quote and that a string left
+    /// open at the end of the input is an error.
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(
+            tokenize("'it''s' '' ''''"),
+            Ok(vec![
+                Token::StringLiteral("it's".to_string()),
+                Token::StringLiteral(String::new()),
+                Token::StringLiteral("'".to_string()),
+            ])
+        );
+
+        let unterminated = |line, column| {
+            Err(LexError {
+                message: "Unterminated string literal".to_string(),
+                position: Position { line, column },
+            })
+        };
+        assert_eq!(tokenize("SELECT 'abc"), unterminated(1, 8));
+        assert_eq!(tokenize("'it''s"), unterminated(1, 1));
+        assert_eq!(tokenize("X'AB"), unterminated(1, 1));
+        assert_eq!(
+            tokenize("SELECT a\nFROM t\nWHERE b = 'x"),
+            unterminated(3, 11)
+        );
+
+        let backslash = LexerOptions {
+            quote_escape: QuoteEscape::Backslash,
+        };
+        let mut lexer = Lexer::with_options(r"'abc\", backslash);
+        assert_eq!(lexer.next_token(), None);
+        assert_eq!(
+            lexer.error().map(|e| e.message.as_str()),
+            Some("Unterminated string literal")
+        );
+    }
+
+    /// Tests that a representative query lexes into the expected token sequence.
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("SELECT name, COUNT(*) FROM users WHERE age >= 21 AND tag != 'x'");
+        assert_eq!(
+            tokens,
+            Ok(vec![
+                Token::Keyword("SELECT".to_string()),
+                Token::Identifier("name".to_string()),
+                Token::Comma,
+                Token::Identifier("COUNT".to_string()),
+                Token::LeftParen,
+                Token::Asterisk,
+                Token::RightParen,
+                Token::Keyword("FROM".to_string()),
+                Token::Identifier("users".to_string()),
+                Token::Keyword("WHERE".to_string()),
+                Token::Identifier("age".to_string()),
+                Token::GreaterThanOrEqual,
+                Token::Integer(21),
+                Token::Keyword("AND".to_string()),
+                Token::Identifier("tag".to_string()),
+                Token::NotEqual,
+                Token::StringLiteral("x".to_string()),
+            ])
+        );
+        assert_eq!(tokenize("  "), Ok(Vec::new()));
+    }
+
+    /// Tests that `<>` lexes like `!=`, but only when its two characters are adjacent.
+    #[test]
+    fn test_angle_bracket_not_equal() {
+        let a = || Token::Identifier("a".to_string());
+        let b = || Token::Identifier("b".to_string());
+        for sql in ["a <> b", "a<>b", "a != b"] {
+            assert_eq!(
+                tokenize(sql),
+                Ok(vec![a(), Token::NotEqual, b()]),
+                "{}",
+                sql
+            );
+        }
+        assert_eq!(
+            tokenize("a < > b"),
+            Ok(vec![a(), Token::LessThan, Token::GreaterThan, b()])
+        );
+        assert_eq!(
+            tokenize("a <>= b"),
+            Ok(vec![a(), Token::NotEqual, Token::Equal, b()])
+        );
+    }
+
+    /// Tests that each arithmetic operator lexes into its own token, with or without spaces,
+    /// and that `-` and `/` still start comments when doubled or followed by `*`.
+    #[test]
+    fn test_arithmetic_operators() {
+        let a = || Token::Identifier("a".to_string());
+        let b = || Token::Identifier("b".to_string());
+        for (sql, operator) in [
+            ("a + b", Token::Plus),
+            ("a - b", Token::Minus),
+            ("a * b", Token::Asterisk),
+            ("a / b", Token::Slash),
+            ("a % b", Token::Percent),
+            ("a/b", Token::Slash),
+            ("a%b", Token::Percent),
+        ] {
+            assert_eq!(tokenize(sql), Ok(vec![a(), operator, b()]), "{}", sql);
+        }
+
+        assert_eq!(
+            tokenize("price*quantity-1"),
+            Ok(vec![
+                Token::Identifier("price".to_string()),
+                Token::Asterisk,
+                Token::Identifier("quantity".to_string()),
+                Token::Minus,
+                Token::Integer(1),
+            ])
+        );
+        assert_eq!(tokenize("a /* b */ -- c"), Ok(vec![a()]));
+        assert_eq!(
+            tokenize("a / * b"),
+            Ok(vec![a(), Token::Slash, Token::Asterisk, b()])
+        );
+    }
+
+    /// Tests that an unrecognized character is reported with its position, and that the lexer
+    /// stops there instead of looking like the end of the input.
+    #[test]
+    fn test_tokenize_error() {
+        assert_eq!(
+            tokenize("SELECT ! FROM t"),
+            Err(LexError {
+                message: "Unexpected character '!'; use NOT or '!='".to_string(),
+                position: Position { line: 1, column: 8 },
+            })
+        );
+        assert_eq!(
+            tokenize("a : b").unwrap_err().message,
+            "Unexpected character ':'"
+        );
+
+        let mut lexer = Lexer::new("a # b");
+        assert_eq!(lexer.next_token(), Some(Token::Identifier("a".to_string())));
+        assert_eq!(lexer.next_token(), None);
+        assert_eq!(
+            lexer.error(),
+            Some(&LexError {
+                message: "Unexpected character '#'".to_string(),
+                position: Position { line: 1, column: 3 },
+            })
+        );
+        assert_eq!(lexer.next_token(), None);
+
+        assert_eq!(
+            tokenize("SELECT a ! b"),
+            Err(LexError {
+                message: "Unexpected character '!'; use NOT or '!='".to_string(),
+                position: Position {
+                    line: 1,
+                    column: 10,
+                },
+            })
+        );
+        // A `;` ends a statement rather than being an unknown character
+        assert_eq!(
+            tokenize("SELECT a;"),
+            Ok(vec![
+                Token::Keyword("SELECT".to_string()),
+                Token::Identifier("a".to_string()),
+                Token::Semicolon,
+            ])
+        );
+    }
+
+    /// Tests that hex blob literals lex into bytes and malformed ones are lex errors.
+    #[test]
+    fn test_blob_literals() {
+        assert_eq!(
+            tokenize("X'DEADbeef' x'' xs"),
+            Ok(vec![
+                Token::Blob(vec![0xDE, 0xAD, 0xBE, 0xEF]),
+                Token::Blob(Vec::new()),
+                Token::Identifier("xs".to_string()),
+            ])
+        );
+        assert_eq!(
+            tokenize("SELECT X'ABC'"),
+            Err(LexError {
+                message: "Blob literal must have an even number of hex digits".to_string(),
+                position: Position { line: 1, column: 8 },
+            })
+        );
+        assert_eq!(
+            tokenize("SELECT X'0G'"),
+            Err(LexError {
+                message: "Invalid hex digit 'G' in blob literal".to_string(),
+                position: Position { line: 1, column: 8 },
+            })
+        );
+    }
+
+    /// Tests that line and block comments, including nested ones, are skipped like whitespace.
+    #[test]
+    fn test_comments() {
+        let plain = tokenize("SELECT a, b - 1 FROM t WHERE a > 1");
+        assert_eq!(
+            tokenize(
+                "-- leading\nSELECT a, /* inline */ b - 1 -- trailing\n\
+                 FROM /* outer /* nested */ still outer */ t WHERE a > 1 -- at the end"
+            ),
+            plain
+        );
+        assert_eq!(tokenize("SELECT a, b-/**/1 FROM t WHERE a>1"), plain);
+        assert_eq!(tokenize("/* only */ -- comments"), Ok(Vec::new()));
+
+        assert_eq!(
+            tokenize("SELECT a /* open /* nested */"),
+            Err(LexError {
+                message: "Unterminated block comment".to_string(),
+                position: Position {
+                    line: 1,
+                    column: 10,
+                },
+            })
+        );
+    }
+
+    /// Tests that numbers with exponents lex as floats and that malformed numbers are errors.
+    #[test]
+    fn test_scientific_notation() {
+        assert_eq!(
+            tokenize("1.5e10 2E-3 6.02e23 1e+2 7 3.25"),
+            Ok(vec![
+                Token::Float(1.5e10),
+                Token::Float(2e-3),
+                Token::Float(6.02e23),
+                Token::Float(100.0),
+                Token::Integer(7),
+                Token::Float(3.25),
+            ])
+        );
+
+        let error = |sql: &str| tokenize(sql).unwrap_err();
+        assert_eq!(
+            error("SELECT 1e FROM t"),
+            LexError {
+                message: "Missing exponent digits in number '1e'".to_string(),
+                position: Position { line: 1, column: 8 },
+            }
+        );
+        assert_eq!(
+            error("2.5E-").message,
+            "Missing exponent digits in number '2.5E-'"
+        );
+        assert_eq!(
+            error("SELECT 1.2.3"),
+            LexError {
+                message: "Unexpected '.' after number '1.2'".to_string(),
+                position: Position { line: 1, column: 8 },
+            }
+        );
+        assert_eq!(error("1e5.0").message, "Unexpected '.' after number '1e5'");
+        assert_eq!(error("1e999").message, "Number '1e999' is out of range");
+        assert_eq!(
+            error("SELECT 9223372036854775808 FROM t"),
+            LexError {
+                message: "Number '9223372036854775808' is out of range".to_string(),
+                position: Position { line: 1, column: 8 },
+            }
+        );
+        assert_eq!(
+            tokenize("9223372036854775807"),
+            Ok(vec![Token::Integer(i64::MAX)])
+        );
+    }
+
+    /// Tests that token positions count lines and characters, including across comments and
+    /// multi-byte characters, and that peeking does not move them.
+    #[test]
+    fn test_token_position() {
+        let mut lexer = Lexer::new("SELECT a,\n  /* é\n */ naïve, b\n\nFROM t");
+        let mut positions = Vec::new();
+        while lexer.next_token().is_some() {
+            positions.push(lexer.token_position().to_string());
+            lexer.peek_token();
+            assert_eq!(
+                lexer.token_position().to_string(),
+                positions[positions.len() - 1]
+            );
+        }
+        assert_eq!(
+            positions,
+            vec![
+                "line 1, column 1",
+                "line 1, column 8",
+                "line 1, column 9",
+                "line 3, column 5",
+                "line 3, column 10",
+                "line 3, column 12",
+                "line 5, column 1",
+                "line 5, column 6",
+            ]
+        );
+        assert_eq!(lexer.token_position(), Position { line: 5, column: 7 });
+    }
+
+    /// Tests that `tokenize_all` returns every token of a statement with increasing positions,
+    /// and returns a lexing error instead of the tokens before it.
+    #[test]
+    fn test_tokenize_all() {
+        let sql = "INSERT INTO archive (id, note)\nSELECT id, 'old' FROM logs WHERE day <= 7";
+        let mut lexer = Lexer::new(sql);
+        let tokens = lexer.tokenize_all().unwrap();
+
+        let keyword = |k: &str| Token::Keyword(k.to_string());
+        let ident = |i: &str| Token::Identifier(i.to_string());
+        let (kinds, positions): (Vec<Token>, Vec<Position>) = tokens.into_iter().unzip();
+        assert_eq!(
+            kinds,
+            vec![
+                keyword("INSERT"),
+                keyword("INTO"),
+                ident("archive"),
+                Token::LeftParen,
+                ident("id"),
+                Token::Comma,
+                ident("note"),
+                Token::RightParen,
+                keyword("SELECT"),
+                ident("id"),
+                Token::Comma,
+                Token::StringLiteral("old".to_string()),
+                keyword("FROM"),
+                ident("logs"),
+                keyword("WHERE"),
+                ident("day"),
+                Token::LessThanOrEqual,
+                Token::Integer(7),
+            ]
+        );
+        assert_eq!(positions[0], Position { line: 1, column: 1 });
+        assert_eq!(positions[8], Position { line: 2, column: 1 });
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let mut lexer = Lexer::new("SELECT a ! b");
+        assert_eq!(
+            lexer.tokenize_all(),
+            Err(LexError {
+                message: "Unexpected character '!'; use NOT or '!='".to_string(),
+                position: Position {
+                    line: 1,
+                    column: 10
+                },
+            })
+        );
+    }
+
+    /// Tests that peeking returns the next token without consuming it or moving `token_start`.
+    #[test]
+    fn test_peek_token() {
+        let mut lexer = Lexer::new("SELECT a");
+        assert_eq!(
+            lexer.next_token(),
+            Some(Token::Keyword("SELECT".to_string()))
+        );
+
+        let a = Token::Identifier("a".to_string());
+        assert_eq!(lexer.peek_token(), Some(&a));
+        assert_eq!(lexer.peek_token(), Some(&a));
+        assert_eq!(lexer.token_start(), 0);
+        assert_eq!(lexer.next_token(), Some(a));
+        assert_eq!(lexer.token_start(), 7);
+
+        assert_eq!(lexer.peek_token(), None);
+        assert_eq!(lexer.next_token(), None);
     }
 }
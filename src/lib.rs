@@ -1,13 +1,41 @@
+pub mod analyzer;
 pub mod ast;
 pub mod buffer_pool;
+pub mod cancellation;
+pub mod catalog;
+pub mod csv;
+pub mod executor;
+pub mod functions;
 pub mod index;
 pub mod lexer;
 pub mod parser;
+pub mod plan;
+pub mod render;
 pub mod storage;
 pub mod tokens;
+#[cfg(feature = "tokio")]
+pub mod tokio_storage;
+pub mod transaction;
+pub mod wal;
 
-pub use ast::{Expression, Insert, Join, Ordering, Query, Select, SortOrder, Table, Value};
-pub use buffer_pool::BufferPool;
-pub use index::{BPlusTree, ORDER};
-pub use parser::Parser;
-pub use storage::StorageEngine;
+pub use analyzer::{Analyzer, OuterReference};
+pub use ast::{
+    BinaryOperator, ColumnDef, Cte, DataType, Expression, Insert, Join, JoinType, Ordering,
+    QuantifiedOperand, Quantifier, Query, Replacement, SampleMethod, Select, SelectItem, SetQuery,
+    SortOrder, StatementCategory, Table, TableAccess, TableRef, TableSample, Update, Value, With,
+};
+pub use buffer_pool::{BufferPool, BufferPoolStats};
+pub use cancellation::CancellationToken;
+pub use catalog::{Catalog, TableSchema};
+pub use executor::{CoercionPolicy, Executor, QueryResult};
+pub use functions::{Arity, FunctionRegistry, FunctionSignature, ReturnType};
+pub use index::{BPlusTree, BPlusTreeIter, CompositeKey, TreeSnapshot, TreeStats, ORDER};
+pub use lexer::{tokenize, LexError, LexerOptions, Position, QuoteEscape};
+pub use parser::{Parser, ParserLimits};
+pub use plan::{PlanNode, Planner};
+pub use render::{KeywordCase, RenderOptions};
+pub use storage::{Durability, OverflowPage, Storage, StorageEngine, StorageHeader};
+#[cfg(feature = "tokio")]
+pub use tokio_storage::{AsyncStorage, TokioFileStorage};
+pub use transaction::TransactionManager;
+pub use wal::{Lsn, Wal, WalEntry, WalRecord};
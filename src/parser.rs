@@ -1,27 +1,110 @@
+#[cfg(feature = "uuid")]
+use crate::ast::parse_uuid;
 use crate::ast::{
-    BinaryOperator, Expression, Insert, Join, Ordering, Query, Select, SortOrder, Table, Value,
+    is_aggregate_function, BinaryOperator, ColumnDef, Cte, DataType, Expression, Insert, Join,
+    JoinType, Ordering, QuantifiedOperand, Quantifier, Query, Replacement, SampleMethod, Select,
+    SelectItem, SetQuery, SortOrder, Table, TableSample, Update, Value, With,
 };
-use crate::lexer::Lexer;
-use crate::tokens::Token;
+use crate::lexer::{Lexer, LexerOptions, Position};
+use crate::tokens::{is_contextual_keyword, Token};
+
+/// Upper bounds on the size of list constructs, which protect a server parsing untrusted SQL
+/// from queries generated to exhaust its memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    pub max_select_items: usize,
+    pub max_group_by: usize,
+    pub max_order_by: usize,
+    pub max_in_list: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        ParserLimits {
+            max_select_items: 10_000,
+            max_group_by: 10_000,
+            max_order_by: 10_000,
+            max_in_list: 10_000,
+        }
+    }
+}
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Option<Token>,
+    current_token_start: usize,
+    current_token_position: Position,
+    limits: ParserLimits,
 }
 
 impl<'a> Parser<'a> {
     /// Create a new parser.
     pub fn new(input: &'a str) -> Result<Self, String> {
-        let mut lexer = Lexer::new(input);
+        Self::with_options(input, LexerOptions::default())
+    }
+
+    /// Create a parser whose lexer follows the given dialect settings.
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Result<Self, String> {
+        let mut lexer = Lexer::with_options(input, options);
         let first_token = lexer.next_token();
+        let current_token_start = lexer.token_start();
+        let current_token_position = lexer.token_position();
         Ok(Parser {
             lexer,
             current_token: first_token,
+            current_token_start,
+            current_token_position,
+            limits: ParserLimits::default(),
         })
     }
 
+    /// Replaces the default limits on list lengths.
+    pub fn with_limits(mut self, limits: ParserLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Fails once a list has grown past `limit` items, before it grows any further.
+    fn check_limit(len: usize, limit: usize, what: &str) -> Result<(), String> {
+        if len > limit {
+            Err(format!("Too many {} (the limit is {})", what, limit))
+        } else {
+            Ok(())
+        }
+    }
+
     fn next_token(&mut self) {
         self.current_token = self.lexer.next_token();
+        self.current_token_start = self.lexer.token_start();
+        self.current_token_position = self.lexer.token_position();
+    }
+
+    /// Returns the byte offset of the token the parser is currently looking at.
+    /// After a failed parse this is where the error was detected.
+    pub fn error_offset(&self) -> usize {
+        self.current_token_start
+    }
+
+    /// Returns the line and column of the token the parser is currently looking at, like
+    /// `error_offset`.
+    pub fn error_position(&self) -> Position {
+        self.current_token_position
+    }
+
+    /// Finishes a parse: a lex error ends the token stream early, which the parser would
+    /// misreport, so it takes precedence; a parse error gets the line and column where it was
+    /// detected.
+    fn finish<T>(&self, result: Result<T, String>) -> Result<T, String> {
+        if let Some(error) = self.lexer.error() {
+            return Err(error.to_string());
+        }
+        result.map_err(|message| format!("{} at {}", message, self.current_token_position))
+    }
+
+    /// Returns the input that has not been parsed yet, starting at the current token.
+    /// Useful for showing the user where a failed parse stopped.
+    pub fn remaining_input(&self) -> &'a str {
+        &self.lexer.input()[self.current_token_start..]
     }
 
     fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
@@ -72,6 +155,41 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Returns whether the current token is `word` written as a bare identifier. Contextual
+    /// keywords lex as identifiers and are matched this way where the grammar expects them; a
+    /// quoted identifier never matches.
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(&self.current_token, Some(Token::Identifier(name)) if name.eq_ignore_ascii_case(word))
+            && !self.current_token_is_quoted()
+    }
+
+    fn consume_word(&mut self, word: &str) -> bool {
+        if self.peek_word(word) {
+            self.next_token();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), String> {
+        if self.consume_word(word) {
+            Ok(())
+        } else {
+            Err(format!("Expected keyword '{}'", word))
+        }
+    }
+
+    /// Returns whether the current token is a bare identifier spelling a contextual keyword.
+    fn peek_contextual_keyword(&self) -> bool {
+        matches!(&self.current_token, Some(Token::Identifier(name)) if is_contextual_keyword(name))
+            && !self.current_token_is_quoted()
+    }
+
+    fn current_token_is_quoted(&self) -> bool {
+        self.lexer.input()[self.current_token_start..].starts_with('"')
+    }
+
     fn expect_token(&mut self, expected: &Token) -> Result<(), String> {
         if let Some(ref current) = self.current_token {
             if current == expected {
@@ -101,17 +219,169 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// The entire query is parsed.
+    /// The entire query is parsed. A `;` may end it, but nothing else can follow it, so input
+    /// the grammar does not cover is an error rather than silently dropped.
     pub fn parse(&mut self) -> Result<Query, String> {
-        if self.peek_keyword("SELECT") {
+        let query = self.parse_query().and_then(|query| {
+            let terminated = self.consume_token(&Token::Semicolon);
+            match &self.current_token {
+                None => Ok(query),
+                Some(_) if terminated => {
+                    Err("Only one statement can be parsed at a time".to_string())
+                }
+                Some(token) => Err(format!(
+                    "Unexpected {:?} after the end of the statement",
+                    token
+                )),
+            }
+        });
+        self.finish(query)
+    }
+
+    /// Parses a line of comma-separated values with nothing around them, such as
+    /// `1, 'a', NULL`, for bulk input that skips the INSERT statement.
+    pub fn parse_value_list(&mut self) -> Result<Vec<Value>, String> {
+        let values = self.parse_value_list_inner();
+        self.finish(values)
+    }
+
+    fn parse_value_list_inner(&mut self) -> Result<Vec<Value>, String> {
+        let mut values = Vec::new();
+        loop {
+            values.push(self.parse_value()?);
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+            if self.current_token.is_none() {
+                return Err("Expected a value after the last ','".to_string());
+            }
+        }
+        match &self.current_token {
+            None => Ok(values),
+            Some(token) => Err(format!(
+                "Expected ',' between values, but found {:?}",
+                token
+            )),
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, String> {
+        if self.consume_keyword("EXPLAIN") {
+            Ok(Query::Explain(Box::new(self.parse_query()?)))
+        } else if self.peek_keyword("SELECT") || self.peek_keyword("WITH") {
             self.parse_select()
         } else if self.peek_keyword("INSERT") {
             self.parse_insert()
+        } else if self.peek_keyword("UPDATE") {
+            self.parse_update()
+        } else if self.consume_word("BEGIN") {
+            self.consume_word("TRANSACTION");
+            Ok(Query::Begin)
+        } else if self.consume_word("START") {
+            self.expect_word("TRANSACTION")?;
+            Ok(Query::Begin)
+        } else if self.consume_word("COMMIT") {
+            self.consume_word("TRANSACTION");
+            Ok(Query::Commit)
+        } else if self.consume_word("ROLLBACK") {
+            self.consume_word("TRANSACTION");
+            Ok(Query::Rollback)
+        } else if self.peek_word("SHOW") {
+            self.parse_show()
+        } else if self.peek_word("DESCRIBE") {
+            self.parse_describe()
+        } else if self.peek_keyword("CREATE") {
+            self.parse_create_table()
+        } else if self.peek_keyword("DROP") {
+            self.parse_drop()
         } else {
             Err("This is an unsupported query type.".to_string())
         }
     }
 
+    /// Parses `SHOW TABLES` or `SHOW COLUMNS FROM table`.
+    fn parse_show(&mut self) -> Result<Query, String> {
+        self.expect_word("SHOW")?;
+        if self.consume_word("TABLES") {
+            Ok(Query::ShowTables)
+        } else if self.consume_word("COLUMNS") {
+            self.expect_keyword("FROM")?;
+            let table = self.parse_table()?.name;
+            Ok(Query::ShowColumns { table })
+        } else {
+            Err("Expected TABLES or COLUMNS after 'SHOW'".to_string())
+        }
+    }
+
+    /// Parses `DESCRIBE table`.
+    fn parse_describe(&mut self) -> Result<Query, String> {
+        self.expect_word("DESCRIBE")?;
+        let table = self.parse_table()?.name;
+        Ok(Query::Describe { table })
+    }
+
+    /// Parses `CREATE TABLE name (column type [NOT NULL] [DEFAULT value], ...)`.
+    fn parse_create_table(&mut self) -> Result<Query, String> {
+        self.expect_keyword("CREATE")?;
+        self.expect_keyword("TABLE")?;
+        let name = self.parse_table()?.name;
+
+        self.expect_token(&Token::LeftParen)?;
+        let mut columns: Vec<ColumnDef> = Vec::new();
+        loop {
+            let column = self.parse_column_def()?;
+            if columns
+                .iter()
+                .any(|c| c.name.eq_ignore_ascii_case(&column.name))
+            {
+                return Err(format!(
+                    "Column '{}' is defined more than once",
+                    column.name
+                ));
+            }
+            columns.push(column);
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+            if self.current_token == Some(Token::RightParen) {
+                return Err("Expected a column definition after the last ','".to_string());
+            }
+        }
+        self.expect_token(&Token::RightParen)?;
+        Ok(Query::CreateTable { name, columns })
+    }
+
+    /// Parses `DROP TABLE [IF EXISTS] name`.
+    fn parse_drop(&mut self) -> Result<Query, String> {
+        self.expect_keyword("DROP")?;
+        self.expect_keyword("TABLE")?;
+        let if_exists = self.consume_keywords(&["IF", "EXISTS"]);
+        let name = self.parse_table()?.name;
+        Ok(Query::DropTable { name, if_exists })
+    }
+
+    /// Parses a column name, its type and any `NOT NULL` and `DEFAULT value` constraints.
+    fn parse_column_def(&mut self) -> Result<ColumnDef, String> {
+        let name = self.parse_column_name()?;
+        let data_type = self.parse_data_type()?;
+        let mut column = ColumnDef {
+            name,
+            data_type,
+            not_null: false,
+            default: None,
+        };
+        loop {
+            if self.consume_keyword("NOT") {
+                self.expect_token(&Token::Null)?;
+                column.not_null = true;
+            } else if self.consume_keyword("DEFAULT") {
+                column.default = Some(self.parse_value()?);
+            } else {
+                return Ok(column);
+            }
+        }
+    }
+
     /// Parses the INSERT statement.
     fn parse_insert(&mut self) -> Result<Query, String> {
         self.expect_keyword("INSERT")?;
@@ -121,12 +391,7 @@ impl<'a> Parser<'a> {
         self.expect_token(&Token::LeftParen)?;
         let mut columns = Vec::new();
         loop {
-            if let Some(Token::Identifier(ref col)) = self.current_token {
-                columns.push(col.clone());
-                self.next_token();
-            } else {
-                return Err("I was expecting a column name.".to_string());
-            }
+            columns.push(self.parse_column_name()?);
 
             if !self.consume_token(&Token::Comma) {
                 break;
@@ -166,18 +431,71 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses `UPDATE table SET column = expression, ... [WHERE condition]`.
+    fn parse_update(&mut self) -> Result<Query, String> {
+        self.expect_keyword("UPDATE")?;
+        let table = self.parse_table()?;
+        self.expect_keyword("SET")?;
+
+        let mut assignments: Vec<(String, Expression)> = Vec::new();
+        loop {
+            let column = self.parse_column_name()?;
+            if assignments
+                .iter()
+                .any(|(assigned, _)| assigned.eq_ignore_ascii_case(&column))
+            {
+                return Err(format!("Column '{}' is assigned more than once", column));
+            }
+            self.expect_token(&Token::Equal)?;
+            assignments.push((column, self.parse_expression()?));
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+
+        let where_clause = if self.consume_keyword("WHERE") {
+            Some(self.parse_logical_expression()?)
+        } else {
+            None
+        };
+        Ok(Query::Update(Update {
+            table,
+            assignments,
+            where_clause,
+        }))
+    }
+
     /// Parse the SELECT statement and wrap it in `Query::Select`.
     fn parse_select(&mut self) -> Result<Query, String> {
         let select = self.parse_select_inner()?;
-        Ok(Query::Select(select))
+        Ok(Query::Select(Box::new(select)))
     }
 
     /// A function that parses SELECT statements internally
     fn parse_select_inner(&mut self) -> Result<Select, String> {
+        let with = if self.peek_keyword("WITH") {
+            Some(self.parse_with()?)
+        } else {
+            None
+        };
         self.expect_keyword("SELECT")?;
+        let distinct = self.consume_keyword("DISTINCT");
         let mut columns = Vec::new();
         loop {
-            columns.push(self.parse_expression()?);
+            let column = self.parse_expression()?;
+            if column == Expression::Asterisk {
+                columns.push(SelectItem::from(self.parse_wildcard_modifiers()?));
+            } else {
+                columns.push(SelectItem {
+                    expression: column,
+                    alias: self.parse_column_alias()?,
+                });
+            }
+            Self::check_limit(
+                columns.len(),
+                self.limits.max_select_items,
+                "select list items",
+            )?;
             if !self.consume_token(&Token::Comma) {
                 break;
             }
@@ -210,7 +528,40 @@ impl<'a> Parser<'a> {
             None
         };
 
+        let (mut limit, mut offset) = (None, None);
+        if self.consume_keyword("LIMIT") {
+            let count = self.parse_additive_expression()?;
+            // MySQL spells LIMIT n OFFSET m as LIMIT m, n
+            if self.consume_token(&Token::Comma) {
+                offset = Some(count);
+                limit = Some(self.parse_additive_expression()?);
+            } else {
+                limit = Some(count);
+            }
+        }
+
+        if self.consume_keyword("OFFSET") {
+            if offset.is_some() {
+                return Err("OFFSET cannot be combined with LIMIT offset, count".to_string());
+            }
+            let count = self.parse_additive_expression()?;
+            // The standard form spells it OFFSET n ROWS
+            if !self.consume_word("ROWS") {
+                self.consume_word("ROW");
+            }
+            offset = Some(count);
+        }
+
+        if self.consume_word("FETCH") {
+            if limit.is_some() {
+                return Err("FETCH cannot be combined with LIMIT".to_string());
+            }
+            limit = Some(self.parse_fetch()?);
+        }
+
         Ok(Select {
+            with,
+            distinct,
             columns,
             table,
             joins,
@@ -218,38 +569,364 @@ impl<'a> Parser<'a> {
             group_by,
             having,
             order_by,
+            limit,
+            offset,
         })
     }
 
+    /// Parses `WITH [RECURSIVE] name [(column, ...)] AS (query), ...`.
+    fn parse_with(&mut self) -> Result<With, String> {
+        self.expect_keyword("WITH")?;
+        let recursive = self.consume_keyword("RECURSIVE");
+        let mut ctes: Vec<Cte> = Vec::new();
+        loop {
+            let name = match self.current_token.clone() {
+                Some(Token::Identifier(name)) => {
+                    self.next_token();
+                    name
+                }
+                _ => return Err(self.identifier_error("Expected a CTE name")),
+            };
+            if ctes.iter().any(|cte| cte.name.eq_ignore_ascii_case(&name)) {
+                return Err(format!("CTE '{}' is defined more than once", name));
+            }
+
+            let mut columns = Vec::new();
+            if self.consume_token(&Token::LeftParen) {
+                loop {
+                    columns.push(self.parse_column_name()?);
+                    if !self.consume_token(&Token::Comma) {
+                        break;
+                    }
+                }
+                self.expect_token(&Token::RightParen)?;
+            }
+
+            self.expect_keyword("AS")?;
+            self.expect_token(&Token::LeftParen)?;
+            let query = self.parse_set_query()?;
+            self.expect_token(&Token::RightParen)?;
+            Self::validate_cte(&name, &query, recursive)?;
+            ctes.push(Cte {
+                name,
+                columns,
+                query,
+            });
+
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(With { recursive, ctes })
+    }
+
+    /// Parses a SELECT, or SELECTs joined by `UNION [ALL]`, grouping from the left.
+    fn parse_set_query(&mut self) -> Result<SetQuery, String> {
+        let mut query = SetQuery::Select(Box::new(self.parse_select_inner()?));
+        while self.consume_keyword("UNION") {
+            let all = self.consume_keyword("ALL");
+            let right = SetQuery::Select(Box::new(self.parse_select_inner()?));
+            query = SetQuery::Union {
+                left: Box::new(query),
+                right: Box::new(right),
+                all,
+            };
+        }
+        Ok(query)
+    }
+
+    /// Checks a CTE that reads itself: only WITH RECURSIVE allows that, and its body must be
+    /// `base UNION [ALL] recursive term` where the base query does not read the CTE.
+    fn validate_cte(name: &str, query: &SetQuery, recursive: bool) -> Result<(), String> {
+        if !query.reads_table(name) {
+            return Ok(());
+        }
+        if !recursive {
+            return Err(format!(
+                "CTE '{}' refers to itself; declare it with WITH RECURSIVE",
+                name
+            ));
+        }
+        match query {
+            SetQuery::Union { left, .. } if left.reads_table(name) => Err(format!(
+                "The base query of recursive CTE '{}' cannot refer to '{}'",
+                name, name
+            )),
+            SetQuery::Union { .. } => Ok(()),
+            SetQuery::Select(_) => Err(format!(
+                "Recursive CTE '{}' must be a base query UNION [ALL] a recursive term",
+                name
+            )),
+        }
+    }
+
+    /// Parses the optional `[AS] alias` after a select list expression.
+    fn parse_column_alias(&mut self) -> Result<Option<String>, String> {
+        if self.consume_keyword("AS") {
+            match self.current_token.clone() {
+                Some(Token::Identifier(alias)) => {
+                    self.next_token();
+                    Ok(Some(alias))
+                }
+                _ => Err(self.identifier_error("Expected a column alias after AS")),
+            }
+        } else if let Some(Token::Identifier(alias)) = self.current_token.clone() {
+            self.next_token();
+            Ok(Some(alias))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Parses the rest of `FETCH {FIRST | NEXT} [count] {ROW | ROWS} ONLY`, returning the count,
+    /// which is 1 when omitted.
+    fn parse_fetch(&mut self) -> Result<Expression, String> {
+        if !self.consume_word("FIRST") && !self.consume_word("NEXT") {
+            return Err("Expected FIRST or NEXT after FETCH".to_string());
+        }
+        let count = if self.peek_word("ROW") || self.peek_word("ROWS") {
+            Expression::Integer(1)
+        } else {
+            self.parse_additive_expression()?
+        };
+        if !self.consume_word("ROWS") && !self.consume_word("ROW") {
+            return Err("Expected ROW or ROWS in FETCH clause".to_string());
+        }
+        self.expect_word("ONLY")?;
+        Ok(count)
+    }
+
+    /// Parses the optional `EXCEPT (column, ...)` and `REPLACE (expr AS column, ...)` modifiers
+    /// after a `*` in a select list.
+    fn parse_wildcard_modifiers(&mut self) -> Result<Expression, String> {
+        let mut except = Vec::new();
+        if self.consume_word("EXCEPT") {
+            self.expect_token(&Token::LeftParen)?;
+            loop {
+                except.push(self.parse_column_name()?);
+                if !self.consume_token(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect_token(&Token::RightParen)?;
+        }
+
+        let mut replace = Vec::new();
+        if self.consume_word("REPLACE") {
+            self.expect_token(&Token::LeftParen)?;
+            loop {
+                let expression = self.parse_expression()?;
+                self.expect_keyword("AS")?;
+                let column = self.parse_column_name()?;
+                replace.push(Replacement { expression, column });
+                if !self.consume_token(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect_token(&Token::RightParen)?;
+        }
+
+        if except.is_empty() && replace.is_empty() {
+            Ok(Expression::Asterisk)
+        } else {
+            Ok(Expression::Wildcard { except, replace })
+        }
+    }
+
+    fn parse_column_name(&mut self) -> Result<String, String> {
+        if let Some(Token::Identifier(ref name)) = self.current_token {
+            let name = name.clone();
+            self.next_token();
+            Ok(name)
+        } else {
+            Err(self.identifier_error("I was expecting a column name."))
+        }
+    }
+
+    /// Describes a missing identifier. A keyword in its place gets a hint to quote it, since
+    /// reserved keywords are only identifiers when quoted.
+    fn identifier_error(&self, expected: &str) -> String {
+        match &self.current_token {
+            Some(Token::Keyword(keyword)) => format!(
+                "Reserved keyword '{}' cannot be used as an identifier; quote it as \"{}\"",
+                keyword.to_uppercase(),
+                keyword.to_lowercase()
+            ),
+            _ => expected.to_string(),
+        }
+    }
+
     fn parse_table_with_joins(&mut self) -> Result<(Table, Vec<Join>), String> {
-        let table = self.parse_table()?;
+        let table = self.parse_table_reference()?;
         let mut joins = Vec::new();
-        while self.peek_keyword("JOIN") {
-            let join = self.parse_join_clause()?;
-            joins.push(join);
+        loop {
+            if let Some(join_type) = self.consume_join_type()? {
+                joins.push(self.parse_join_clause(join_type)?);
+            } else if self.consume_token(&Token::Comma) {
+                // `FROM a, b` joins every row of `a` with every row of `b`, like a JOIN without ON
+                let table = self.parse_table_reference()?;
+                joins.push(Join {
+                    join_type: JoinType::Cross,
+                    table,
+                    condition: None,
+                });
+            } else {
+                break;
+            }
         }
         Ok((table, joins))
     }
 
     fn parse_table(&mut self) -> Result<Table, String> {
         if let Some(Token::Identifier(ref name)) = self.current_token {
-            let table = Table { name: name.clone() };
+            let table = Table {
+                name: name.clone(),
+                alias: None,
+                sample: None,
+                subquery: None,
+                lateral: false,
+            };
             self.next_token();
             Ok(table)
         } else {
-            Err("I was expecting a table name".to_string())
+            Err(self.identifier_error("I was expecting a table name"))
+        }
+    }
+
+    /// Parses a table in a FROM or JOIN clause, which may be followed by an alias, with or
+    /// without AS, and a TABLESAMPLE clause, or a parenthesized subquery, optionally LATERAL.
+    fn parse_table_reference(&mut self) -> Result<Table, String> {
+        let lateral = self.consume_keyword("LATERAL");
+        if self.current_token == Some(Token::LeftParen) {
+            return self.parse_derived_table(lateral);
+        }
+        if lateral {
+            return Err("LATERAL must be followed by a subquery in parentheses".to_string());
+        }
+
+        let mut table = self.parse_table()?;
+        if self.consume_keyword("AS") {
+            match self.current_token.clone() {
+                Some(Token::Identifier(alias)) => {
+                    table.alias = Some(alias);
+                    self.next_token();
+                }
+                _ => return Err(self.identifier_error("Expected a table alias after AS")),
+            }
+        } else if let Some(Token::Identifier(alias)) = self.current_token.clone() {
+            // A keyword such as WHERE or JOIN lexes as a keyword, so it is never taken as an
+            // alias. A contextual keyword may start the next clause, so it needs AS or quotes.
+            if !self.peek_contextual_keyword() {
+                table.alias = Some(alias);
+                self.next_token();
+            }
+        }
+        if self.consume_keyword("TABLESAMPLE") {
+            let method = if self.consume_keyword("BERNOULLI") {
+                SampleMethod::Bernoulli
+            } else if self.consume_keyword("SYSTEM") {
+                SampleMethod::System
+            } else {
+                return Err("Expected BERNOULLI or SYSTEM after TABLESAMPLE".to_string());
+            };
+
+            self.expect_token(&Token::LeftParen)?;
+            let percent = match self.current_token {
+                Some(Token::Integer(i)) => i as f64,
+                Some(Token::Float(f)) => f,
+                _ => return Err("Expected a sample percentage".to_string()),
+            };
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(format!(
+                    "Sample percentage must be between 0 and 100, got {}",
+                    percent
+                ));
+            }
+            self.next_token();
+            self.expect_token(&Token::RightParen)?;
+
+            table.sample = Some(TableSample { method, percent });
         }
+        Ok(table)
+    }
+
+    /// Parses `(SELECT ...) [AS] alias`. Unlike a table, the subquery has no name of its own, so
+    /// the alias is required.
+    fn parse_derived_table(&mut self, lateral: bool) -> Result<Table, String> {
+        self.expect_token(&Token::LeftParen)?;
+        let subquery = self.parse_select_inner()?;
+        self.expect_token(&Token::RightParen)?;
+        let explicit = self.consume_keyword("AS");
+        let alias = match self.current_token.clone() {
+            Some(Token::Identifier(alias)) if explicit || !self.peek_contextual_keyword() => {
+                Some(alias)
+            }
+            _ => None,
+        };
+        let Some(alias) = alias else {
+            return Err(if explicit {
+                self.identifier_error("Expected a table alias after AS")
+            } else {
+                "A subquery in FROM must have an alias".to_string()
+            });
+        };
+        self.next_token();
+        Ok(Table {
+            name: alias,
+            alias: None,
+            sample: None,
+            subquery: Some(Box::new(subquery)),
+            lateral,
+        })
     }
 
-    fn parse_join_clause(&mut self) -> Result<Join, String> {
+    /// Consumes the keywords that open a join, `[INNER] JOIN`, `LEFT|RIGHT|FULL [OUTER] JOIN`
+    /// or `CROSS JOIN`, and returns its type, or `None` if no join follows.
+    fn consume_join_type(&mut self) -> Result<Option<JoinType>, String> {
+        let join_type = if self.consume_word("INNER") {
+            JoinType::Inner
+        } else if self.consume_word("LEFT") {
+            JoinType::Left
+        } else if self.consume_word("RIGHT") {
+            JoinType::Right
+        } else if self.consume_word("FULL") {
+            JoinType::Full
+        } else if self.consume_word("CROSS") {
+            JoinType::Cross
+        } else if self.peek_keyword("JOIN") {
+            JoinType::Inner
+        } else {
+            return Ok(None);
+        };
+        if matches!(join_type, JoinType::Left | JoinType::Right | JoinType::Full) {
+            self.consume_word("OUTER");
+        }
         self.expect_keyword("JOIN")?;
-        let table = self.parse_table()?;
+        Ok(Some(join_type))
+    }
+
+    /// Parses the table and ON condition of a join whose keywords have been consumed. Outer
+    /// joins need a condition and cross joins cannot have one.
+    fn parse_join_clause(&mut self, join_type: JoinType) -> Result<Join, String> {
+        let table = self.parse_table_reference()?;
         let condition = if self.consume_keyword("ON") {
+            if join_type == JoinType::Cross {
+                return Err("CROSS JOIN cannot have an ON condition".to_string());
+            }
             Some(self.parse_logical_expression()?)
         } else {
             None
         };
-        Ok(Join { table, condition })
+        if condition.is_none()
+            && matches!(join_type, JoinType::Left | JoinType::Right | JoinType::Full)
+        {
+            return Err(format!("{} requires an ON condition", join_type.to_sql()));
+        }
+        Ok(Join {
+            join_type,
+            table,
+            condition,
+        })
     }
 
     fn parse_logical_expression(&mut self) -> Result<Expression, String> {
@@ -274,41 +951,103 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// Parses any number of `NOT`s before a comparison. `NOT` binds looser than comparison
+    /// operators but tighter than AND, so `NOT a = b AND c` is `(NOT (a = b)) AND c`.
     fn parse_not_expression(&mut self) -> Result<Expression, String> {
         if self.consume_keyword("NOT") {
-            let expr = self.parse_primary_expression()?;
+            let expr = self.parse_not_expression()?;
             Ok(Expression::Not(Box::new(expr)))
         } else {
-            self.parse_primary_expression()
+            self.parse_comparison_expression()
         }
     }
 
-    fn parse_primary_expression(&mut self) -> Result<Expression, String> {
-        if self.consume_token(&Token::LeftParen) {
-            let expr = self.parse_logical_expression()?;
+    /// Finishes a parenthesized group whose first expression has been parsed. A top-level
+    /// comma makes the group a row constructor rather than a grouped expression.
+    fn parse_parenthesized_rest(&mut self, first: Expression) -> Result<Expression, String> {
+        if !self.consume_token(&Token::Comma) {
             self.expect_token(&Token::RightParen)?;
-            Ok(expr)
-        } else {
-            self.parse_comparison_expression()
+            return Ok(first);
         }
+        let mut elements = vec![first];
+        loop {
+            elements.push(self.parse_expression()?);
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect_token(&Token::RightParen)?;
+        Ok(Expression::Row(elements))
     }
 
+    /// Parses an additive expression and at most one comparison after it. Comparisons do not
+    /// associate, so `a = b = c` is rejected rather than stopping after `a = b`.
     fn parse_comparison_expression(&mut self) -> Result<Expression, String> {
-        let left = self.parse_term()?;
-        if let Some(op) = self.current_token.clone() {
-            let operator = match op {
-                Token::Equal => Some(BinaryOperator::Equal),
-                Token::NotEqual => Some(BinaryOperator::NotEqual),
-                Token::LessThan => Some(BinaryOperator::LessThan),
-                Token::LessThanOrEqual => Some(BinaryOperator::LessThanOrEqual),
-                Token::GreaterThan => Some(BinaryOperator::GreaterThan),
-                Token::GreaterThanOrEqual => Some(BinaryOperator::GreaterThanOrEqual),
-                _ => None,
-            };
+        let left = self.parse_additive_expression()?;
+        let expr = self.parse_comparison_rest(left)?;
+        if self.at_comparison() {
+            return Err(
+                "Comparisons cannot be chained; combine them with AND or use parentheses"
+                    .to_string(),
+            );
+        }
+        Ok(expr)
+    }
+
+    /// Returns whether the current token starts a comparison.
+    fn at_comparison(&mut self) -> bool {
+        matches!(
+            self.current_token,
+            Some(
+                Token::Equal
+                    | Token::NotEqual
+                    | Token::LessThan
+                    | Token::LessThanOrEqual
+                    | Token::GreaterThan
+                    | Token::GreaterThanOrEqual
+            )
+        ) || self.peek_keyword("BETWEEN")
+            || self.peek_keyword("IN")
+            || self.peek_keyword("LIKE")
+            || (self.peek_keyword("NOT")
+                && matches!(self.lexer.peek_token(), Some(Token::Keyword(kw)) if kw == "BETWEEN"))
+    }
+
+    /// Parses the comparison, LIKE, IN or BETWEEN that may follow an already parsed left operand.
+    fn parse_comparison_rest(&mut self, left: Expression) -> Result<Expression, String> {
+        if self.peek_keyword("NOT")
+            && matches!(self.lexer.peek_token(), Some(Token::Keyword(kw)) if kw == "BETWEEN")
+        {
+            self.next_token();
+            self.next_token();
+            return self.parse_between(left, true);
+        }
+        if self.consume_keyword("BETWEEN") {
+            return self.parse_between(left, false);
+        }
+        if self.consume_keyword("IN") {
+            return self.parse_in_list(left);
+        }
+        if self.consume_keyword("LIKE") {
+            return self.parse_like(left);
+        }
+        if let Some(op) = self.current_token.clone() {
+            let operator = match op {
+                Token::Equal => Some(BinaryOperator::Equal),
+                Token::NotEqual => Some(BinaryOperator::NotEqual),
+                Token::LessThan => Some(BinaryOperator::LessThan),
+                Token::LessThanOrEqual => Some(BinaryOperator::LessThanOrEqual),
+                Token::GreaterThan => Some(BinaryOperator::GreaterThan),
+                Token::GreaterThanOrEqual => Some(BinaryOperator::GreaterThanOrEqual),
+                _ => None,
+            };
 
             if let Some(op) = operator {
                 self.next_token();
-                let right = self.parse_term()?;
+                if let Some(quantifier) = self.consume_quantifier() {
+                    return self.parse_quantified(left, op, quantifier);
+                }
+                let right = self.parse_additive_expression()?;
                 Ok(Expression::Binary {
                     left: Box::new(left),
                     operator: op,
@@ -322,10 +1061,194 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Consumes an `ANY`, `SOME` or `ALL` keyword following a comparison operator.
+    fn consume_quantifier(&mut self) -> Option<Quantifier> {
+        if self.consume_keyword("ANY") || self.consume_keyword("SOME") {
+            Some(Quantifier::Any)
+        } else if self.consume_keyword("ALL") {
+            Some(Quantifier::All)
+        } else {
+            None
+        }
+    }
+
+    /// Parses the parenthesized subquery or array expression after `left op ANY`.
+    fn parse_quantified(
+        &mut self,
+        left: Expression,
+        operator: BinaryOperator,
+        quantifier: Quantifier,
+    ) -> Result<Expression, String> {
+        self.expect_token(&Token::LeftParen)?;
+        let right = if self.peek_keyword("SELECT") {
+            QuantifiedOperand::Subquery(Box::new(self.parse_select_inner()?))
+        } else {
+            QuantifiedOperand::Array(Box::new(self.parse_expression()?))
+        };
+        self.expect_token(&Token::RightParen)?;
+        Ok(Expression::Quantified {
+            left: Box::new(left),
+            operator,
+            quantifier,
+            right,
+        })
+    }
+
+    /// Parses the `low AND high` bounds of `expr [NOT] BETWEEN low AND high`. The bounds are
+    /// additive expressions, so the AND between them is not taken as a logical AND.
+    fn parse_between(&mut self, expr: Expression, negated: bool) -> Result<Expression, String> {
+        let low = self.parse_additive_expression()?;
+        self.expect_keyword("AND")?;
+        let high = self.parse_additive_expression()?;
+        Ok(Expression::Between {
+            expr: Box::new(expr),
+            low: Box::new(low),
+            high: Box::new(high),
+            negated,
+        })
+    }
+
+    /// Parses the parenthesized value list of `expr IN (...)`.
+    fn parse_in_list(&mut self, expr: Expression) -> Result<Expression, String> {
+        self.expect_token(&Token::LeftParen)?;
+        let mut list = Vec::new();
+        loop {
+            list.push(self.parse_expression()?);
+            Self::check_limit(list.len(), self.limits.max_in_list, "IN list values")?;
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect_token(&Token::RightParen)?;
+        Ok(Expression::InList {
+            expr: Box::new(expr),
+            list,
+        })
+    }
+
+    /// Parses the pattern and optional `ESCAPE 'c'` clause of `expr LIKE pattern`.
+    fn parse_like(&mut self, expr: Expression) -> Result<Expression, String> {
+        let pattern = self.parse_additive_expression()?;
+        let escape = if self.consume_keyword("ESCAPE") {
+            let Some(Token::StringLiteral(escape)) = self.current_token.clone() else {
+                return Err("Expected a string literal after 'ESCAPE'".to_string());
+            };
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    self.next_token();
+                    Some(c)
+                }
+                _ => {
+                    return Err(format!(
+                        "ESCAPE must be a single character, got '{}'",
+                        escape
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+        Ok(Expression::Like {
+            expr: Box::new(expr),
+            pattern: Box::new(pattern),
+            escape,
+        })
+    }
+
+    /// Parses left-associative `+` and `-`, which bind tighter than comparisons.
+    /// Either side may be an INTERVAL literal, as in `ts + INTERVAL '1 day'`.
+    fn parse_additive_expression(&mut self) -> Result<Expression, String> {
+        let mut expr = self.parse_multiplicative_expression()?;
+        loop {
+            let operator = match self.current_token {
+                Some(Token::Plus) => BinaryOperator::Plus,
+                Some(Token::Minus) => BinaryOperator::Minus,
+                _ => break,
+            };
+            self.next_token();
+            let right = self.parse_multiplicative_expression()?;
+            expr = Expression::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// Parses left-associative `*`, `/` and `%`, which bind tighter than `+` and `-`.
+    fn parse_multiplicative_expression(&mut self) -> Result<Expression, String> {
+        let mut expr = self.parse_unary_expression()?;
+        loop {
+            let operator = match self.current_token {
+                Some(Token::Asterisk) => BinaryOperator::Multiply,
+                Some(Token::Slash) => BinaryOperator::Divide,
+                Some(Token::Percent) => BinaryOperator::Modulo,
+                _ => break,
+            };
+            self.next_token();
+            let right = self.parse_unary_expression()?;
+            expr = Expression::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// Parses prefix `-` and `+`, which bind tighter than `*` and `/`. A minus sign right before
+    /// a number literal makes a negative literal, and `+` changes nothing.
+    fn parse_unary_expression(&mut self) -> Result<Expression, String> {
+        let negate = match self.current_token {
+            Some(Token::Minus) => true,
+            Some(Token::Plus) => false,
+            _ => return self.parse_json_access_expression(),
+        };
+        self.next_token();
+        let literal = matches!(
+            self.current_token,
+            Some(Token::Integer(_) | Token::Float(_))
+        );
+        let operand = self.parse_unary_expression()?;
+        Ok(match operand {
+            operand if !negate => operand,
+            Expression::Integer(i) if literal => Expression::Integer(-i),
+            Expression::Float(f) if literal => Expression::Float(-f),
+            operand => Expression::Negate(Box::new(operand)),
+        })
+    }
+
+    /// Parses `->` and `->>` JSON accessors, which bind tighter than comparisons.
+    fn parse_json_access_expression(&mut self) -> Result<Expression, String> {
+        let mut expr = self.parse_term()?;
+        loop {
+            let operator = match self.current_token {
+                Some(Token::Arrow) => BinaryOperator::JsonGet,
+                Some(Token::LongArrow) => BinaryOperator::JsonGetText,
+                _ => break,
+            };
+            self.next_token();
+            let right = self.parse_term()?;
+            expr = Expression::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
     fn parse_group_by_clause(&mut self) -> Result<Vec<Expression>, String> {
         let mut expressions = Vec::new();
         loop {
             expressions.push(self.parse_expression()?);
+            Self::check_limit(
+                expressions.len(),
+                self.limits.max_group_by,
+                "GROUP BY expressions",
+            )?;
             if !self.consume_token(&Token::Comma) {
                 break;
             }
@@ -337,6 +1260,15 @@ impl<'a> Parser<'a> {
         let mut orderings = Vec::new();
         loop {
             let expr = self.parse_expression()?;
+            let collation = if self.consume_keyword("COLLATE") {
+                let Some(Token::Identifier(name)) = self.current_token.clone() else {
+                    return Err("Expected a collation name after 'COLLATE'".to_string());
+                };
+                self.next_token();
+                Some(name)
+            } else {
+                None
+            };
             let direction = if self.consume_keyword("ASC") {
                 SortOrder::Ascending
             } else if self.consume_keyword("DESC") {
@@ -347,7 +1279,9 @@ impl<'a> Parser<'a> {
             orderings.push(Ordering {
                 expression: expr,
                 direction,
+                collation,
             });
+            Self::check_limit(orderings.len(), self.limits.max_order_by, "ORDER BY items")?;
             if !self.consume_token(&Token::Comma) {
                 break;
             }
@@ -369,10 +1303,29 @@ impl<'a> Parser<'a> {
                 self.next_token();
                 Ok(Value::Float(f))
             }
+            Some(sign @ (Token::Minus | Token::Plus)) => {
+                self.next_token();
+                let negate = sign == Token::Minus;
+                let value = match self.current_token {
+                    Some(Token::Integer(i)) => Value::Integer(if negate { -i } else { i }),
+                    Some(Token::Float(f)) => Value::Float(if negate { -f } else { f }),
+                    _ => return Err("Expected a number after the sign".to_string()),
+                };
+                self.next_token();
+                Ok(value)
+            }
             Some(Token::StringLiteral(ref s)) => {
                 self.next_token();
                 Ok(Value::Text(s.clone()))
             }
+            Some(Token::Blob(bytes)) => {
+                self.next_token();
+                Ok(Value::Blob(bytes))
+            }
+            Some(Token::Keyword(ref kw)) if kw == "DEFAULT" => {
+                self.next_token();
+                Ok(Value::Default)
+            }
             Some(Token::Null) => {
                 self.next_token();
                 Ok(Value::Null)
@@ -381,38 +1334,215 @@ impl<'a> Parser<'a> {
                 self.next_token();
                 Ok(Value::Boolean(b))
             }
+            Some(Token::Identifier(ref type_name)) => {
+                let type_name = type_name.to_uppercase();
+                self.next_token();
+                self.parse_typed_literal(&type_name)
+            }
             _ => Err("This is an unexpected token.".to_string()),
         }
     }
 
+    /// Parses the string following a type name, as in `JSON '{"a": 1}'`.
+    fn parse_typed_literal(&mut self, type_name: &str) -> Result<Value, String> {
+        let literal = match self.current_token.clone() {
+            Some(Token::StringLiteral(s)) => s,
+            _ => return Err(format!("Expected a string literal after '{}'", type_name)),
+        };
+        self.next_token();
+
+        match type_name {
+            #[cfg(feature = "json")]
+            "JSON" => serde_json::from_str(&literal)
+                .map(Value::Json)
+                .map_err(|e| format!("Invalid JSON literal '{}': {}", literal, e)),
+            #[cfg(not(feature = "json"))]
+            "JSON" => Err(format!(
+                "JSON literal '{}' requires the `json` feature",
+                literal
+            )),
+            #[cfg(feature = "uuid")]
+            "UUID" => parse_uuid(&literal).map(Value::Uuid).ok_or_else(|| {
+                format!(
+                    "Invalid UUID literal '{}': expected 32 hex digits grouped 8-4-4-4-12",
+                    literal
+                )
+            }),
+            #[cfg(not(feature = "uuid"))]
+            "UUID" => Err(format!(
+                "UUID literal '{}' requires the `uuid` feature",
+                literal
+            )),
+            _ => Err(format!("Unknown literal type '{}'", type_name)),
+        }
+    }
+
+    /// Parses the `FILTER (WHERE condition)` that may follow a call to an aggregate function.
+    fn parse_aggregate_filter(
+        &mut self,
+        function: &str,
+    ) -> Result<Option<Box<Expression>>, String> {
+        if !self.consume_keyword("FILTER") {
+            return Ok(None);
+        }
+        if !is_aggregate_function(function) {
+            return Err(format!(
+                "FILTER is only allowed on aggregate functions, not '{}'",
+                function
+            ));
+        }
+        self.expect_token(&Token::LeftParen)?;
+        self.expect_keyword("WHERE")?;
+        let condition = self.parse_logical_expression()?;
+        self.expect_token(&Token::RightParen)?;
+        Ok(Some(Box::new(condition)))
+    }
+
+    /// Parses a type name such as `INTEGER` or `text`, ignoring case.
+    fn parse_data_type(&mut self) -> Result<DataType, String> {
+        let name = match &self.current_token {
+            Some(Token::Identifier(name)) => name.clone(),
+            other => return Err(format!("Expected a type name, found {:?}", other)),
+        };
+        let data_type = match name.to_uppercase().as_str() {
+            "INTEGER" | "INT" => DataType::Integer,
+            "FLOAT" | "REAL" => DataType::Float,
+            "TEXT" | "VARCHAR" => DataType::Text,
+            "BOOLEAN" | "BOOL" => DataType::Boolean,
+            #[cfg(feature = "json")]
+            "JSON" => DataType::Json,
+            _ => return Err(format!("Unknown data type '{}'", name)),
+        };
+        self.next_token();
+        Ok(data_type)
+    }
+
+    /// Parses the bracketed element list of an `ARRAY[...]` literal.
+    fn parse_array(&mut self) -> Result<Expression, String> {
+        self.expect_token(&Token::LeftBracket)?;
+        let mut elements = Vec::new();
+        if !self.consume_token(&Token::RightBracket) {
+            loop {
+                elements.push(self.parse_expression()?);
+                if !self.consume_token(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect_token(&Token::RightBracket)?;
+        }
+        Ok(Expression::Array(elements))
+    }
+
+    /// Parses an operand followed by any `[index]` subscripts and `AT TIME ZONE zone` suffixes.
     fn parse_term(&mut self) -> Result<Expression, String> {
+        let mut expr = self.parse_operand()?;
+        loop {
+            if self.consume_token(&Token::LeftBracket) {
+                let index = self.parse_expression()?;
+                self.expect_token(&Token::RightBracket)?;
+                expr = Expression::Subscript {
+                    base: Box::new(expr),
+                    index: Box::new(index),
+                };
+            } else if self.consume_token(&Token::DoubleColon) {
+                let data_type = self.parse_data_type()?;
+                expr = Expression::Cast {
+                    expr: Box::new(expr),
+                    data_type,
+                };
+            } else if self.consume_keyword("AT") {
+                self.expect_word("TIME")?;
+                self.expect_word("ZONE")?;
+                let zone = self.parse_operand()?;
+                expr = Expression::AtTimeZone {
+                    expr: Box::new(expr),
+                    zone: Box::new(zone),
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Expression, String> {
         match self.current_token.clone() {
             Some(Token::Identifier(ref name)) => {
-                let identifier = name.clone();
+                let mut identifier = name.clone();
+                let quoted = self.current_token_is_quoted();
                 self.next_token();
                 if self.consume_token(&Token::Dot) {
                     if let Some(Token::Identifier(ref field)) = self.current_token {
-                        let field_name = format!("{}.{}", identifier, field);
+                        // Either a qualified column or a qualified function name
+                        identifier = format!("{}.{}", identifier, field);
                         self.next_token();
-                        Ok(Expression::Identifier(field_name))
                     } else {
-                        Err("I was expecting a field name.".to_string())
+                        return Err(self.identifier_error("I was expecting a field name."));
                     }
-                } else if self.consume_token(&Token::LeftParen) {
+                }
+                if !quoted
+                    && identifier.eq_ignore_ascii_case("CAST")
+                    && self.consume_token(&Token::LeftParen)
+                {
+                    // `CAST(expr AS type)`, the standard spelling of `expr::type`
+                    let expr = self.parse_expression()?;
+                    self.expect_keyword("AS")?;
+                    let data_type = self.parse_data_type()?;
+                    self.expect_token(&Token::RightParen)?;
+                    return Ok(Expression::Cast {
+                        expr: Box::new(expr),
+                        data_type,
+                    });
+                }
+                if self.consume_token(&Token::LeftParen) {
                     let mut args = Vec::new();
+                    let mut named_args = Vec::new();
+                    let mut order_by = Vec::new();
                     if !self.consume_token(&Token::RightParen) {
                         loop {
-                            let expr = self.parse_expression()?;
-                            args.push(expr);
-                            if self.consume_token(&Token::Comma) {
-                                continue;
+                            let arg_name = match &self.current_token {
+                                Some(Token::Identifier(name))
+                                    if self.lexer.peek_token() == Some(&Token::FatArrow) =>
+                                {
+                                    Some(name.clone())
+                                }
+                                _ => None,
+                            };
+                            if let Some(arg_name) = arg_name {
+                                self.next_token();
+                                self.next_token();
+                                named_args.push((arg_name, self.parse_expression()?));
+                            } else if named_args.is_empty() {
+                                args.push(self.parse_expression()?);
                             } else {
-                                self.expect_token(&Token::RightParen)?;
+                                return Err(format!(
+                                    "Positional arguments cannot follow named arguments in a call to '{}'",
+                                    identifier
+                                ));
+                            }
+                            if !self.consume_token(&Token::Comma) {
                                 break;
                             }
                         }
+                        if self.consume_keyword("ORDER") {
+                            if !is_aggregate_function(&identifier) {
+                                return Err(format!(
+                                    "ORDER BY is only allowed in aggregate function calls, not '{}'",
+                                    identifier
+                                ));
+                            }
+                            self.expect_keyword("BY")?;
+                            order_by = self.parse_order_by_clause()?;
+                        }
+                        self.expect_token(&Token::RightParen)?;
                     }
-                    Ok(Expression::Function(identifier, args))
+                    let filter = self.parse_aggregate_filter(&identifier)?;
+                    Ok(Expression::Function {
+                        name: identifier,
+                        args,
+                        named_args,
+                        filter,
+                        order_by,
+                    })
                 } else {
                     Ok(Expression::Identifier(identifier))
                 }
@@ -429,6 +1559,14 @@ impl<'a> Parser<'a> {
                 self.next_token();
                 Ok(Expression::Text(s.clone()))
             }
+            Some(Token::Blob(bytes)) => {
+                self.next_token();
+                Ok(Expression::Blob(bytes))
+            }
+            Some(Token::Keyword(ref kw)) if kw == "DEFAULT" => {
+                self.next_token();
+                Ok(Expression::Default)
+            }
             Some(Token::Null) => {
                 self.next_token();
                 Ok(Expression::Identifier("NULL".to_string()))
@@ -441,7 +1579,1940 @@ impl<'a> Parser<'a> {
                 self.next_token();
                 Ok(Expression::Asterisk)
             }
+            Some(Token::Keyword(ref kw)) if kw == "EXISTS" => {
+                self.next_token();
+                self.expect_token(&Token::LeftParen)?;
+                let subquery = self.parse_select_inner()?;
+                self.expect_token(&Token::RightParen)?;
+                Ok(Expression::Exists(Box::new(subquery)))
+            }
+            Some(Token::Keyword(ref kw)) if kw == "ARRAY" => {
+                self.next_token();
+                self.parse_array()
+            }
+            Some(Token::LeftParen) => {
+                // A parenthesized operand, as in `a - (b - c)`, or a row such as `(1, 2)`
+                self.next_token();
+                let expr = self.parse_expression()?;
+                self.parse_parenthesized_rest(expr)
+            }
+            Some(Token::Keyword(ref kw)) if kw == "INTERVAL" => {
+                self.next_token();
+                match self.current_token.clone() {
+                    Some(Token::StringLiteral(interval)) => {
+                        self.next_token();
+                        Ok(Expression::Interval(interval))
+                    }
+                    _ => Err("Expected a string literal after 'INTERVAL'".to_string()),
+                }
+            }
+            Some(Token::Keyword(_)) => Err(self.identifier_error("This is an unexpected token.")),
             _ => Err("This is an unexpected token.".to_string()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(sql: &str) -> Result<Query, String> {
+        Parser::new(sql)?.parse()
+    }
+
+    fn select_items(expressions: Vec<Expression>) -> Vec<SelectItem> {
+        expressions.into_iter().map(SelectItem::from).collect()
+    }
+
+    fn parse_where(condition: &str) -> Expression {
+        let sql = format!("SELECT a FROM t WHERE {}", condition);
+        let Query::Select(select) = parse(&sql).unwrap() else {
+            panic!("Expected SELECT query");
+        };
+        select.where_clause.unwrap()
+    }
+
+    fn identifier(name: &str) -> Box<Expression> {
+        Box::new(Expression::Identifier(name.to_string()))
+    }
+
+    /// Tests select list aliases with and without AS, and items without one.
+    #[test]
+    fn test_column_alias() {
+        let Query::Select(select) = parse("SELECT COUNT(*) AS total, name n, id FROM t").unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        let aliases: Vec<Option<&str>> = select
+            .columns
+            .iter()
+            .map(|item| item.alias.as_deref())
+            .collect();
+        assert_eq!(aliases, vec![Some("total"), Some("n"), None]);
+        assert!(matches!(
+            &select.columns[0].expression,
+            Expression::Function { name, .. } if name == "COUNT"
+        ));
+        assert_eq!(select.columns[1].expression, *identifier("name"));
+
+        assert_eq!(
+            parse("SELECT a AS FROM t").unwrap_err(),
+            "Reserved keyword 'FROM' cannot be used as an identifier; quote it as \"from\" \
+             at line 1, column 13"
+        );
+        assert!(parse("SELECT a AS \"from\" FROM t").is_ok());
+    }
+
+    /// Tests SELECT DISTINCT with `*` and a column list, and that a quoted column named
+    /// `distinct` is an ordinary select list item.
+    #[test]
+    fn test_select_distinct() {
+        let select = |sql: &str| match parse(sql).unwrap() {
+            Query::Select(select) => *select,
+            other => panic!("Expected a SELECT, got {:?}", other),
+        };
+
+        let distinct = select("SELECT DISTINCT * FROM t");
+        assert!(distinct.distinct);
+        assert_eq!(distinct.columns, select_items(vec![Expression::Asterisk]));
+
+        let distinct = select("select distinct a, b FROM t");
+        assert!(distinct.distinct);
+        assert_eq!(
+            distinct.columns,
+            select_items(vec![*identifier("a"), *identifier("b")])
+        );
+
+        let plain = select("SELECT a, b FROM t");
+        assert!(!plain.distinct);
+
+        let quoted = select("SELECT \"distinct\" FROM t");
+        assert!(!quoted.distinct);
+        assert_eq!(quoted.columns, select_items(vec![*identifier("distinct")]));
+
+        assert!(parse("SELECT DISTINCT FROM t").is_err());
+    }
+
+    /// Tests table aliases with and without AS, and that a following keyword is not an alias.
+    #[test]
+    fn test_table_alias() {
+        let tables = |sql: &str| match parse(sql).unwrap() {
+            Query::Select(select) => std::iter::once(select.table)
+                .chain(select.joins.into_iter().map(|join| join.table))
+                .map(|table| (table.name, table.alias))
+                .collect::<Vec<_>>(),
+            other => panic!("Expected a SELECT, got {:?}", other),
+        };
+        let alias = |name: &str, alias: Option<&str>| (name.to_string(), alias.map(String::from));
+
+        assert_eq!(tables("SELECT * FROM t"), vec![alias("t", None)]);
+        assert_eq!(tables("SELECT * FROM t x"), vec![alias("t", Some("x"))]);
+        assert_eq!(tables("SELECT * FROM t AS x"), vec![alias("t", Some("x"))]);
+        assert_eq!(
+            tables("SELECT * FROM t WHERE a = 1 ORDER BY a"),
+            vec![alias("t", None)]
+        );
+        assert_eq!(
+            tables("SELECT u.id FROM users u JOIN users v ON u.id = v.manager_id"),
+            vec![alias("users", Some("u")), alias("users", Some("v"))]
+        );
+        assert_eq!(
+            tables("SELECT * FROM t JOIN s ON t.a = s.a"),
+            vec![alias("t", None), alias("s", None)]
+        );
+    }
+
+    /// Tests UPDATE with one or several assignments, with and without WHERE.
+    #[test]
+    fn test_update() {
+        let update = |sql: &str| match parse(sql).unwrap() {
+            Query::Update(update) => update,
+            other => panic!("Expected an UPDATE, got {:?}", other),
+        };
+
+        let single = update("UPDATE users SET name = 'x'");
+        assert_eq!(single.table.name, "users");
+        assert_eq!(
+            single.assignments,
+            vec![("name".to_string(), Expression::Text("x".to_string()))]
+        );
+        assert_eq!(single.where_clause, None);
+
+        let multiple = update("UPDATE t SET a = a + 1, b = DEFAULT, c = NOT d WHERE id = 3");
+        assert_eq!(
+            multiple.assignments,
+            vec![
+                (
+                    "a".to_string(),
+                    Expression::Binary {
+                        left: identifier("a"),
+                        operator: BinaryOperator::Plus,
+                        right: Box::new(Expression::Integer(1)),
+                    }
+                ),
+                ("b".to_string(), Expression::Default),
+                ("c".to_string(), Expression::Not(identifier("d"))),
+            ]
+        );
+        assert_eq!(
+            multiple.where_clause,
+            Some(Expression::Binary {
+                left: identifier("id"),
+                operator: BinaryOperator::Equal,
+                right: Box::new(Expression::Integer(3)),
+            })
+        );
+
+        assert_eq!(
+            parse("UPDATE t WHERE a = 1").unwrap_err(),
+            "Expected keyword 'SET', but found 'WHERE' at line 1, column 10"
+        );
+        assert_eq!(
+            parse("UPDATE t SET a 1").unwrap_err(),
+            "Expected token 'Equal', but found 'Integer(1)' at line 1, column 16"
+        );
+        assert_eq!(
+            parse("UPDATE t SET a = 1, A = 2").unwrap_err(),
+            "Column 'A' is assigned more than once at line 1, column 23"
+        );
+    }
+
+    /// Tests CREATE TABLE column definitions, their constraints and malformed column lists.
+    #[test]
+    fn test_create_table() {
+        let query = parse(
+            "CREATE TABLE users (id INT NOT NULL, name TEXT DEFAULT 'x', score FLOAT, ok BOOLEAN)",
+        )
+        .unwrap();
+        let column = |name: &str, data_type, not_null, default| ColumnDef {
+            name: name.to_string(),
+            data_type,
+            not_null,
+            default,
+        };
+        assert_eq!(
+            query,
+            Query::CreateTable {
+                name: "users".to_string(),
+                columns: vec![
+                    column("id", DataType::Integer, true, None),
+                    column(
+                        "name",
+                        DataType::Text,
+                        false,
+                        Some(Value::Text("x".to_string()))
+                    ),
+                    column("score", DataType::Float, false, None),
+                    column("ok", DataType::Boolean, false, None),
+                ],
+            }
+        );
+
+        assert_eq!(
+            parse("CREATE TABLE t (a INTEGER, b TEXT,)").unwrap_err(),
+            "Expected a column definition after the last ',' at line 1, column 35"
+        );
+        assert_eq!(
+            parse("CREATE TABLE t (a INTEGER NOT 1)").unwrap_err(),
+            "Expected token 'Null', but found 'Integer(1)' at line 1, column 31"
+        );
+        assert_eq!(
+            parse("CREATE TABLE t (a INTEGER, A TEXT)").unwrap_err(),
+            "Column 'A' is defined more than once at line 1, column 34"
+        );
+        assert!(parse("CREATE TABLE t ()").is_err());
+    }
+
+    /// Tests DROP TABLE with and without IF EXISTS, and without a table name.
+    #[test]
+    fn test_drop_table() {
+        assert_eq!(
+            parse("DROP TABLE users").unwrap(),
+            Query::DropTable {
+                name: "users".to_string(),
+                if_exists: false,
+            }
+        );
+        assert_eq!(
+            parse("drop table if exists users").unwrap(),
+            Query::DropTable {
+                name: "users".to_string(),
+                if_exists: true,
+            }
+        );
+        assert_eq!(
+            parse("DROP TABLE").unwrap_err(),
+            "I was expecting a table name at line 1, column 11"
+        );
+        assert_eq!(
+            parse("DROP TABLE IF EXISTS").unwrap_err(),
+            "I was expecting a table name at line 1, column 21"
+        );
+        assert!(parse("DROP users").is_err());
+    }
+
+    /// Tests that INSERT ... SELECT keeps its target columns and select list.
+    #[test]
+    fn test_insert_select() {
+        let query = parse("INSERT INTO t (a, b) SELECT x, y FROM s").unwrap();
+        match query {
+            Query::Insert(insert) => {
+                assert_eq!(insert.columns, vec!["a", "b"]);
+                assert_eq!(insert.select.unwrap().columns.len(), 2);
+            }
+            other => panic!("Expected an INSERT, got {:?}", other),
+        }
+    }
+
+    /// Tests that ARRAY literals parse into their elements.
+    #[test]
+    fn test_array_literal() {
+        let query = parse("SELECT ARRAY[1, 2, 3] FROM t").unwrap();
+        match query {
+            Query::Select(select) => match &select.columns[0].expression {
+                Expression::Array(elements) => {
+                    assert_eq!(elements.len(), 3);
+                    assert!(matches!(elements[2], Expression::Integer(3)));
+                }
+                other => panic!("Expected an array, got {:?}", other),
+            },
+            other => panic!("Expected a SELECT, got {:?}", other),
+        }
+
+        let query = parse("SELECT ARRAY[] FROM t").unwrap();
+        match query {
+            Query::Select(select) => {
+                assert!(
+                    matches!(&select.columns[0].expression, Expression::Array(e) if e.is_empty())
+                )
+            }
+            other => panic!("Expected a SELECT, got {:?}", other),
+        }
+    }
+
+    /// Tests that an array can be the operand of ANY in a WHERE clause.
+    #[test]
+    fn test_array_in_any() {
+        assert_eq!(
+            parse_where("x = ANY(ARRAY[1, 2])"),
+            Expression::Quantified {
+                left: identifier("x"),
+                operator: BinaryOperator::Equal,
+                quantifier: Quantifier::Any,
+                right: QuantifiedOperand::Array(Box::new(Expression::Array(vec![
+                    Expression::Integer(1),
+                    Expression::Integer(2),
+                ]))),
+            }
+        );
+        assert_eq!(
+            parse_where("x <> SOME (tags)"),
+            Expression::Quantified {
+                left: identifier("x"),
+                operator: BinaryOperator::NotEqual,
+                quantifier: Quantifier::Any,
+                right: QuantifiedOperand::Array(identifier("tags")),
+            }
+        );
+    }
+
+    /// Tests that a subquery can be the operand of a quantified comparison.
+    #[test]
+    fn test_quantified_subquery() {
+        match parse_where("x + 1 > ALL (SELECT y FROM s WHERE z = 1) AND b") {
+            Expression::And(left, _) => match *left {
+                Expression::Quantified {
+                    left,
+                    operator: BinaryOperator::GreaterThan,
+                    quantifier: Quantifier::All,
+                    right: QuantifiedOperand::Subquery(subquery),
+                } => {
+                    assert!(matches!(*left, Expression::Binary { .. }));
+                    assert_eq!(subquery.table.name, "s");
+                    assert!(subquery.where_clause.is_some());
+                }
+                other => panic!("Expected > ALL (SELECT ...), got {:?}", other),
+            },
+            other => panic!("Expected AND, got {:?}", other),
+        }
+
+        assert!(parse("SELECT a FROM t WHERE x = ANY (SELECT y FROM s").is_err());
+        assert!(parse("SELECT a FROM t WHERE x = ALL").is_err());
+    }
+
+    /// Tests that an unterminated array literal is rejected.
+    #[test]
+    fn test_array_literal_unterminated() {
+        assert!(parse("SELECT ARRAY[1, 2 FROM t").is_err());
+    }
+
+    /// Tests that subscripts parse on identifiers and with string keys.
+    #[test]
+    fn test_subscript() {
+        let query = parse("SELECT col[1], data['key'] FROM t").unwrap();
+        match query {
+            Query::Select(select) => {
+                match &select.columns[0].expression {
+                    Expression::Subscript { base, index } => {
+                        assert!(matches!(base.as_ref(), Expression::Identifier(n) if n == "col"));
+                        assert!(matches!(index.as_ref(), Expression::Integer(1)));
+                    }
+                    other => panic!("Expected a subscript, got {:?}", other),
+                }
+                match &select.columns[1].expression {
+                    Expression::Subscript { index, .. } => {
+                        assert!(matches!(index.as_ref(), Expression::Text(k) if k == "key"));
+                    }
+                    other => panic!("Expected a subscript, got {:?}", other),
+                }
+            }
+            other => panic!("Expected a SELECT, got {:?}", other),
+        }
+    }
+
+    /// Tests that nested subscripts chain left-associatively.
+    #[test]
+    fn test_nested_subscript() {
+        let query = parse("SELECT a[1][2] FROM t").unwrap();
+        match query {
+            Query::Select(select) => match &select.columns[0].expression {
+                Expression::Subscript { base, index } => {
+                    assert!(matches!(index.as_ref(), Expression::Integer(2)));
+                    match base.as_ref() {
+                        Expression::Subscript { base, index } => {
+                            assert!(matches!(base.as_ref(), Expression::Identifier(n) if n == "a"));
+                            assert!(matches!(index.as_ref(), Expression::Integer(1)));
+                        }
+                        other => panic!("Expected a subscript, got {:?}", other),
+                    }
+                }
+                other => panic!("Expected a subscript, got {:?}", other),
+            },
+            other => panic!("Expected a SELECT, got {:?}", other),
+        }
+    }
+
+    /// Tests that `->` and `->>` parse as distinct JSON operators.
+    #[test]
+    fn test_json_access_operators() {
+        let query = parse("SELECT a FROM t WHERE data -> 'k' ->> 'j' = 'v'").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected a SELECT");
+        };
+        let Some(Expression::Binary {
+            left,
+            operator: BinaryOperator::Equal,
+            ..
+        }) = select.where_clause
+        else {
+            panic!("Expected an equality");
+        };
+        match *left {
+            Expression::Binary {
+                left,
+                operator: BinaryOperator::JsonGetText,
+                right,
+            } => {
+                assert!(matches!(*right, Expression::Text(ref k) if k == "j"));
+                assert!(matches!(
+                    *left,
+                    Expression::Binary {
+                        operator: BinaryOperator::JsonGet,
+                        ..
+                    }
+                ));
+            }
+            other => panic!("Expected ->>, got {:?}", other),
+        }
+    }
+
+    /// Tests that JSON literals are validated and parsed into JSON values.
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_literal() {
+        let query = parse(r#"INSERT INTO t (doc) VALUES (JSON '{"a": [1, 2]}')"#).unwrap();
+        let Query::Insert(insert) = query else {
+            panic!("Expected an INSERT");
+        };
+        assert_eq!(
+            insert.values,
+            Some(vec![Value::Json(serde_json::json!({"a": [1, 2]}))])
+        );
+    }
+
+    /// Tests that malformed JSON literals are a parse error.
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_malformed_json_literal() {
+        let err = parse(r#"INSERT INTO t (doc) VALUES (JSON '{"a": ')"#).unwrap_err();
+        assert!(err.starts_with("Invalid JSON literal"), "{}", err);
+    }
+
+    /// Tests that JSON literals are rejected when the feature is disabled.
+    #[cfg(not(feature = "json"))]
+    #[test]
+    fn test_json_literal_requires_feature() {
+        let err = parse(r#"INSERT INTO t (doc) VALUES (JSON '{}')"#).unwrap_err();
+        assert_eq!(
+            err,
+            "JSON literal '{}' requires the `json` feature at line 1, column 38"
+        );
+    }
+
+    /// Tests that UUID literals are parsed in their canonical form, in either case, and that
+    /// anything else is rejected.
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_literal() {
+        let Query::Insert(insert) =
+            parse("INSERT INTO t (id) VALUES (uuid '550E8400-e29b-41d4-a716-446655440000')")
+                .unwrap()
+        else {
+            panic!("Expected an INSERT");
+        };
+        assert_eq!(
+            insert.values,
+            Some(vec![Value::Uuid([
+                0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44,
+                0x00, 0x00,
+            ])])
+        );
+
+        for malformed in [
+            "550e8400e29b41d4a716446655440000",
+            "550e8400-e29b-41d4-a716-44665544000",
+            "550e8400-e29b-41d4-a716-4466554400000",
+            "550e8400-e29b-41d4a-716-446655440000",
+            "550e8400-e29b-41d4-a716-44665544000g",
+            "{550e8400-e29b-41d4-a716-446655440000}",
+            "+50e8400-e29b-41d4-a716-446655440000",
+        ] {
+            assert_eq!(
+                parse(&format!("INSERT INTO t (id) VALUES (UUID '{}')", malformed)).unwrap_err(),
+                format!(
+                    "Invalid UUID literal '{}': expected 32 hex digits grouped 8-4-4-4-12 \
+                     at line 1, column {}",
+                    malformed,
+                    35 + malformed.len()
+                )
+            );
+        }
+    }
+
+    /// Tests that UUID literals are rejected when the feature is disabled.
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_uuid_literal_requires_feature() {
+        let err = parse("INSERT INTO t (id) VALUES (UUID '550e8400-e29b-41d4-a716-446655440000')")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "UUID literal '550e8400-e29b-41d4-a716-446655440000' requires the `uuid` feature \
+             at line 1, column 71"
+        );
+    }
+
+    /// Tests that an error on a later line of a multi-line query reports that line and the
+    /// column of the offending token.
+    #[test]
+    fn test_error_line_and_column() {
+        let sql = "SELECT a,\n       b\n  FROM t WHERE a BETWEEN 1 OR 2";
+        let mut parser = Parser::new(sql).unwrap();
+        assert_eq!(
+            parser.parse(),
+            Err("Expected keyword 'AND', but found 'OR' at line 3, column 28".to_string())
+        );
+        assert_eq!(
+            parser.error_position(),
+            Position {
+                line: 3,
+                column: 28
+            }
+        );
+        assert_eq!(&sql[parser.error_offset()..], "OR 2");
+
+        assert_eq!(
+            parse("SELECT a\nFROM t\nWHERE a =\n"),
+            Err("This is an unexpected token. at line 4, column 1".to_string())
+        );
+        assert_eq!(
+            parse("SELECT a\nFROM t\nWHERE a = 'x"),
+            Err("Unterminated string literal at line 3, column 11".to_string())
+        );
+    }
+
+    /// Tests that a failed parse reports the unparsed rest of the input.
+    #[test]
+    fn test_remaining_input_after_error() {
+        let sql = "SELECT a, FROM t WHERE b = 1";
+        let mut parser = Parser::new(sql).unwrap();
+        assert!(parser.parse().is_err());
+        assert_eq!(parser.remaining_input(), "FROM t WHERE b = 1");
+        assert_eq!(parser.error_offset(), 10);
+
+        let mut parser = Parser::new("SELECT naïve, FROM t").unwrap();
+        assert!(parser.parse().is_err());
+        assert_eq!(parser.remaining_input(), "FROM t");
+
+        let mut parser = Parser::new("SELECT a FROM t JOIN   ").unwrap();
+        assert!(parser.parse().is_err());
+        assert_eq!(parser.remaining_input(), "");
+    }
+
+    /// Tests that interval arithmetic parses into a left-associative binary expression
+    /// that binds tighter than comparisons.
+    #[test]
+    fn test_interval_arithmetic() {
+        let query = parse("SELECT ts - INTERVAL '1 hour' FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![Expression::Binary {
+                left: Box::new(Expression::Identifier("ts".to_string())),
+                operator: BinaryOperator::Minus,
+                right: Box::new(Expression::Interval("1 hour".to_string())),
+            }])
+        );
+
+        let query = parse("SELECT a FROM t WHERE d + INTERVAL '7 days' - x < now").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        let Some(Expression::Binary { left, operator, .. }) = select.where_clause else {
+            panic!("Expected a comparison");
+        };
+        assert_eq!(operator, BinaryOperator::LessThan);
+        assert!(matches!(
+            *left,
+            Expression::Binary {
+                operator: BinaryOperator::Minus,
+                ref left,
+                ..
+            } if matches!(**left, Expression::Binary { operator: BinaryOperator::Plus, .. })
+        ));
+
+        assert_eq!(
+            parse("SELECT INTERVAL 1 FROM t").unwrap_err(),
+            "Expected a string literal after 'INTERVAL' at line 1, column 17"
+        );
+    }
+
+    /// Tests that AT TIME ZONE applies to the preceding term and accepts an expression zone.
+    #[test]
+    fn test_at_time_zone() {
+        let query =
+            parse("SELECT ts AT TIME ZONE 'UTC' FROM t WHERE a + b AT TIME ZONE tz > c").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![Expression::AtTimeZone {
+                expr: Box::new(Expression::Identifier("ts".to_string())),
+                zone: Box::new(Expression::Text("UTC".to_string())),
+            }])
+        );
+
+        let Some(Expression::Binary { left, .. }) = select.where_clause else {
+            panic!("Expected a comparison");
+        };
+        assert_eq!(
+            *left,
+            Expression::Binary {
+                left: Box::new(Expression::Identifier("a".to_string())),
+                operator: BinaryOperator::Plus,
+                right: Box::new(Expression::AtTimeZone {
+                    expr: Box::new(Expression::Identifier("b".to_string())),
+                    zone: Box::new(Expression::Identifier("tz".to_string())),
+                }),
+            }
+        );
+
+        // TIME and ZONE are only keywords after AT
+        let Query::Select(select) =
+            parse("SELECT time AT TIME ZONE zone FROM events WHERE time > 1").unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![Expression::AtTimeZone {
+                expr: identifier("time"),
+                zone: identifier("zone"),
+            }])
+        );
+        assert_eq!(
+            parse("SELECT ts AT ZONE 'UTC' FROM t").unwrap_err(),
+            "Expected keyword 'TIME' at line 1, column 14"
+        );
+    }
+
+    /// Tests that `::` casts apply left to right to the preceding term, including qualified calls.
+    #[test]
+    fn test_postfix_cast() {
+        let query =
+            parse("SELECT x::integer, pg_catalog.now()::TEXT, 1.5::int::text FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        let cast = |expr, data_type| Expression::Cast {
+            expr: Box::new(expr),
+            data_type,
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![
+                cast(Expression::Identifier("x".to_string()), DataType::Integer),
+                cast(
+                    Expression::Function {
+                        name: "pg_catalog.now".to_string(),
+                        args: vec![],
+                        named_args: Vec::new(),
+                        filter: None,
+                        order_by: Vec::new(),
+                    },
+                    DataType::Text
+                ),
+                cast(
+                    cast(Expression::Float(1.5), DataType::Integer),
+                    DataType::Text
+                ),
+            ])
+        );
+
+        #[cfg(feature = "json")]
+        {
+            let query = parse("SELECT x::text::json FROM t").unwrap();
+            let Query::Select(select) = query else {
+                panic!("Expected SELECT query");
+            };
+            assert_eq!(
+                select.columns,
+                select_items(vec![cast(
+                    cast(Expression::Identifier("x".to_string()), DataType::Text),
+                    DataType::Json
+                )])
+            );
+        }
+        #[cfg(not(feature = "json"))]
+        assert_eq!(
+            parse("SELECT x::text::json FROM t").unwrap_err(),
+            "Unknown data type 'json' at line 1, column 17"
+        );
+        assert!(parse("SELECT x:: FROM t").is_err());
+    }
+
+    /// Tests that `CAST(expr AS type)` parses to the same cast as `::`, and that a quoted
+    /// `"cast"` is still an ordinary function call.
+    #[test]
+    fn test_cast_function() {
+        let query = parse("SELECT CAST(score AS INTEGER), cast(a + 1 AS text) FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![
+                Expression::Cast {
+                    expr: Box::new(Expression::Identifier("score".to_string())),
+                    data_type: DataType::Integer,
+                },
+                Expression::Cast {
+                    expr: Box::new(Expression::Binary {
+                        left: Box::new(Expression::Identifier("a".to_string())),
+                        operator: BinaryOperator::Plus,
+                        right: Box::new(Expression::Integer(1)),
+                    }),
+                    data_type: DataType::Text,
+                },
+            ])
+        );
+
+        let query = parse(r#"SELECT "cast"(score) FROM t"#).unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert!(matches!(
+            &select.columns[0].expression,
+            Expression::Function { name, .. } if name == "cast"
+        ));
+
+        assert_eq!(
+            parse("SELECT CAST(score) FROM t").unwrap_err(),
+            "Expected keyword 'AS' at line 1, column 18"
+        );
+        assert_eq!(
+            parse("SELECT CAST(score AS blob) FROM t").unwrap_err(),
+            "Unknown data type 'blob' at line 1, column 22"
+        );
+    }
+
+    /// Tests that a parenthesized group with a top-level comma is a row constructor.
+    #[test]
+    fn test_row_constructors() {
+        let ident = |name: &str| Expression::Identifier(name.to_string());
+        let row = |elements| Expression::Row(elements);
+
+        let query = parse("SELECT a FROM t WHERE (a, b) = (1, 2)").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.where_clause,
+            Some(Expression::Binary {
+                left: Box::new(row(vec![ident("a"), ident("b")])),
+                operator: BinaryOperator::Equal,
+                right: Box::new(row(vec![Expression::Integer(1), Expression::Integer(2)])),
+            })
+        );
+
+        let query = parse("SELECT a FROM t WHERE (a, b) IN ((1, 2), (3, 4)) AND (c = 5)").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        let Some(Expression::And(left, right)) = select.where_clause else {
+            panic!("Expected AND");
+        };
+        assert_eq!(
+            *left,
+            Expression::InList {
+                expr: Box::new(row(vec![ident("a"), ident("b")])),
+                list: vec![
+                    row(vec![Expression::Integer(1), Expression::Integer(2)]),
+                    row(vec![Expression::Integer(3), Expression::Integer(4)]),
+                ],
+            }
+        );
+        // A single parenthesized expression is still just a group
+        assert_eq!(
+            *right,
+            Expression::Binary {
+                left: Box::new(ident("c")),
+                operator: BinaryOperator::Equal,
+                right: Box::new(Expression::Integer(5)),
+            }
+        );
+    }
+
+    /// Tests that blob literals parse and that a malformed one reports the lex error.
+    #[test]
+    fn test_blob_literal() {
+        let query = parse("SELECT a FROM t WHERE data = X'CAFE'").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        let Some(Expression::Binary { right, .. }) = select.where_clause else {
+            panic!("Expected a comparison");
+        };
+        assert_eq!(*right, Expression::Blob(vec![0xCA, 0xFE]));
+
+        assert_eq!(
+            parse("SELECT a FROM t WHERE data = X'CAF'").unwrap_err(),
+            "Blob literal must have an even number of hex digits at line 1, column 30"
+        );
+    }
+
+    /// Tests each spelling of the transaction control statements.
+    #[test]
+    fn test_transaction_statements() {
+        for sql in ["BEGIN", "begin transaction", "START TRANSACTION"] {
+            assert_eq!(parse(sql), Ok(Query::Begin), "{}", sql);
+        }
+        for sql in ["COMMIT", "COMMIT TRANSACTION"] {
+            assert_eq!(parse(sql), Ok(Query::Commit), "{}", sql);
+        }
+        for sql in ["ROLLBACK", "Rollback Transaction"] {
+            assert_eq!(parse(sql), Ok(Query::Rollback), "{}", sql);
+        }
+        assert!(parse("START").is_err());
+
+        // The words are only keywords as the first word of a statement
+        let Query::Select(select) =
+            parse("SELECT start, begin, commit FROM transaction WHERE rollback = 1").unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![
+                *identifier("start"),
+                *identifier("begin"),
+                *identifier("commit")
+            ])
+        );
+        assert_eq!(select.table.name, "transaction");
+    }
+
+    /// Tests that an ORDER BY item takes an optional quoted or bare COLLATE name before
+    /// its direction.
+    #[test]
+    fn test_order_by_collate() {
+        let query = parse(r#"SELECT name FROM t ORDER BY name COLLATE "en_US" DESC, id COLLATE c"#)
+            .unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.order_by,
+            Some(vec![
+                Ordering {
+                    expression: Expression::Identifier("name".to_string()),
+                    direction: SortOrder::Descending,
+                    collation: Some("en_US".to_string()),
+                },
+                Ordering {
+                    expression: Expression::Identifier("id".to_string()),
+                    direction: SortOrder::Ascending,
+                    collation: Some("c".to_string()),
+                },
+            ])
+        );
+        assert_eq!(
+            parse("SELECT name FROM t ORDER BY name COLLATE 'x'").unwrap_err(),
+            "Expected a collation name after 'COLLATE' at line 1, column 42"
+        );
+        assert_eq!(
+            parse(r#"SELECT name FROM t ORDER BY name COLLATE "en"#).unwrap_err(),
+            "Unterminated quoted identifier at line 1, column 42"
+        );
+    }
+
+    /// Tests that aggregate calls take an inner ORDER BY after their arguments, and that
+    /// other functions reject one.
+    #[test]
+    fn test_aggregate_order_by() {
+        let query = parse("SELECT STRING_AGG(name, ', ' ORDER BY name DESC) FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![Expression::Function {
+                name: "STRING_AGG".to_string(),
+                args: vec![
+                    Expression::Identifier("name".to_string()),
+                    Expression::Text(", ".to_string()),
+                ],
+                named_args: Vec::new(),
+                filter: None,
+                order_by: vec![Ordering {
+                    expression: Expression::Identifier("name".to_string()),
+                    direction: SortOrder::Descending,
+                    collation: None,
+                }],
+            }])
+        );
+        assert_eq!(
+            parse("SELECT LOWER(name ORDER BY name) FROM t").unwrap_err(),
+            "ORDER BY is only allowed in aggregate function calls, not 'LOWER' at line 1, column 25"
+        );
+    }
+
+    /// Tests that NOT binds looser than comparisons but tighter than AND.
+    #[test]
+    fn test_not_precedence() {
+        let a_equals_b = Expression::Binary {
+            left: identifier("a"),
+            operator: BinaryOperator::Equal,
+            right: identifier("b"),
+        };
+        assert_eq!(
+            parse_where("NOT a = b"),
+            Expression::Not(Box::new(a_equals_b))
+        );
+        assert_eq!(
+            parse_where("NOT a AND b"),
+            Expression::And(Box::new(Expression::Not(identifier("a"))), identifier("b"))
+        );
+
+        // A parenthesized operand is compared like any other
+        let Expression::Not(inner) = parse_where("NOT (a) = (b)") else {
+            panic!("Expected NOT");
+        };
+        assert!(matches!(
+            *inner,
+            Expression::Binary {
+                operator: BinaryOperator::Equal,
+                ..
+            }
+        ));
+    }
+
+    /// Tests how NOT, AND and OR group when combined, including repeated NOTs.
+    #[test]
+    fn test_logical_precedence_matrix() {
+        let not = |expr: Box<Expression>| Box::new(Expression::Not(expr));
+        let and = |l: Box<Expression>, r: Box<Expression>| Box::new(Expression::And(l, r));
+        let or = |l: Box<Expression>, r: Box<Expression>| Box::new(Expression::Or(l, r));
+        let (a, b, c) = (|| identifier("a"), || identifier("b"), || identifier("c"));
+
+        let cases = [
+            ("NOT NOT a", not(not(a()))),
+            ("NOT NOT NOT a", not(not(not(a())))),
+            ("NOT a OR b", or(not(a()), b())),
+            ("a OR NOT b", or(a(), not(b()))),
+            ("NOT a AND NOT b", and(not(a()), not(b()))),
+            ("NOT (a AND b)", not(and(a(), b()))),
+            ("NOT (a OR b) AND c", and(not(or(a(), b())), c())),
+            ("a AND b OR c", or(and(a(), b()), c())),
+            ("a OR b AND c", or(a(), and(b(), c()))),
+            ("NOT a OR b AND NOT c", or(not(a()), and(b(), not(c())))),
+            ("NOT NOT a AND b", and(not(not(a())), b())),
+            ("a AND NOT NOT b OR c", or(and(a(), not(not(b()))), c())),
+        ];
+        for (condition, expected) in cases {
+            assert_eq!(parse_where(condition), *expected, "{}", condition);
+        }
+    }
+
+    /// Tests that `<>` parses to the same not-equal comparison as `!=`.
+    #[test]
+    fn test_angle_bracket_not_equal() {
+        let not_equal = Expression::Binary {
+            left: identifier("a"),
+            operator: BinaryOperator::NotEqual,
+            right: identifier("b"),
+        };
+        assert_eq!(parse_where("a <> b"), not_equal);
+        assert_eq!(parse_where("a <> b"), parse_where("a != b"));
+        assert!(parse("SELECT a FROM t WHERE a < > b").is_err());
+    }
+
+    /// Tests that mixed arithmetic, comparison and logical operators group as in standard SQL.
+    #[test]
+    fn test_operator_precedence() {
+        let binary = |l: Box<Expression>, operator, r: Box<Expression>| {
+            Box::new(Expression::Binary {
+                left: l,
+                operator,
+                right: r,
+            })
+        };
+        let not = |expr: Box<Expression>| Box::new(Expression::Not(expr));
+        let neg = |expr: Box<Expression>| Box::new(Expression::Negate(expr));
+        let and = |l: Box<Expression>, r: Box<Expression>| Box::new(Expression::And(l, r));
+        let or = |l: Box<Expression>, r: Box<Expression>| Box::new(Expression::Or(l, r));
+        let (a, b, c, d) = (
+            || identifier("a"),
+            || identifier("b"),
+            || identifier("c"),
+            || identifier("d"),
+        );
+        let int = |i| Box::new(Expression::Integer(i));
+        use BinaryOperator::*;
+
+        let cases = [
+            ("a OR b AND c", or(a(), and(b(), c()))),
+            ("NOT a = b", not(binary(a(), Equal, b()))),
+            (
+                "a + b * c > d",
+                binary(
+                    binary(a(), Plus, binary(b(), Multiply, c())),
+                    GreaterThan,
+                    d(),
+                ),
+            ),
+            (
+                "a * b + c * d = 1",
+                binary(
+                    binary(binary(a(), Multiply, b()), Plus, binary(c(), Multiply, d())),
+                    Equal,
+                    int(1),
+                ),
+            ),
+            ("a - b - c", binary(binary(a(), Minus, b()), Minus, c())),
+            ("a / b * c % d", {
+                binary(binary(binary(a(), Divide, b()), Multiply, c()), Modulo, d())
+            }),
+            ("a * (b + c)", binary(a(), Multiply, binary(b(), Plus, c()))),
+            (
+                "a = 1 OR NOT b = 2 AND c",
+                or(
+                    binary(a(), Equal, int(1)),
+                    and(not(binary(b(), Equal, int(2))), c()),
+                ),
+            ),
+            (
+                "a -> 'k' * 2",
+                binary(
+                    binary(a(), JsonGet, Box::new(Expression::Text("k".to_string()))),
+                    Multiply,
+                    int(2),
+                ),
+            ),
+            ("-a * b", binary(neg(a()), Multiply, b())),
+            ("a - -b", binary(a(), Minus, neg(b()))),
+            ("a < -5", binary(a(), LessThan, int(-5))),
+            (
+                "-2.5 * +a + -(b - c)",
+                binary(
+                    binary(Box::new(Expression::Float(-2.5)), Multiply, a()),
+                    Plus,
+                    neg(binary(b(), Minus, c())),
+                ),
+            ),
+            (
+                "-a -> 'k'",
+                neg(binary(
+                    a(),
+                    JsonGet,
+                    Box::new(Expression::Text("k".to_string())),
+                )),
+            ),
+            ("- -1", neg(int(-1))),
+            (
+                "a * 2 BETWEEN b AND c + 1",
+                Box::new(Expression::Between {
+                    expr: binary(a(), Multiply, int(2)),
+                    low: b(),
+                    high: binary(c(), Plus, int(1)),
+                    negated: false,
+                }),
+            ),
+        ];
+        for (condition, expected) in cases {
+            assert_eq!(parse_where(condition), *expected, "{}", condition);
+        }
+
+        // `*` is still a wildcard where an expression starts
+        let Query::Select(select) = parse("SELECT *, a * b FROM t").unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.columns[0].expression, Expression::Asterisk);
+
+        // A sign starts an expression or a value anywhere one is expected
+        let Query::Select(select) = parse("SELECT -1 FROM t").unwrap() else {
+            panic!("Expected SELECT");
+        };
+        assert_eq!(select.columns[0].expression, Expression::Integer(-1));
+        let Query::Insert(insert) = parse("INSERT INTO t (a, b) VALUES (-1, +2.5)").unwrap() else {
+            panic!("Expected INSERT");
+        };
+        assert_eq!(
+            insert.values,
+            Some(vec![Value::Integer(-1), Value::Float(2.5)])
+        );
+
+        // Comparisons do not associate, so a second one is an error rather than ignored
+        for (condition, column) in [
+            ("a = b = c", 29),
+            ("a < b > c", 29),
+            ("a LIKE 'x' = b", 34),
+            ("a IN (1) NOT BETWEEN 1 AND 2", 32),
+        ] {
+            assert_eq!(
+                parse(&format!("SELECT a FROM t WHERE {}", condition)).unwrap_err(),
+                format!(
+                    "Comparisons cannot be chained; combine them with AND or use parentheses \
+                     at line 1, column {}",
+                    column
+                ),
+                "{}",
+                condition
+            );
+        }
+    }
+
+    /// Tests that list lengths past the configured limits are rejected with a clear error.
+    #[test]
+    fn test_list_limits() {
+        let values: Vec<String> = (0..100_000).map(|i| i.to_string()).collect();
+        let sql = format!("SELECT a FROM t WHERE a IN ({})", values.join(", "));
+        assert_eq!(
+            parse(&sql).unwrap_err(),
+            "Too many IN list values (the limit is 10000) at line 1, column 58924"
+        );
+
+        let limits = ParserLimits {
+            max_select_items: 2,
+            max_order_by: 1,
+            ..ParserLimits::default()
+        };
+        let parse_limited = |sql: &str| Parser::new(sql)?.with_limits(limits).parse();
+        assert!(parse_limited("SELECT a, b FROM t ORDER BY a").is_ok());
+        assert_eq!(
+            parse_limited("SELECT a, b, c FROM t").unwrap_err(),
+            "Too many select list items (the limit is 2) at line 1, column 16"
+        );
+        assert_eq!(
+            parse_limited("SELECT a FROM t ORDER BY a, b").unwrap_err(),
+            "Too many ORDER BY items (the limit is 1) at line 1, column 30"
+        );
+    }
+
+    /// Tests that BETWEEN takes additive bounds joined by AND, optionally negated.
+    #[test]
+    fn test_between() {
+        assert_eq!(
+            parse_where("a NOT BETWEEN 1 AND b AND c"),
+            Expression::And(
+                Box::new(Expression::Between {
+                    expr: identifier("a"),
+                    low: Box::new(Expression::Integer(1)),
+                    high: identifier("b"),
+                    negated: true,
+                }),
+                identifier("c")
+            )
+        );
+        assert!(matches!(
+            parse_where("NOT a BETWEEN 1 AND 2"),
+            Expression::Not(between) if matches!(*between, Expression::Between { negated: false, .. })
+        ));
+        assert_eq!(
+            parse("SELECT a FROM t WHERE a BETWEEN 1 OR 2").unwrap_err(),
+            "Expected keyword 'AND', but found 'OR' at line 1, column 35"
+        );
+    }
+
+    /// Tests that function calls take positional arguments followed by `name => expr` ones.
+    #[test]
+    fn test_named_arguments() {
+        let call = |sql: &str| {
+            let Query::Select(mut select) = parse(sql).unwrap() else {
+                panic!("Expected a SELECT");
+            };
+            match select.columns.remove(0).expression {
+                Expression::Function {
+                    args, named_args, ..
+                } => (args, named_args),
+                other => panic!("Expected a function call, got {:?}", other),
+            }
+        };
+
+        let (args, named_args) = call("SELECT date_trunc('month', ts) FROM t");
+        assert_eq!(
+            args,
+            vec![Expression::Text("month".to_string()), *identifier("ts")]
+        );
+        assert!(named_args.is_empty());
+
+        let (args, named_args) = call("SELECT make_date(year => 2024, month=>1 + 1) FROM t");
+        assert!(args.is_empty());
+        assert_eq!(
+            named_args,
+            vec![
+                ("year".to_string(), Expression::Integer(2024)),
+                (
+                    "month".to_string(),
+                    Expression::Binary {
+                        left: Box::new(Expression::Integer(1)),
+                        operator: BinaryOperator::Plus,
+                        right: Box::new(Expression::Integer(1)),
+                    }
+                ),
+            ]
+        );
+
+        let (args, named_args) = call("SELECT f(a = b, c => d) FROM t");
+        assert_eq!(args.len(), 1);
+        assert_eq!(named_args, vec![("c".to_string(), *identifier("d"))]);
+
+        assert_eq!(
+            parse("SELECT f(a => 1, 2) FROM t").unwrap_err(),
+            "Positional arguments cannot follow named arguments in a call to 'f' \
+             at line 1, column 18"
+        );
+    }
+
+    /// Tests LIMIT n, LIMIT n OFFSET m and the MySQL LIMIT m, n form.
+    #[test]
+    fn test_limit_forms() {
+        let bounds = |sql: &str| match parse(sql).unwrap() {
+            Query::Select(select) => (select.limit, select.offset),
+            other => panic!("Expected a SELECT, got {:?}", other),
+        };
+
+        assert_eq!(
+            bounds("SELECT a FROM t LIMIT 10"),
+            (Some(Expression::Integer(10)), None)
+        );
+        assert_eq!(
+            bounds("SELECT a FROM t ORDER BY a LIMIT 10 OFFSET 5"),
+            (Some(Expression::Integer(10)), Some(Expression::Integer(5)))
+        );
+        assert_eq!(
+            bounds("SELECT a FROM t ORDER BY a LIMIT 5, 10"),
+            (Some(Expression::Integer(10)), Some(Expression::Integer(5)))
+        );
+        // The count is any expression; the planner checks it is a non-negative integer
+        assert_eq!(
+            bounds("SELECT a FROM t LIMIT a + 1"),
+            (
+                Some(Expression::Binary {
+                    left: identifier("a"),
+                    operator: BinaryOperator::Plus,
+                    right: Box::new(Expression::Integer(1)),
+                }),
+                None
+            )
+        );
+
+        assert_eq!(
+            parse("SELECT a FROM t LIMIT 5, 10 OFFSET 2").unwrap_err(),
+            "OFFSET cannot be combined with LIMIT offset, count at line 1, column 36"
+        );
+        assert!(parse("SELECT a FROM t LIMIT 5,").is_err());
+    }
+
+    /// Tests WITH RECURSIVE and the checks on the shape of a self-referencing CTE.
+    #[test]
+    fn test_with_recursive() {
+        let sql = "WITH RECURSIVE tree (id) AS (SELECT id FROM nodes WHERE parent_id = 0 \
+                   UNION ALL SELECT n.id FROM nodes n JOIN tree t ON n.parent_id = t.id) \
+                   SELECT id FROM tree";
+        let query = parse(sql).unwrap();
+        let Query::Select(select) = &query else {
+            panic!("Expected a SELECT, got {:?}", query);
+        };
+        let with = select.with.as_ref().unwrap();
+        assert!(with.recursive);
+        assert_eq!(with.ctes.len(), 1);
+        assert_eq!(with.ctes[0].name, "tree");
+        assert_eq!(with.ctes[0].columns, vec!["id".to_string()]);
+        match &with.ctes[0].query {
+            SetQuery::Union { left, right, all } => {
+                assert!(*all);
+                assert!(!left.reads_table("tree"));
+                assert!(right.reads_table("tree"));
+            }
+            other => panic!("Expected a UNION, got {:?}", other),
+        }
+        assert_eq!(select.table.name, "tree");
+        // The CTE's name is not a table
+        let tables = query.tables_referenced();
+        assert_eq!(
+            tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+            vec!["nodes"]
+        );
+        assert_eq!(parse(&query.to_sql()).unwrap(), query);
+
+        // Without RECURSIVE, a plain CTE parses and a UNION without ALL is kept apart
+        let query = parse(
+            "WITH a AS (SELECT x FROM t UNION SELECT x FROM s), b AS (SELECT x FROM a) \
+             SELECT x FROM b",
+        )
+        .unwrap();
+        let Query::Select(select) = &query else {
+            panic!("Expected a SELECT, got {:?}", query);
+        };
+        let with = select.with.as_ref().unwrap();
+        assert!(!with.recursive);
+        assert!(matches!(
+            with.ctes[0].query,
+            SetQuery::Union { all: false, .. }
+        ));
+        assert_eq!(parse(&query.to_sql()).unwrap(), query);
+
+        assert_eq!(
+            parse("WITH t AS (SELECT a FROM s UNION ALL SELECT a FROM t) SELECT a FROM t")
+                .unwrap_err(),
+            "CTE 't' refers to itself; declare it with WITH RECURSIVE at line 1, column 55"
+        );
+        assert_eq!(
+            parse("WITH RECURSIVE t AS (SELECT a FROM t) SELECT a FROM t").unwrap_err(),
+            "Recursive CTE 't' must be a base query UNION [ALL] a recursive term at line 1, column 39"
+        );
+        assert_eq!(
+            parse(
+                "WITH RECURSIVE t AS (SELECT a FROM t UNION ALL SELECT a FROM t) SELECT a FROM t"
+            )
+            .unwrap_err(),
+            "The base query of recursive CTE 't' cannot refer to 't' at line 1, column 65"
+        );
+        assert_eq!(
+            parse("WITH t AS (SELECT a FROM s), t AS (SELECT a FROM s) SELECT a FROM t")
+                .unwrap_err(),
+            "CTE 't' is defined more than once at line 1, column 32"
+        );
+        assert!(parse("WITH t AS SELECT a FROM s SELECT a FROM t").is_err());
+    }
+
+    /// Tests that the standard OFFSET ... FETCH form parses into the same limit and offset as
+    /// LIMIT ... OFFSET.
+    #[test]
+    fn test_offset_fetch() {
+        let expected = parse("SELECT a FROM t ORDER BY a LIMIT 20 OFFSET 10");
+        assert!(expected.is_ok());
+        for sql in [
+            "SELECT a FROM t ORDER BY a OFFSET 10 ROWS FETCH NEXT 20 ROWS ONLY",
+            "SELECT a FROM t ORDER BY a OFFSET 10 ROW FETCH FIRST 20 ROW ONLY",
+            "SELECT a FROM t ORDER BY a offset 10 fetch first 20 rows only",
+        ] {
+            assert_eq!(parse(sql), expected, "{}", sql);
+        }
+
+        assert_eq!(
+            parse("SELECT a FROM t FETCH FIRST ROW ONLY"),
+            parse("SELECT a FROM t LIMIT 1")
+        );
+        assert_eq!(
+            parse("SELECT a FROM t LIMIT 5 FETCH NEXT 2 ROWS ONLY"),
+            Err("FETCH cannot be combined with LIMIT at line 1, column 31".to_string())
+        );
+        assert_eq!(
+            parse("SELECT a FROM t FETCH 2 ROWS ONLY"),
+            Err("Expected FIRST or NEXT after FETCH at line 1, column 23".to_string())
+        );
+        assert_eq!(
+            parse("SELECT a FROM t FETCH NEXT 2 ONLY"),
+            Err("Expected ROW or ROWS in FETCH clause at line 1, column 30".to_string())
+        );
+
+        // The FETCH clause words are only keywords inside the clause
+        let Query::Select(select) =
+            parse("SELECT first, next, row, rows, only, fetch FROM people ORDER BY next").unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(select.columns.len(), 6);
+        assert_eq!(select.columns[0].expression, *identifier("first"));
+        assert_eq!(
+            parse("SELECT a FROM t FETCH FIRST 3 ROWS ONLY"),
+            parse("SELECT a FROM t LIMIT 3")
+        );
+        assert!(parse("SELECT a FROM t WHERE first > 1 FETCH NEXT first ROWS ONLY").is_ok());
+    }
+
+    /// Tests that a character the lexer rejects is reported as a lexing error rather than as the
+    /// query ending early.
+    #[test]
+    fn test_unexpected_character() {
+        assert_eq!(
+            parse("SELECT ! FROM t"),
+            Err("Unexpected character '!'; use NOT or '!=' at line 1, column 8".to_string())
+        );
+        assert_eq!(
+            parse("SELECT a FROM t WHERE a ? 1"),
+            Err("Unexpected character '?' at line 1, column 25".to_string())
+        );
+    }
+
+    /// Tests that a trailing `;` ends the statement and that anything after the statement is rejected.
+    #[test]
+    fn test_statement_terminator() {
+        assert_eq!(
+            parse("SELECT * FROM table1;"),
+            parse("SELECT * FROM table1")
+        );
+        assert_eq!(parse("COMMIT ; -- done\n"), Ok(Query::Commit));
+        assert_eq!(
+            parse("SELECT a FROM t; SELECT b FROM t").unwrap_err(),
+            "Only one statement can be parsed at a time at line 1, column 18"
+        );
+        assert!(parse(";").is_err());
+
+        // Input left over after a complete statement is an error, not dropped
+        for (sql, token, column) in [
+            ("SELECT a FROM t SELECT b FROM t", "Keyword(\"SELECT\")", 17),
+            ("SELECT a FROM t WHERE a IS NULL", "Identifier(\"IS\")", 25),
+            (
+                "SELECT a FROM t WHERE a NOT IN (1, 2)",
+                "Keyword(\"NOT\")",
+                25,
+            ),
+            ("SELECT a FROM t JOIN u USING (a)", "LeftParen", 30),
+        ] {
+            assert_eq!(
+                parse(sql).unwrap_err(),
+                format!(
+                    "Unexpected {} after the end of the statement at line 1, column {}",
+                    token, column
+                ),
+                "{}",
+                sql
+            );
+        }
+    }
+
+    /// Tests that GROUPING parses as a call in the select list of a grouped query.
+    #[test]
+    fn test_grouping_function() {
+        let Ok(Query::Select(select)) = parse("SELECT a, GROUPING(a, b) FROM t GROUP BY a, b")
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns[1].expression,
+            Expression::Function {
+                name: "GROUPING".to_string(),
+                args: vec![*identifier("a"), *identifier("b")],
+                named_args: Vec::new(),
+                filter: None,
+                order_by: Vec::new(),
+            }
+        );
+        assert_eq!(
+            parse("SELECT GROUPING(a) FILTER (WHERE a > 1) FROM t GROUP BY a"),
+            Err(
+                "FILTER is only allowed on aggregate functions, not 'GROUPING' \
+                 at line 1, column 27"
+                    .to_string()
+            )
+        );
+    }
+
+    /// Tests that comments anywhere in a query leave the parsed query unchanged.
+    #[test]
+    fn test_comments_are_ignored() {
+        let commented = parse(
+            "SELECT name, -- the display name\n\
+             age /* in years */ FROM users /* all of them,\n except */ WHERE age > 21 -- adults",
+        );
+        assert_eq!(
+            commented,
+            parse("SELECT name, age FROM users WHERE age > 21")
+        );
+        assert!(commented.is_ok());
+        assert_eq!(
+            parse("SELECT a FROM t /* never closed"),
+            Err("Unterminated block comment at line 1, column 17".to_string())
+        );
+    }
+
+    /// Tests that a bare value list parses values of mixed types and rejects a trailing comma.
+    #[test]
+    fn test_parse_value_list() {
+        let values = |line: &str| Parser::new(line)?.parse_value_list();
+
+        assert_eq!(
+            values("1, 2.5, 'a,b', NULL, TRUE, X'ff', DEFAULT"),
+            Ok(vec![
+                Value::Integer(1),
+                Value::Float(2.5),
+                Value::Text("a,b".to_string()),
+                Value::Null,
+                Value::Boolean(true),
+                Value::Blob(vec![0xff]),
+                Value::Default,
+            ])
+        );
+        assert_eq!(values("42"), Ok(vec![Value::Integer(42)]));
+        assert_eq!(
+            values("1, 2,"),
+            Err("Expected a value after the last ',' at line 1, column 6".to_string())
+        );
+        assert_eq!(
+            values("1 2"),
+            Err(
+                "Expected ',' between values, but found Integer(2) at line 1, column 3".to_string()
+            )
+        );
+        assert!(values("1, @").is_err());
+    }
+
+    /// Tests that a reserved keyword where a table or column name belongs is reported with a
+    /// hint to quote it, and that quoting it works.
+    #[test]
+    fn test_reserved_keyword_as_identifier() {
+        let reserved = |keyword: &str, column: usize| {
+            Err(format!(
+                "Reserved keyword '{}' cannot be used as an identifier; quote it as \"{}\" \
+                 at line 1, column {}",
+                keyword.to_uppercase(),
+                keyword.to_lowercase(),
+                column
+            ))
+        };
+
+        assert_eq!(parse("SELECT a FROM from"), reserved("from", 15));
+        assert_eq!(parse("SELECT select FROM t"), reserved("select", 8));
+        assert_eq!(parse("SELECT t.order FROM t"), reserved("order", 10));
+        assert_eq!(
+            parse("SELECT a FROM t WHERE a = limit"),
+            reserved("limit", 27)
+        );
+        assert_eq!(
+            parse("INSERT INTO t (id, group) VALUES (1, 2)"),
+            reserved("group", 20)
+        );
+
+        let Ok(Query::Select(select)) = parse(r#"SELECT "select" FROM "from""#) else {
+            panic!("Expected quoted keywords to parse as identifiers");
+        };
+        assert_eq!(select.columns, select_items(vec![*identifier("select")]));
+        assert_eq!(select.table.name, "from");
+    }
+
+    /// Tests that SHOW and DESCRIBE parse into metadata queries.
+    #[test]
+    fn test_show_and_describe() {
+        assert_eq!(parse("SHOW TABLES"), Ok(Query::ShowTables));
+        assert_eq!(
+            parse("show columns from users"),
+            Ok(Query::ShowColumns {
+                table: "users".to_string()
+            })
+        );
+        assert_eq!(
+            parse("DESCRIBE users"),
+            Ok(Query::Describe {
+                table: "users".to_string()
+            })
+        );
+        assert_eq!(
+            parse("SHOW INDEXES").unwrap_err(),
+            "Expected TABLES or COLUMNS after 'SHOW' at line 1, column 6"
+        );
+        assert_eq!(
+            parse("SHOW COLUMNS users").unwrap_err(),
+            "Expected keyword 'FROM' at line 1, column 14"
+        );
+
+        // The words are only keywords at the start of the statement and after SHOW
+        assert_eq!(
+            parse("SHOW COLUMNS FROM tables"),
+            Ok(Query::ShowColumns {
+                table: "tables".to_string()
+            })
+        );
+        let Query::Select(select) = parse("SELECT columns, show FROM tables").unwrap() else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![*identifier("columns"), *identifier("show")])
+        );
+        assert_eq!(select.table.name, "tables");
+        assert!(parse("\"SHOW\" TABLES").is_err());
+    }
+
+    /// Tests that DEFAULT parses as a marker in INSERT values and in expressions.
+    #[test]
+    fn test_default_keyword() {
+        let query = parse("INSERT INTO t (a, b) VALUES (1, default)").unwrap();
+        let Query::Insert(insert) = query else {
+            panic!("Expected INSERT query");
+        };
+        assert_eq!(insert.values, Some(vec![Value::Integer(1), Value::Default]));
+
+        // Whether DEFAULT is allowed here is checked later, not by the parser
+        let query = parse("SELECT DEFAULT FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(select.columns, select_items(vec![Expression::Default]));
+    }
+
+    /// Tests that LIKE accepts an optional single-character ESCAPE clause.
+    #[test]
+    fn test_like_escape() {
+        let query = parse(r"SELECT a FROM t WHERE name LIKE 'a\%b' ESCAPE '\'").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.where_clause,
+            Some(Expression::Like {
+                expr: Box::new(Expression::Identifier("name".to_string())),
+                pattern: Box::new(Expression::Text(r"a\%b".to_string())),
+                escape: Some('\\'),
+            })
+        );
+
+        let query = parse("SELECT a FROM t WHERE name LIKE 'a%' AND b = 1").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert!(matches!(
+            select.where_clause,
+            Some(Expression::And(ref like, _))
+                if matches!(**like, Expression::Like { escape: None, .. })
+        ));
+
+        assert_eq!(
+            parse("SELECT a FROM t WHERE name LIKE 'a' ESCAPE 'xy'").unwrap_err(),
+            "ESCAPE must be a single character, got 'xy' at line 1, column 44"
+        );
+        assert!(parse("SELECT a FROM t WHERE name LIKE 'a' ESCAPE ''").is_err());
+    }
+
+    /// Tests that calls parse with any number of arguments; arity is the analyzer's to check.
+    #[test]
+    fn test_known_function_arity() {
+        let query = parse("SELECT GREATEST(a, b, 3), least(a) FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert!(
+            matches!(&select.columns[0].expression, Expression::Function { name, args, .. }
+            if name == "GREATEST" && args.len() == 3)
+        );
+
+        assert!(parse("SELECT GREATEST() FROM t").is_ok());
+        assert!(parse("SELECT my_function() FROM t").is_ok());
+    }
+
+    /// Tests each join type with and without OUTER, that a bare JOIN and a comma default to
+    /// inner and cross joins, and which joins need or forbid an ON condition.
+    #[test]
+    fn test_join_types() {
+        let join = |sql: &str| match parse(sql).unwrap() {
+            Query::Select(mut select) => {
+                let join = select.joins.remove(0);
+                (join.join_type, join.table.name, join.condition.is_some())
+            }
+            other => panic!("Expected a SELECT, got {:?}", other),
+        };
+        let on = |join_type: JoinType| (join_type, "u".to_string(), true);
+
+        for (sql, join_type) in [
+            ("JOIN", JoinType::Inner),
+            ("INNER JOIN", JoinType::Inner),
+            ("LEFT JOIN", JoinType::Left),
+            ("left outer join", JoinType::Left),
+            ("RIGHT JOIN", JoinType::Right),
+            ("RIGHT OUTER JOIN", JoinType::Right),
+            ("FULL JOIN", JoinType::Full),
+            ("FULL OUTER JOIN", JoinType::Full),
+        ] {
+            assert_eq!(
+                join(&format!("SELECT * FROM t {} u ON t.id = u.id", sql)),
+                on(join_type),
+                "{}",
+                sql
+            );
+        }
+
+        assert_eq!(
+            join("SELECT * FROM t CROSS JOIN u"),
+            (JoinType::Cross, "u".to_string(), false)
+        );
+        assert_eq!(
+            join("SELECT * FROM t, u"),
+            (JoinType::Cross, "u".to_string(), false)
+        );
+        assert_eq!(
+            join("SELECT * FROM t JOIN u"),
+            (JoinType::Inner, "u".to_string(), false)
+        );
+
+        assert_eq!(
+            parse("SELECT * FROM t CROSS JOIN u ON t.id = u.id").unwrap_err(),
+            "CROSS JOIN cannot have an ON condition at line 1, column 33"
+        );
+        assert_eq!(
+            parse("SELECT * FROM t LEFT JOIN u WHERE a = 1").unwrap_err(),
+            "LEFT JOIN requires an ON condition at line 1, column 29"
+        );
+        assert!(parse("SELECT * FROM t INNER OUTER JOIN u ON true").is_err());
+        assert!(parse("SELECT * FROM t CROSS u").is_err());
+        assert_eq!(
+            parse("SELECT * FROM (SELECT a FROM u) LEFT JOIN v ON true").unwrap_err(),
+            "A subquery in FROM must have an alias at line 1, column 33"
+        );
+    }
+
+    /// Tests that the join type words are only keywords before JOIN: LEFT and RIGHT are still
+    /// string functions, and all of them are still column names.
+    #[test]
+    fn test_join_words_outside_joins() {
+        let columns = |sql: &str| match parse(sql).unwrap() {
+            Query::Select(select) => select
+                .columns
+                .into_iter()
+                .map(|item| item.expression)
+                .collect::<Vec<_>>(),
+            other => panic!("Expected a SELECT, got {:?}", other),
+        };
+        let function = |name: &str| Expression::Function {
+            name: name.to_string(),
+            args: vec![*identifier("name"), Expression::Integer(3)],
+            named_args: Vec::new(),
+            filter: None,
+            order_by: Vec::new(),
+        };
+
+        assert_eq!(
+            columns("SELECT LEFT(name, 3), right(name, 3) FROM t"),
+            vec![function("LEFT"), function("right")]
+        );
+        assert_eq!(
+            columns("SELECT left, right, full, inner, outer, cross FROM t"),
+            ["left", "right", "full", "inner", "outer", "cross"]
+                .into_iter()
+                .map(|name| *identifier(name))
+                .collect::<Vec<_>>()
+        );
+        assert!(parse("SELECT a FROM t WHERE left = 1 AND RIGHT(name, 1) = 'x'").is_ok());
+    }
+
+    /// Tests that a subquery in FROM is parsed as a derived table with its alias, that LATERAL
+    /// marks it, and that a comma in FROM joins the next table without a condition.
+    #[test]
+    fn test_lateral_join() {
+        let Query::Select(select) =
+            parse("SELECT * FROM t, LATERAL (SELECT x FROM u WHERE u.id = t.id) s").unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(select.table.name, "t");
+        assert_eq!(select.joins.len(), 1);
+        let join = &select.joins[0];
+        assert_eq!(join.condition, None);
+        assert_eq!(join.table.name, "s");
+        assert!(join.table.lateral);
+        assert_eq!(join.table.subquery.as_ref().unwrap().table.name, "u");
+
+        let Query::Select(select) =
+            parse("SELECT * FROM (SELECT x FROM u) AS s JOIN LATERAL (SELECT 1 FROM v) w ON true")
+                .unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert!(select.table.subquery.is_some());
+        assert!(!select.table.lateral);
+        assert!(select.joins[0].table.lateral);
+        assert_eq!(select.joins[0].condition, Some(Expression::Boolean(true)));
+
+        assert_eq!(
+            parse("SELECT * FROM t, LATERAL u").unwrap_err(),
+            "LATERAL must be followed by a subquery in parentheses at line 1, column 26"
+        );
+        assert_eq!(
+            parse("SELECT * FROM (SELECT x FROM u) WHERE x = 1").unwrap_err(),
+            "A subquery in FROM must have an alias at line 1, column 33"
+        );
+    }
+
+    /// Tests that TABLESAMPLE attaches to the table in FROM and JOIN clauses.
+    #[test]
+    fn test_tablesample() {
+        let query =
+            parse("SELECT * FROM big_table TABLESAMPLE BERNOULLI (10) JOIN t TABLESAMPLE SYSTEM (2.5) ON a = b")
+                .unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.table,
+            Table {
+                name: "big_table".to_string(),
+                alias: None,
+                sample: Some(TableSample {
+                    method: SampleMethod::Bernoulli,
+                    percent: 10.0,
+                }),
+                subquery: None,
+                lateral: false,
+            }
+        );
+        assert_eq!(
+            select.joins[0].table.sample,
+            Some(TableSample {
+                method: SampleMethod::System,
+                percent: 2.5,
+            })
+        );
+
+        assert_eq!(
+            parse("SELECT * FROM t TABLESAMPLE RANDOM (10)").unwrap_err(),
+            "Expected BERNOULLI or SYSTEM after TABLESAMPLE at line 1, column 29"
+        );
+        assert_eq!(
+            parse("SELECT * FROM t TABLESAMPLE SYSTEM (150)").unwrap_err(),
+            "Sample percentage must be between 0 and 100, got 150 at line 1, column 37"
+        );
+    }
+
+    /// Tests that FILTER (WHERE ...) attaches to an aggregate call and is rejected elsewhere.
+    #[test]
+    fn test_aggregate_filter() {
+        let query = parse("SELECT COUNT(*) FILTER (WHERE status = 'active') FROM t").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![Expression::Function {
+                name: "COUNT".to_string(),
+                args: vec![Expression::Asterisk],
+                named_args: Vec::new(),
+                filter: Some(Box::new(Expression::Binary {
+                    left: Box::new(Expression::Identifier("status".to_string())),
+                    operator: BinaryOperator::Equal,
+                    right: Box::new(Expression::Text("active".to_string())),
+                })),
+                order_by: Vec::new(),
+            }])
+        );
+
+        assert_eq!(
+            parse("SELECT lower(a) FILTER (WHERE a = 1) FROM t").unwrap_err(),
+            "FILTER is only allowed on aggregate functions, not 'lower' at line 1, column 24"
+        );
+        assert!(parse("SELECT COUNT(*) FILTER (a = 1) FROM t").is_err());
+    }
+
+    /// Tests that EXCEPT and REPLACE modifiers on `*` parse into a wildcard.
+    #[test]
+    fn test_wildcard_modifiers() {
+        let query =
+            parse("SELECT * EXCEPT (password, secret) REPLACE (lower(name) AS name), 1 FROM users")
+                .unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns[0].expression,
+            Expression::Wildcard {
+                except: vec!["password".to_string(), "secret".to_string()],
+                replace: vec![Replacement {
+                    expression: Expression::Function {
+                        name: "lower".to_string(),
+                        args: vec![Expression::Identifier("name".to_string())],
+                        named_args: Vec::new(),
+                        filter: None,
+                        order_by: Vec::new(),
+                    },
+                    column: "name".to_string(),
+                }],
+            }
+        );
+        assert_eq!(select.columns[1].expression, Expression::Integer(1));
+
+        let query = parse("SELECT * FROM users").unwrap();
+        let Query::Select(select) = query else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(select.columns, select_items(vec![Expression::Asterisk]));
+
+        assert!(parse("SELECT * EXCEPT () FROM users").is_err());
+        assert!(parse("SELECT * REPLACE (1 name) FROM users").is_err());
+    }
+
+    /// Tests that EXCEPT and REPLACE are only keywords after `*`: elsewhere REPLACE is the
+    /// string function and both are ordinary names.
+    #[test]
+    fn test_wildcard_words_outside_select_list() {
+        let Query::Select(select) = parse("SELECT REPLACE(name, 'a', 'b') FROM t").unwrap() else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns[0].expression,
+            Expression::Function {
+                name: "REPLACE".to_string(),
+                args: vec![
+                    *identifier("name"),
+                    Expression::Text("a".to_string()),
+                    Expression::Text("b".to_string()),
+                ],
+                named_args: Vec::new(),
+                filter: None,
+                order_by: Vec::new(),
+            }
+        );
+
+        let Query::Select(select) = parse("SELECT except, replace FROM t AS replace").unwrap()
+        else {
+            panic!("Expected SELECT query");
+        };
+        assert_eq!(
+            select.columns,
+            select_items(vec![*identifier("except"), *identifier("replace")])
+        );
+        assert_eq!(select.table.alias, Some("replace".to_string()));
+    }
+}
@@ -0,0 +1,841 @@
+//! Query plans: trees of operators built from a parsed query by the [`Planner`] and walked by
+//! the executor.
+//!
+//! Keeping the plan separate from the AST gives optimizations such as index selection a place
+//! to live, and lets EXPLAIN show exactly what the executor will run.
+
+use crate::ast::{
+    BinaryOperator, Expression, JoinType, Ordering, QuantifiedOperand, Select, SelectItem,
+    SortOrder,
+};
+use crate::catalog::{Catalog, TableSchema};
+use crate::executor::resolve_column;
+use crate::storage::Key;
+use std::ops::RangeInclusive;
+
+/// One operator in a query plan. Each operator consumes the rows of its inputs.
+#[derive(Debug, PartialEq)]
+pub enum PlanNode<'a> {
+    /// Reads a table's rows in key order, within `range` if one is given.
+    Scan {
+        table: String,
+        key_column: String,
+        range: Option<RangeInclusive<Key>>,
+    },
+    /// Like `Scan`, but reads only the keys from the table's index, without touching its rows.
+    IndexScan {
+        table: String,
+        key_column: String,
+        range: Option<RangeInclusive<Key>>,
+    },
+    /// Answers a `COUNT(*)` from the table's index: its number of entries, or the number of
+    /// keys within `range` if one is given.
+    IndexCount {
+        table: String,
+        key_column: String,
+        range: Option<RangeInclusive<Key>>,
+    },
+    /// Keeps the rows for which every one of the ANDed `conditions` is TRUE.
+    Filter {
+        input: Box<PlanNode<'a>>,
+        conditions: Vec<&'a Expression>,
+    },
+    /// Computes the output columns of each row.
+    Project {
+        input: Box<PlanNode<'a>>,
+        columns: &'a [SelectItem],
+    },
+    /// Orders the rows. `columns` is the select list, whose aliases ORDER BY may name.
+    Sort {
+        input: Box<PlanNode<'a>>,
+        order_by: &'a [Ordering],
+        columns: &'a [SelectItem],
+    },
+    /// Folds every row into one row of aggregate values.
+    Aggregate {
+        input: Box<PlanNode<'a>>,
+        aggregates: &'a [SelectItem],
+    },
+    /// Pairs the rows of two inputs that satisfy `condition`, keeping unmatched rows as
+    /// `join_type` says.
+    Join {
+        join_type: JoinType,
+        left: Box<PlanNode<'a>>,
+        right: Box<PlanNode<'a>>,
+        condition: Option<&'a Expression>,
+    },
+    /// Skips `offset` rows, then passes on at most `limit` rows.
+    Limit {
+        input: Box<PlanNode<'a>>,
+        limit: Option<u64>,
+        offset: u64,
+    },
+}
+
+impl PlanNode<'_> {
+    /// Returns the table the leftmost scan below this node reads.
+    pub fn table(&self) -> &str {
+        match self {
+            PlanNode::Scan { table, .. }
+            | PlanNode::IndexScan { table, .. }
+            | PlanNode::IndexCount { table, .. } => table,
+            PlanNode::Filter { input, .. }
+            | PlanNode::Project { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Aggregate { input, .. }
+            | PlanNode::Limit { input, .. } => input.table(),
+            PlanNode::Join { left, .. } => left.table(),
+        }
+    }
+
+    /// Describes the plan for EXPLAIN, one operator per line with the outermost first and
+    /// each input indented below the operator that consumes it.
+    pub fn explain(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        self.explain_into(0, &mut lines);
+        lines
+    }
+
+    fn explain_into(&self, depth: usize, lines: &mut Vec<String>) {
+        let line = match self {
+            PlanNode::Scan {
+                table,
+                key_column,
+                range,
+            } => describe_scan("Scan", table, key_column, range.as_ref()),
+            PlanNode::IndexScan {
+                table,
+                key_column,
+                range,
+            } => describe_scan("Index Only Scan", table, key_column, range.as_ref()),
+            PlanNode::IndexCount {
+                table,
+                key_column,
+                range,
+            } => match range {
+                Some(range) => format!(
+                    "Count {} (index range count, {})",
+                    table,
+                    describe_key_range(range, key_column)
+                ),
+                None => format!("Count {} (index entry count)", table),
+            },
+            PlanNode::Filter { conditions, .. } => {
+                let conditions: Vec<String> = conditions
+                    .iter()
+                    .map(|condition| match condition {
+                        Expression::Or(..) => format!("({})", condition.to_sql()),
+                        _ => condition.to_sql(),
+                    })
+                    .collect();
+                format!("Filter {}", conditions.join(" AND "))
+            }
+            PlanNode::Project { columns, .. } => format!("Project {}", expression_list(columns)),
+            PlanNode::Sort { order_by, .. } => {
+                let orderings: Vec<String> = order_by.iter().map(Ordering::to_sql).collect();
+                format!("Sort {}", orderings.join(", "))
+            }
+            PlanNode::Aggregate { aggregates, .. } => {
+                format!("Aggregate {}", expression_list(aggregates))
+            }
+            PlanNode::Join {
+                join_type,
+                condition,
+                ..
+            } => {
+                let join = match join_type {
+                    JoinType::Inner => "Join",
+                    JoinType::Left => "Left Join",
+                    JoinType::Right => "Right Join",
+                    JoinType::Full => "Full Join",
+                    JoinType::Cross => "Cross Join",
+                };
+                match condition {
+                    Some(condition) => format!("{} ON {}", join, condition.to_sql()),
+                    None => join.to_string(),
+                }
+            }
+            PlanNode::Limit { limit, offset, .. } => match limit {
+                Some(limit) => format!("Limit {} OFFSET {}", limit, offset),
+                None => format!("Limit ALL OFFSET {}", offset),
+            },
+        };
+        lines.push(format!("{}{}", "  ".repeat(depth), line));
+
+        match self {
+            PlanNode::Scan { .. } | PlanNode::IndexScan { .. } | PlanNode::IndexCount { .. } => {}
+            PlanNode::Filter { input, .. }
+            | PlanNode::Project { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Aggregate { input, .. }
+            | PlanNode::Limit { input, .. } => input.explain_into(depth + 1, lines),
+            PlanNode::Join { left, right, .. } => {
+                left.explain_into(depth + 1, lines);
+                right.explain_into(depth + 1, lines);
+            }
+        }
+    }
+}
+
+/// Builds plans for queries against the tables in a catalog.
+pub struct Planner<'c> {
+    catalog: &'c Catalog,
+}
+
+impl<'c> Planner<'c> {
+    /// Creates a planner that looks tables up in `catalog`.
+    pub fn new(catalog: &'c Catalog) -> Self {
+        Planner { catalog }
+    }
+
+    /// Plans a SELECT as scan, filter, then sort and projection or aggregation, and applies
+    /// [`push_predicates`] to the result.
+    ///
+    /// The scan reads only the index when the query needs nothing but the key, and the sort is
+    /// dropped when the scan's key order already satisfies ORDER BY.
+    pub fn plan<'a>(&self, select: &'a Select) -> Result<PlanNode<'a>, String> {
+        if select.with.is_some() {
+            return Err("WITH cannot be planned yet".to_string());
+        }
+        if select.distinct {
+            return Err("SELECT DISTINCT cannot be planned yet".to_string());
+        }
+        let schema = self.schema(&select.table.name)?;
+        let table = schema.name.clone();
+        let key_column = key_column_name(schema);
+        let range = None;
+        let mut node = if reads_only_key(select, schema) {
+            PlanNode::IndexScan {
+                table,
+                key_column,
+                range,
+            }
+        } else {
+            PlanNode::Scan {
+                table,
+                key_column,
+                range,
+            }
+        };
+
+        for join in &select.joins {
+            let schema = self.schema(&join.table.name)?;
+            node = PlanNode::Join {
+                join_type: join.join_type,
+                left: Box::new(node),
+                right: Box::new(PlanNode::Scan {
+                    table: schema.name.clone(),
+                    key_column: key_column_name(schema),
+                    range: None,
+                }),
+                condition: join.condition.as_ref(),
+            };
+        }
+        if let Some(condition) = &select.where_clause {
+            node = PlanNode::Filter {
+                input: Box::new(node),
+                conditions: conjuncts(condition),
+            };
+        }
+
+        if select.is_aggregate() {
+            let node = PlanNode::Aggregate {
+                input: Box::new(node),
+                aggregates: &select.columns,
+            };
+            return Ok(push_predicates(with_limit(node, select)?));
+        }
+        if let Some(order_by) = &select.order_by {
+            // Rows come out of the index in ascending key order, so ordering by the key needs
+            // no sort
+            if !is_key_order(order_by, &select.columns, schema) {
+                node = PlanNode::Sort {
+                    input: Box::new(node),
+                    order_by,
+                    columns: &select.columns,
+                };
+            }
+        }
+        // Limiting below the projection lets a scan stop as soon as enough rows have passed
+        // the filter and sort
+        node = with_limit(node, select)?;
+        Ok(push_predicates(PlanNode::Project {
+            input: Box::new(node),
+            columns: &select.columns,
+        }))
+    }
+
+    fn schema(&self, name: &str) -> Result<&'c TableSchema, String> {
+        self.catalog
+            .get_table(name)
+            .ok_or_else(|| format!("Table '{}' does not exist", name))
+    }
+}
+
+/// Puts a Limit over `node` if the SELECT has a LIMIT or OFFSET clause.
+fn with_limit<'a>(node: PlanNode<'a>, select: &Select) -> Result<PlanNode<'a>, String> {
+    if select.limit.is_none() && select.offset.is_none() {
+        return Ok(node);
+    }
+    let limit = match &select.limit {
+        Some(limit) => Some(row_count(limit, "LIMIT")?),
+        None => None,
+    };
+    let offset = match &select.offset {
+        Some(offset) => row_count(offset, "OFFSET")?,
+        None => 0,
+    };
+    Ok(PlanNode::Limit {
+        input: Box::new(node),
+        limit,
+        offset,
+    })
+}
+
+/// Reads the row count of a LIMIT or OFFSET clause, which must be a non-negative integer.
+fn row_count(expression: &Expression, clause: &str) -> Result<u64, String> {
+    match expression {
+        Expression::Integer(count) if *count >= 0 => Ok(*count as u64),
+        other => Err(format!(
+            "{} must be a non-negative integer, got {}",
+            clause,
+            other.to_sql()
+        )),
+    }
+}
+
+fn key_column_name(schema: &TableSchema) -> String {
+    schema
+        .columns
+        .first()
+        .map(|column| column.name.clone())
+        .unwrap_or_default()
+}
+
+fn describe_scan(
+    operator: &str,
+    table: &str,
+    key_column: &str,
+    range: Option<&RangeInclusive<Key>>,
+) -> String {
+    match range {
+        Some(range) => format!(
+            "{} {} (key order, {})",
+            operator,
+            table,
+            describe_key_range(range, key_column)
+        ),
+        None => format!("{} {} (key order)", operator, table),
+    }
+}
+
+/// Describes a key range for EXPLAIN, such as `id >= 2 AND id <= 4`.
+fn describe_key_range(range: &RangeInclusive<Key>, column: &str) -> String {
+    if range.start() == range.end() {
+        return format!("{} = {}", column, range.start());
+    }
+    let mut bounds = Vec::new();
+    if *range.start() > Key::MIN {
+        bounds.push(format!("{} >= {}", column, range.start()));
+    }
+    if *range.end() < Key::MAX {
+        bounds.push(format!("{} <= {}", column, range.end()));
+    }
+    bounds.join(" AND ")
+}
+
+fn expression_list(items: &[SelectItem]) -> String {
+    items
+        .iter()
+        .map(SelectItem::to_sql)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn is_count_star(expr: &Expression) -> bool {
+    matches!(expr, Expression::Function { name, args, filter: None, .. }
+        if name.eq_ignore_ascii_case("COUNT") && matches!(args.as_slice(), [Expression::Asterisk]))
+}
+
+/// Returns whether every column a non-aggregate SELECT reads is the key column.
+fn reads_only_key(select: &Select, schema: &TableSchema) -> bool {
+    let order_by = select
+        .order_by
+        .iter()
+        .flatten()
+        .map(|ordering| sort_expression(ordering, &select.columns));
+    !select.is_aggregate()
+        && select.joins.is_empty()
+        && select
+            .columns
+            .iter()
+            .map(|item| &item.expression)
+            .chain(&select.where_clause)
+            .chain(order_by)
+            .all(|expr| references_only_key(expr, schema))
+}
+
+fn references_only_key(expr: &Expression, schema: &TableSchema) -> bool {
+    match expr {
+        Expression::Identifier(name) if name == "NULL" => true,
+        Expression::Identifier(name) => resolve_column(name, schema) == Some(0),
+        Expression::Asterisk
+        | Expression::Wildcard { .. }
+        | Expression::Exists(_)
+        | Expression::Quantified {
+            right: QuantifiedOperand::Subquery(_),
+            ..
+        } => false,
+        other => other
+            .children()
+            .into_iter()
+            .all(|child| references_only_key(child, schema)),
+    }
+}
+
+/// Returns whether `order_by` is exactly the key column ascending, the order a scan already yields.
+///
+/// A descending key order still sorts, since the tree has no reverse iterator.
+fn is_key_order(order_by: &[Ordering], columns: &[SelectItem], schema: &TableSchema) -> bool {
+    match order_by {
+        [ordering @ Ordering {
+            direction: SortOrder::Ascending,
+            collation: None,
+            ..
+        }] => matches!(
+            sort_expression(ordering, columns),
+            Expression::Identifier(name) if resolve_column(name, schema) == Some(0)
+        ),
+        _ => false,
+    }
+}
+
+/// Returns what an ORDER BY item sorts by. A bare name that is the alias of a select list item
+/// stands for that item's expression, as in `SELECT a + 1 AS b FROM t ORDER BY b`, even when
+/// the table also has a column by that name.
+pub(crate) fn sort_expression<'a>(
+    ordering: &'a Ordering,
+    columns: &'a [SelectItem],
+) -> &'a Expression {
+    if let Expression::Identifier(name) = &ordering.expression {
+        let aliased = columns.iter().find(|item| {
+            item.alias
+                .as_ref()
+                .is_some_and(|alias| alias.eq_ignore_ascii_case(name))
+        });
+        if let Some(item) = aliased {
+            return &item.expression;
+        }
+    }
+    &ordering.expression
+}
+
+/// Rewrites `plan` so that the conditions of a filter directly above a scan that compare the
+/// key with an integer literal become the scan's key range instead. The other conditions stay
+/// in the filter, and a filter left without conditions is removed.
+///
+/// A `COUNT(*)` left directly above a scan is then answered by counting the index entries
+/// within the scan's range.
+pub fn push_predicates(plan: PlanNode) -> PlanNode {
+    match plan {
+        PlanNode::Filter { input, conditions } => match push_predicates(*input) {
+            PlanNode::Scan {
+                table,
+                key_column,
+                range,
+            } => {
+                let (range, conditions) = narrow_range(range, &table, &key_column, conditions);
+                with_filter(
+                    PlanNode::Scan {
+                        table,
+                        key_column,
+                        range,
+                    },
+                    conditions,
+                )
+            }
+            PlanNode::IndexScan {
+                table,
+                key_column,
+                range,
+            } => {
+                let (range, conditions) = narrow_range(range, &table, &key_column, conditions);
+                with_filter(
+                    PlanNode::IndexScan {
+                        table,
+                        key_column,
+                        range,
+                    },
+                    conditions,
+                )
+            }
+            input => with_filter(input, conditions),
+        },
+        PlanNode::Project { input, columns } => PlanNode::Project {
+            input: Box::new(push_predicates(*input)),
+            columns,
+        },
+        PlanNode::Sort {
+            input,
+            order_by,
+            columns,
+        } => PlanNode::Sort {
+            input: Box::new(push_predicates(*input)),
+            order_by,
+            columns,
+        },
+        PlanNode::Aggregate { input, aggregates } => match (push_predicates(*input), aggregates) {
+            (
+                PlanNode::Scan {
+                    table,
+                    key_column,
+                    range,
+                },
+                [count],
+            ) if is_count_star(&count.expression) => PlanNode::IndexCount {
+                table,
+                key_column,
+                range,
+            },
+            (input, aggregates) => PlanNode::Aggregate {
+                input: Box::new(input),
+                aggregates,
+            },
+        },
+        PlanNode::Join {
+            join_type,
+            left,
+            right,
+            condition,
+        } => PlanNode::Join {
+            join_type,
+            left: Box::new(push_predicates(*left)),
+            right: Box::new(push_predicates(*right)),
+            condition,
+        },
+        PlanNode::Limit {
+            input,
+            limit,
+            offset,
+        } => PlanNode::Limit {
+            input: Box::new(push_predicates(*input)),
+            limit,
+            offset,
+        },
+        leaf @ (PlanNode::Scan { .. }
+        | PlanNode::IndexScan { .. }
+        | PlanNode::IndexCount { .. }) => leaf,
+    }
+}
+
+fn with_filter<'a>(input: PlanNode<'a>, conditions: Vec<&'a Expression>) -> PlanNode<'a> {
+    if conditions.is_empty() {
+        input
+    } else {
+        PlanNode::Filter {
+            input: Box::new(input),
+            conditions,
+        }
+    }
+}
+
+/// Splits a condition into the expressions ANDed together at its top, left to right.
+fn conjuncts(condition: &Expression) -> Vec<&Expression> {
+    match condition {
+        Expression::And(left, right) => {
+            let mut conditions = conjuncts(left);
+            conditions.extend(conjuncts(right));
+            conditions
+        }
+        other => vec![other],
+    }
+}
+
+/// Intersects `range` with the key bounds among `conditions`, returning the narrowed range and
+/// the conditions that did not bound the key.
+fn narrow_range<'a>(
+    range: Option<RangeInclusive<Key>>,
+    table: &str,
+    key_column: &str,
+    conditions: Vec<&'a Expression>,
+) -> (Option<RangeInclusive<Key>>, Vec<&'a Expression>) {
+    // Computed in i128 so that bounds such as `id > 18446744073709551615` cannot overflow
+    let (mut low, mut high) = match &range {
+        Some(range) => (i128::from(*range.start()), i128::from(*range.end())),
+        None => (i128::from(Key::MIN), i128::from(Key::MAX)),
+    };
+    let mut residual = Vec::new();
+    for condition in conditions {
+        match key_bounds(condition, table, key_column) {
+            Some((min, max)) => {
+                low = low.max(min);
+                high = high.min(max);
+            }
+            None => residual.push(condition),
+        }
+    }
+
+    let range = if low == i128::from(Key::MIN) && high == i128::from(Key::MAX) {
+        None
+    } else if low > high {
+        // No key can match; an inverted range scans nothing
+        Some(RangeInclusive::new(1, 0))
+    } else {
+        // Both bounds lie within the initial `Key` range, so the conversions cannot fail
+        Some(Key::try_from(low).unwrap_or(Key::MIN)..=Key::try_from(high).unwrap_or(Key::MAX))
+    };
+    (range, residual)
+}
+
+/// Returns the inclusive bounds `condition` places on the key if it compares the key column
+/// with an integer literal, on either side, or puts it BETWEEN two integer literals.
+fn key_bounds(condition: &Expression, table: &str, key_column: &str) -> Option<(i128, i128)> {
+    let is_key = |expr: &Expression| match expr {
+        Expression::Identifier(name) => match name.split_once('.') {
+            Some((qualifier, column)) => {
+                qualifier.eq_ignore_ascii_case(table) && column.eq_ignore_ascii_case(key_column)
+            }
+            None => name.eq_ignore_ascii_case(key_column),
+        },
+        _ => false,
+    };
+    if let Expression::Between {
+        expr,
+        low,
+        high,
+        negated: false,
+    } = condition
+    {
+        return match (&**expr, &**low, &**high) {
+            (column, Expression::Integer(low), Expression::Integer(high)) if is_key(column) => {
+                Some((i128::from(*low), i128::from(*high)))
+            }
+            _ => None,
+        };
+    }
+    let Expression::Binary {
+        left,
+        operator,
+        right,
+    } = condition
+    else {
+        return None;
+    };
+    let (value, flipped) = match (&**left, &**right) {
+        (column, Expression::Integer(value)) if is_key(column) => (i128::from(*value), false),
+        (Expression::Integer(value), column) if is_key(column) => (i128::from(*value), true),
+        _ => return None,
+    };
+
+    let (min, max) = (i128::from(Key::MIN), i128::from(Key::MAX));
+    match (operator, flipped) {
+        (BinaryOperator::Equal, _) => Some((value, value)),
+        (BinaryOperator::GreaterThan, false) | (BinaryOperator::LessThan, true) => {
+            Some((value + 1, max))
+        }
+        (BinaryOperator::GreaterThanOrEqual, false) | (BinaryOperator::LessThanOrEqual, true) => {
+            Some((value, max))
+        }
+        (BinaryOperator::LessThan, false) | (BinaryOperator::GreaterThan, true) => {
+            Some((min, value - 1))
+        }
+        (BinaryOperator::LessThanOrEqual, false) | (BinaryOperator::GreaterThanOrEqual, true) => {
+            Some((min, value))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{ColumnDef, DataType, Query};
+    use crate::parser::Parser;
+
+    fn catalog() -> Catalog {
+        let column = |name: &str, data_type: DataType| ColumnDef {
+            name: name.to_string(),
+            data_type,
+            not_null: false,
+            default: None,
+        };
+        let mut catalog = Catalog::new();
+        catalog
+            .add_table(TableSchema::new(
+                "users",
+                vec![
+                    column("id", DataType::Integer),
+                    column("name", DataType::Text),
+                ],
+            ))
+            .unwrap();
+        catalog
+    }
+
+    fn parse_select(sql: &str) -> Select {
+        let Query::Select(select) = Parser::new(sql).unwrap().parse().unwrap() else {
+            panic!("Expected a SELECT");
+        };
+        *select
+    }
+
+    /// Tests that a filtered SELECT plans as a scan, a filter and a projection.
+    #[test]
+    fn test_filtered_select_plan() {
+        let catalog = catalog();
+        let select = parse_select("SELECT name FROM users WHERE name = 'bob'");
+        let plan = Planner::new(&catalog).plan(&select).unwrap();
+
+        let PlanNode::Project { input, columns } = &plan else {
+            panic!("Expected a projection, got {:?}", plan);
+        };
+        assert_eq!(*columns, select.columns.as_slice());
+        let PlanNode::Filter { input, conditions } = &**input else {
+            panic!("Expected a filter, got {:?}", input);
+        };
+        assert_eq!(conditions.first().copied(), select.where_clause.as_ref());
+        assert_eq!(
+            **input,
+            PlanNode::Scan {
+                table: "users".to_string(),
+                key_column: "id".to_string(),
+                range: None,
+            }
+        );
+        assert_eq!(plan.table(), "users");
+        assert_eq!(
+            plan.explain(),
+            vec![
+                "Project name",
+                "  Filter name = 'bob'",
+                "    Scan users (key order)"
+            ]
+        );
+    }
+
+    /// Tests that key comparisons move from a filter into the scan's range and only the other
+    /// conditions remain in the filter.
+    #[test]
+    fn test_push_predicates() {
+        let catalog = catalog();
+        let planner = Planner::new(&catalog);
+
+        let select = parse_select("SELECT name FROM users WHERE id = 5");
+        assert_eq!(
+            planner.plan(&select).unwrap(),
+            PlanNode::Project {
+                input: Box::new(PlanNode::Scan {
+                    table: "users".to_string(),
+                    key_column: "id".to_string(),
+                    range: Some(5..=5),
+                }),
+                columns: &select.columns,
+            }
+        );
+
+        let select = parse_select(
+            "SELECT id FROM users WHERE users.id > 2 AND (id = 1 OR id = 9) AND 5 >= id",
+        );
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec![
+                "Project id",
+                "  Filter (id = 1 OR id = 9)",
+                "    Index Only Scan users (key order, id >= 3 AND id <= 5)"
+            ]
+        );
+
+        // The rule also applies to hand-built plans, and narrows an existing range
+        let conditions = conjuncts(select.where_clause.as_ref().unwrap());
+        let plan = push_predicates(PlanNode::Filter {
+            input: Box::new(PlanNode::IndexScan {
+                table: "users".to_string(),
+                key_column: "id".to_string(),
+                range: Some(0..=3),
+            }),
+            conditions: conditions[..2].to_vec(),
+        });
+        assert_eq!(
+            plan.explain(),
+            vec![
+                "Filter (id = 1 OR id = 9)",
+                "  Index Only Scan users (key order, id = 3)"
+            ]
+        );
+    }
+
+    /// Tests that key bounds, key-only reads and aggregates change the plan's shape.
+    #[test]
+    fn test_plan_shapes() {
+        let catalog = catalog();
+        let planner = Planner::new(&catalog);
+
+        let select = parse_select("SELECT id FROM users WHERE id > 2 ORDER BY id DESC");
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec![
+                "Project id",
+                "  Sort id DESC",
+                "    Index Only Scan users (key order, id >= 3)"
+            ]
+        );
+
+        let select = parse_select("SELECT COUNT(*) FROM users");
+        assert_eq!(
+            planner.plan(&select).unwrap(),
+            PlanNode::IndexCount {
+                table: "users".to_string(),
+                key_column: "id".to_string(),
+                range: None,
+            }
+        );
+        let select = parse_select("SELECT COUNT(*) FROM users WHERE id BETWEEN 2 AND 4");
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec!["Count users (index range count, id >= 2 AND id <= 4)"]
+        );
+        // A condition on another column still needs the rows
+        let select = parse_select("SELECT COUNT(*) FROM users WHERE id > 2 AND age > 30");
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec![
+                "Aggregate COUNT(*)",
+                "  Filter age > 30",
+                "    Scan users (key order, id >= 3)"
+            ]
+        );
+        let select = parse_select("SELECT COUNT(name) FROM users ORDER BY name");
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec!["Aggregate COUNT(name)", "  Scan users (key order)"]
+        );
+
+        let select =
+            parse_select("SELECT name FROM users WHERE id > 2 ORDER BY age LIMIT 5 OFFSET 1");
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec![
+                "Project name",
+                "  Limit 5 OFFSET 1",
+                "    Sort age",
+                "      Scan users (key order, id >= 3)"
+            ]
+        );
+        let select = parse_select("SELECT COUNT(*) FROM users OFFSET 1");
+        assert_eq!(
+            planner.plan(&select).unwrap().explain(),
+            vec!["Limit ALL OFFSET 1", "  Count users (index entry count)"]
+        );
+        let select = parse_select("SELECT name FROM users LIMIT age");
+        assert_eq!(
+            planner.plan(&select).unwrap_err(),
+            "LIMIT must be a non-negative integer, got age"
+        );
+
+        let select = parse_select("SELECT name FROM missing");
+        assert_eq!(
+            planner.plan(&select).unwrap_err(),
+            "Table 'missing' does not exist"
+        );
+    }
+}
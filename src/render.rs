@@ -0,0 +1,1237 @@
+//! Renders the AST back into SQL text.
+//!
+//! The output is meant to parse back into the same AST, so parentheses are only emitted where
+//! the parser's precedence would otherwise group the expression differently.
+//!
+//! `to_sql` gives uppercase keywords on a single line; `Query::to_sql_with` can lowercase the
+//! keywords and lay the statement out one clause per line, for use as a formatter.
+
+#[cfg(feature = "uuid")]
+use crate::ast::uuid_string;
+use crate::ast::{
+    BinaryOperator, ColumnDef, Cte, DataType, Expression, Insert, Join, JoinType, Ordering,
+    QuantifiedOperand, Quantifier, Query, SampleMethod, Select, SelectItem, SetQuery, SortOrder,
+    Table, TableSample, Update, Value,
+};
+use crate::lexer::Lexer;
+use crate::tokens::{is_boolean, is_contextual_keyword, is_keyword, Token};
+
+/// Binding strength of each expression level, from loosest to tightest.
+const PRECEDENCE_OR: u8 = 1;
+const PRECEDENCE_AND: u8 = 2;
+const PRECEDENCE_NOT: u8 = 3;
+const PRECEDENCE_COMPARISON: u8 = 4;
+const PRECEDENCE_ADDITIVE: u8 = 5;
+const PRECEDENCE_MULTIPLICATIVE: u8 = 6;
+const PRECEDENCE_UNARY: u8 = 7;
+const PRECEDENCE_JSON: u8 = 8;
+const PRECEDENCE_POSTFIX: u8 = 9;
+const PRECEDENCE_TERM: u8 = 10;
+
+/// How `Query::to_sql_with` spells keywords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeywordCase {
+    #[default]
+    Upper,
+    Lower,
+}
+
+/// Formatting options for `Query::to_sql_with`. The default matches `to_sql`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The case of keywords, `TRUE`, `FALSE` and `NULL`. Identifiers, function names and type
+    /// names are rendered as they are in the AST.
+    pub keyword_case: KeywordCase,
+    /// Puts each clause keyword on its own line, with the clause's items on indented lines
+    /// below it. Subqueries stay on one line.
+    pub pretty: bool,
+    /// The number of spaces items are indented by when `pretty` is set.
+    pub indent: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            keyword_case: KeywordCase::Upper,
+            pretty: false,
+            indent: 4,
+        }
+    }
+}
+
+/// A clause of a statement: its leading keywords and the items that follow them.
+struct Clause {
+    keyword: &'static str,
+    items: Vec<String>,
+    separator: &'static str,
+}
+
+impl Clause {
+    /// A clause whose items are a comma-separated list.
+    fn list(keyword: &'static str, items: Vec<String>) -> Self {
+        Clause {
+            keyword,
+            items,
+            separator: ", ",
+        }
+    }
+
+    /// A clause followed by a single item.
+    fn single(keyword: &'static str, item: String) -> Self {
+        Clause::list(keyword, vec![item])
+    }
+}
+
+/// Joins clauses onto one line, or when `indent` is given, puts each keyword on its own line
+/// with the items on lines indented below it.
+fn join_clauses(clauses: &[Clause], indent: Option<usize>) -> String {
+    let rendered: Vec<String> = clauses
+        .iter()
+        .map(|clause| match indent {
+            None => format!("{} {}", clause.keyword, clause.items.join(clause.separator)),
+            Some(width) => {
+                let indent = " ".repeat(width);
+                let separator = format!("{}\n{}", clause.separator.trim_end(), indent);
+                format!(
+                    "{}\n{}{}",
+                    clause.keyword,
+                    indent,
+                    clause.items.join(&separator)
+                )
+            }
+        })
+        .collect();
+    rendered.join(if indent.is_some() { "\n" } else { " " })
+}
+
+/// Lowercases the keywords, booleans and NULLs in rendered SQL, leaving identifiers, string
+/// literals and quoted names as they are.
+///
+/// Contextual keywords lex as identifiers. Names spelled like one are always rendered quoted,
+/// so a bare one is a keyword, unless the `(` of a function call follows it directly.
+fn lowercase_keywords(sql: &str) -> String {
+    let mut folded = sql.to_string();
+    let mut lexer = Lexer::new(sql);
+    while let Some(token) = lexer.next_token() {
+        let length = match token {
+            Token::Keyword(keyword) => keyword.len(),
+            Token::Identifier(word) if is_contextual_keyword(&word) => {
+                let start = lexer.token_start();
+                if sql[start..].starts_with('"') || sql[start + word.len()..].starts_with('(') {
+                    continue;
+                }
+                word.len()
+            }
+            Token::Boolean(true) => "TRUE".len(),
+            Token::Boolean(false) => "FALSE".len(),
+            Token::Null => "NULL".len(),
+            _ => continue,
+        };
+        let start = lexer.token_start();
+        folded[start..start + length].make_ascii_lowercase();
+    }
+    folded
+}
+
+impl Query {
+    /// Renders the query as SQL laid out as `options` describe.
+    ///
+    /// Pretty-printing applies to SELECT, INSERT and UPDATE; other statements are short enough
+    /// to stay on one line.
+    pub fn to_sql_with(&self, options: &RenderOptions) -> String {
+        let sql = if options.pretty {
+            self.to_sql_indented(options.indent)
+        } else {
+            self.to_sql()
+        };
+        match options.keyword_case {
+            KeywordCase::Upper => sql,
+            KeywordCase::Lower => lowercase_keywords(&sql),
+        }
+    }
+
+    fn to_sql_indented(&self, indent: usize) -> String {
+        match self {
+            Query::Select(select) => join_clauses(&select.clauses(), Some(indent)),
+            Query::Insert(insert) => join_clauses(&insert.clauses(), Some(indent)),
+            Query::Update(update) => join_clauses(&update.clauses(), Some(indent)),
+            Query::Explain(query) => format!("EXPLAIN {}", query.to_sql_indented(indent)),
+            other => other.to_sql(),
+        }
+    }
+
+    /// Renders the query as SQL.
+    pub fn to_sql(&self) -> String {
+        match self {
+            Query::Select(select) => select.to_sql(),
+            Query::Insert(insert) => insert.to_sql(),
+            Query::Update(update) => update.to_sql(),
+            Query::Explain(query) => format!("EXPLAIN {}", query.to_sql()),
+            Query::Begin => "BEGIN".to_string(),
+            Query::Commit => "COMMIT".to_string(),
+            Query::Rollback => "ROLLBACK".to_string(),
+            Query::ShowTables => "SHOW TABLES".to_string(),
+            Query::ShowColumns { table } => {
+                format!("SHOW COLUMNS FROM {}", identifier_sql(table))
+            }
+            Query::Describe { table } => format!("DESCRIBE {}", identifier_sql(table)),
+            Query::DropTable { name, if_exists } => format!(
+                "DROP TABLE {}{}",
+                if *if_exists { "IF EXISTS " } else { "" },
+                identifier_sql(name)
+            ),
+            Query::CreateTable { name, columns } => {
+                let columns: Vec<String> = columns.iter().map(ColumnDef::to_sql).collect();
+                format!(
+                    "CREATE TABLE {} ({})",
+                    identifier_sql(name),
+                    columns.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl Select {
+    /// Renders the SELECT statement as SQL.
+    pub fn to_sql(&self) -> String {
+        join_clauses(&self.clauses(), None)
+    }
+
+    fn clauses(&self) -> Vec<Clause> {
+        let mut clauses = Vec::new();
+        if let Some(with) = &self.with {
+            let keyword = if with.recursive {
+                "WITH RECURSIVE"
+            } else {
+                "WITH"
+            };
+            clauses.push(Clause::list(
+                keyword,
+                with.ctes.iter().map(Cte::to_sql).collect(),
+            ));
+        }
+        clauses.push(Clause::list(
+            if self.distinct {
+                "SELECT DISTINCT"
+            } else {
+                "SELECT"
+            },
+            self.columns.iter().map(SelectItem::to_sql).collect(),
+        ));
+        // Joins follow the first table without commas
+        let tables = std::iter::once(self.table.to_sql())
+            .chain(self.joins.iter().map(Join::to_sql))
+            .collect();
+        clauses.push(Clause {
+            keyword: "FROM",
+            items: tables,
+            separator: " ",
+        });
+        if let Some(where_clause) = &self.where_clause {
+            clauses.push(Clause::single("WHERE", where_clause.to_sql()));
+        }
+        if let Some(group_by) = &self.group_by {
+            clauses.push(Clause::list(
+                "GROUP BY",
+                group_by.iter().map(Expression::to_sql).collect(),
+            ));
+        }
+        if let Some(having) = &self.having {
+            clauses.push(Clause::single("HAVING", having.to_sql()));
+        }
+        if let Some(order_by) = &self.order_by {
+            clauses.push(Clause::list(
+                "ORDER BY",
+                order_by.iter().map(Ordering::to_sql).collect(),
+            ));
+        }
+        if let Some(limit) = &self.limit {
+            clauses.push(Clause::single("LIMIT", limit.to_sql()));
+        }
+        if let Some(offset) = &self.offset {
+            clauses.push(Clause::single("OFFSET", offset.to_sql()));
+        }
+        clauses
+    }
+}
+
+impl Cte {
+    /// Renders the CTE as SQL, `name [(column, ...)] AS (query)`.
+    pub fn to_sql(&self) -> String {
+        let mut sql = identifier_sql(&self.name);
+        if !self.columns.is_empty() {
+            let columns: Vec<String> = self.columns.iter().map(|c| identifier_sql(c)).collect();
+            sql.push_str(&format!(" ({})", columns.join(", ")));
+        }
+        format!("{} AS ({})", sql, self.query.to_sql())
+    }
+}
+
+impl SetQuery {
+    /// Renders the query as SQL, with each UNION on the same line.
+    pub fn to_sql(&self) -> String {
+        match self {
+            SetQuery::Select(select) => select.to_sql(),
+            SetQuery::Union { left, right, all } => {
+                let union = if *all { "UNION ALL" } else { "UNION" };
+                format!("{} {} {}", left.to_sql(), union, right.to_sql())
+            }
+        }
+    }
+}
+
+impl SelectItem {
+    /// Renders the select list item as SQL, with its alias after AS.
+    pub fn to_sql(&self) -> String {
+        match &self.alias {
+            Some(alias) => format!("{} AS {}", self.expression.to_sql(), identifier_sql(alias)),
+            None => self.expression.to_sql(),
+        }
+    }
+}
+
+impl Insert {
+    /// Renders the INSERT statement as SQL.
+    pub fn to_sql(&self) -> String {
+        join_clauses(&self.clauses(), None)
+    }
+
+    fn clauses(&self) -> Vec<Clause> {
+        let columns: Vec<String> = self.columns.iter().map(|c| identifier_sql(c)).collect();
+        let target = format!("{} ({})", self.table.to_sql(), columns.join(", "));
+        let mut clauses = vec![Clause::single("INSERT INTO", target)];
+        if let Some(values) = &self.values {
+            let values: Vec<String> = values.iter().map(Value::to_sql).collect();
+            clauses.push(Clause::single("VALUES", format!("({})", values.join(", "))));
+        } else if let Some(select) = &self.select {
+            clauses.extend(select.clauses());
+        }
+        clauses
+    }
+}
+
+impl Update {
+    /// Renders the UPDATE statement as SQL.
+    pub fn to_sql(&self) -> String {
+        join_clauses(&self.clauses(), None)
+    }
+
+    fn clauses(&self) -> Vec<Clause> {
+        let assignments = self
+            .assignments
+            .iter()
+            .map(|(column, expression)| {
+                format!("{} = {}", identifier_sql(column), expression.to_sql())
+            })
+            .collect();
+        let mut clauses = vec![
+            Clause::single("UPDATE", self.table.to_sql()),
+            Clause::list("SET", assignments),
+        ];
+        if let Some(condition) = &self.where_clause {
+            clauses.push(Clause::single("WHERE", condition.to_sql()));
+        }
+        clauses
+    }
+}
+
+impl Table {
+    /// Renders the table reference as SQL.
+    pub fn to_sql(&self) -> String {
+        if let Some(subquery) = &self.subquery {
+            let lateral = if self.lateral { "LATERAL " } else { "" };
+            return format!(
+                "{}({}) AS {}",
+                lateral,
+                subquery.to_sql(),
+                identifier_sql(&self.name)
+            );
+        }
+        let mut sql = identifier_sql(&self.name);
+        if let Some(alias) = &self.alias {
+            sql.push_str(&format!(" AS {}", identifier_sql(alias)));
+        }
+        if let Some(sample) = &self.sample {
+            sql.push_str(&format!(" {}", sample.to_sql()));
+        }
+        sql
+    }
+}
+
+impl TableSample {
+    /// Renders the TABLESAMPLE clause as SQL.
+    pub fn to_sql(&self) -> String {
+        let method = match self.method {
+            SampleMethod::Bernoulli => "BERNOULLI",
+            SampleMethod::System => "SYSTEM",
+        };
+        format!("TABLESAMPLE {} ({})", method, self.percent)
+    }
+}
+
+impl Join {
+    /// Renders the JOIN clause as SQL.
+    pub fn to_sql(&self) -> String {
+        let join = self.join_type.to_sql();
+        match &self.condition {
+            Some(condition) => {
+                format!("{} {} ON {}", join, self.table.to_sql(), condition.to_sql())
+            }
+            None => format!("{} {}", join, self.table.to_sql()),
+        }
+    }
+}
+
+impl JoinType {
+    /// Returns the keywords that introduce a join of this type. Inner joins use a bare JOIN.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            JoinType::Inner => "JOIN",
+            JoinType::Left => "LEFT JOIN",
+            JoinType::Right => "RIGHT JOIN",
+            JoinType::Full => "FULL JOIN",
+            JoinType::Cross => "CROSS JOIN",
+        }
+    }
+}
+
+impl Ordering {
+    /// Renders the ORDER BY item as SQL.
+    pub fn to_sql(&self) -> String {
+        let mut sql = self.expression.to_sql();
+        if let Some(collation) = &self.collation {
+            sql.push_str(&format!(" COLLATE {}", quote_identifier(collation)));
+        }
+        if self.direction == SortOrder::Descending {
+            sql.push_str(" DESC");
+        }
+        sql
+    }
+}
+
+impl Expression {
+    /// Renders the expression as SQL.
+    pub fn to_sql(&self) -> String {
+        match self {
+            Expression::Or(left, right) => format!(
+                "{} OR {}",
+                left.to_sql_at(PRECEDENCE_OR),
+                right.to_sql_at(PRECEDENCE_AND)
+            ),
+            Expression::And(left, right) => format!(
+                "{} AND {}",
+                left.to_sql_at(PRECEDENCE_AND),
+                right.to_sql_at(PRECEDENCE_NOT)
+            ),
+            Expression::Not(expr) => format!("NOT {}", expr.to_sql_at(PRECEDENCE_NOT)),
+            // A number or another minus gets parentheses, since `-1` would re-parse as a
+            // negative literal and `--` starts a comment
+            Expression::Negate(expr) => match **expr {
+                Expression::Integer(_) | Expression::Float(_) | Expression::Negate(_) => {
+                    format!("-({})", expr.to_sql())
+                }
+                _ => format!("-{}", expr.to_sql_at(PRECEDENCE_UNARY)),
+            },
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let (left_level, right_level) = match operator {
+                    BinaryOperator::JsonGet | BinaryOperator::JsonGetText => {
+                        (PRECEDENCE_JSON, PRECEDENCE_POSTFIX)
+                    }
+                    BinaryOperator::Plus | BinaryOperator::Minus => {
+                        (PRECEDENCE_ADDITIVE, PRECEDENCE_MULTIPLICATIVE)
+                    }
+                    BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => {
+                        (PRECEDENCE_MULTIPLICATIVE, PRECEDENCE_UNARY)
+                    }
+                    _ => (PRECEDENCE_ADDITIVE, PRECEDENCE_ADDITIVE),
+                };
+                format!(
+                    "{} {} {}",
+                    left.to_sql_at(left_level),
+                    operator.to_sql(),
+                    right.to_sql_at(right_level)
+                )
+            }
+            // The parser represents a NULL literal as this identifier
+            Expression::Identifier(name) if name == "NULL" => name.clone(),
+            Expression::Identifier(name) => match name.split_once('.') {
+                Some((qualifier, column)) => {
+                    format!("{}.{}", identifier_sql(qualifier), identifier_sql(column))
+                }
+                None => identifier_sql(name),
+            },
+            Expression::Asterisk => "*".to_string(),
+            Expression::Wildcard { except, replace } => {
+                let mut sql = "*".to_string();
+                if !except.is_empty() {
+                    let except: Vec<String> = except.iter().map(|c| identifier_sql(c)).collect();
+                    sql.push_str(&format!(" EXCEPT ({})", except.join(", ")));
+                }
+                if !replace.is_empty() {
+                    let items: Vec<String> = replace
+                        .iter()
+                        .map(|item| {
+                            let column = identifier_sql(&item.column);
+                            format!("{} AS {}", item.expression.to_sql(), column)
+                        })
+                        .collect();
+                    sql.push_str(&format!(" REPLACE ({})", items.join(", ")));
+                }
+                sql
+            }
+            Expression::Integer(i) => i.to_string(),
+            Expression::Float(f) => float_sql(*f),
+            Expression::Text(s) => quote_string(s),
+            Expression::Boolean(b) => boolean_sql(*b),
+            Expression::Blob(bytes) => blob_sql(bytes),
+            Expression::Default => "DEFAULT".to_string(),
+            Expression::Function {
+                name,
+                args,
+                named_args,
+                filter,
+                order_by,
+            } => {
+                let args: Vec<String> =
+                    args.iter()
+                        .map(Expression::to_sql)
+                        .chain(named_args.iter().map(|(name, arg)| {
+                            format!("{} => {}", identifier_sql(name), arg.to_sql())
+                        }))
+                        .collect();
+                let mut sql = format!("{}({}", name, args.join(", "));
+                if !order_by.is_empty() {
+                    let orderings: Vec<String> = order_by.iter().map(Ordering::to_sql).collect();
+                    sql.push_str(&format!(" ORDER BY {}", orderings.join(", ")));
+                }
+                sql.push(')');
+                if let Some(filter) = filter {
+                    sql.push_str(&format!(" FILTER (WHERE {})", filter.to_sql()));
+                }
+                sql
+            }
+            Expression::Array(elements) => format!("ARRAY[{}]", expression_list(elements)),
+            Expression::Row(elements) => format!("({})", expression_list(elements)),
+            Expression::InList { expr, list } => format!(
+                "{} IN ({})",
+                expr.to_sql_at(PRECEDENCE_ADDITIVE),
+                expression_list(list)
+            ),
+            Expression::Subscript { base, index } => {
+                format!("{}[{}]", base.to_sql_at(PRECEDENCE_POSTFIX), index.to_sql())
+            }
+            Expression::Exists(subquery) => format!("EXISTS ({})", subquery.to_sql()),
+            Expression::Quantified {
+                left,
+                operator,
+                quantifier,
+                right,
+            } => {
+                let right = match right {
+                    QuantifiedOperand::Array(array) => array.to_sql(),
+                    QuantifiedOperand::Subquery(subquery) => subquery.to_sql(),
+                };
+                format!(
+                    "{} {} {} ({})",
+                    left.to_sql_at(PRECEDENCE_ADDITIVE),
+                    operator.to_sql(),
+                    quantifier.to_sql(),
+                    right
+                )
+            }
+            Expression::Interval(interval) => format!("INTERVAL {}", quote_string(interval)),
+            Expression::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => format!(
+                "{} {}BETWEEN {} AND {}",
+                expr.to_sql_at(PRECEDENCE_ADDITIVE),
+                if *negated { "NOT " } else { "" },
+                low.to_sql_at(PRECEDENCE_ADDITIVE),
+                high.to_sql_at(PRECEDENCE_ADDITIVE)
+            ),
+            Expression::Like {
+                expr,
+                pattern,
+                escape,
+            } => {
+                let mut sql = format!(
+                    "{} LIKE {}",
+                    expr.to_sql_at(PRECEDENCE_ADDITIVE),
+                    pattern.to_sql_at(PRECEDENCE_ADDITIVE)
+                );
+                if let Some(escape) = escape {
+                    sql.push_str(&format!(" ESCAPE {}", quote_string(&escape.to_string())));
+                }
+                sql
+            }
+            Expression::Cast { expr, data_type } => {
+                format!(
+                    "{}::{}",
+                    expr.to_sql_at(PRECEDENCE_POSTFIX),
+                    data_type.to_sql()
+                )
+            }
+            Expression::AtTimeZone { expr, zone } => format!(
+                "{} AT TIME ZONE {}",
+                expr.to_sql_at(PRECEDENCE_POSTFIX),
+                zone.to_sql_at(PRECEDENCE_TERM)
+            ),
+        }
+    }
+
+    /// Renders the expression, wrapping it in parentheses if it binds looser than `level`.
+    fn to_sql_at(&self, level: u8) -> String {
+        if self.precedence() < level {
+            format!("({})", self.to_sql())
+        } else {
+            self.to_sql()
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expression::Or(..) => PRECEDENCE_OR,
+            Expression::And(..) => PRECEDENCE_AND,
+            Expression::Not(..) => PRECEDENCE_NOT,
+            Expression::Negate(..) => PRECEDENCE_UNARY,
+            // A negative literal reads as a minus sign applied to the number
+            Expression::Integer(i) if *i < 0 => PRECEDENCE_UNARY,
+            Expression::Float(f) if f.is_sign_negative() => PRECEDENCE_UNARY,
+            Expression::Binary { operator, .. } => match operator {
+                BinaryOperator::JsonGet | BinaryOperator::JsonGetText => PRECEDENCE_JSON,
+                BinaryOperator::Plus | BinaryOperator::Minus => PRECEDENCE_ADDITIVE,
+                BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => {
+                    PRECEDENCE_MULTIPLICATIVE
+                }
+                _ => PRECEDENCE_COMPARISON,
+            },
+            Expression::Like { .. }
+            | Expression::InList { .. }
+            | Expression::Between { .. }
+            | Expression::Quantified { .. } => PRECEDENCE_COMPARISON,
+            Expression::Subscript { .. }
+            | Expression::AtTimeZone { .. }
+            | Expression::Cast { .. } => PRECEDENCE_POSTFIX,
+            _ => PRECEDENCE_TERM,
+        }
+    }
+}
+
+impl Quantifier {
+    /// Renders the quantifier keyword.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            Quantifier::Any => "ANY",
+            Quantifier::All => "ALL",
+        }
+    }
+}
+
+impl ColumnDef {
+    /// Renders the column definition as it appears in CREATE TABLE.
+    pub fn to_sql(&self) -> String {
+        let mut sql = format!("{} {}", identifier_sql(&self.name), self.data_type.to_sql());
+        if self.not_null {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(default) = &self.default {
+            sql.push_str(&format!(" DEFAULT {}", default.to_sql()));
+        }
+        sql
+    }
+}
+
+impl DataType {
+    /// Renders the type name.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            DataType::Integer => "INTEGER",
+            DataType::Float => "FLOAT",
+            DataType::Text => "TEXT",
+            DataType::Boolean => "BOOLEAN",
+            #[cfg(feature = "json")]
+            DataType::Json => "JSON",
+        }
+    }
+}
+
+impl BinaryOperator {
+    /// Renders the operator symbol.
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::Plus => "+",
+            BinaryOperator::Minus => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::JsonGet => "->",
+            BinaryOperator::JsonGetText => "->>",
+        }
+    }
+}
+
+impl Value {
+    /// Renders the value as a SQL literal.
+    pub fn to_sql(&self) -> String {
+        match self {
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => float_sql(*f),
+            Value::Text(s) => quote_string(s),
+            Value::Boolean(b) => boolean_sql(*b),
+            Value::Blob(bytes) => blob_sql(bytes),
+            Value::Default => "DEFAULT".to_string(),
+            #[cfg(feature = "json")]
+            Value::Json(json) => format!("JSON {}", quote_string(&json.to_string())),
+            #[cfg(feature = "uuid")]
+            Value::Uuid(bytes) => format!("UUID '{}'", uuid_string(bytes)),
+            Value::Null => "NULL".to_string(),
+        }
+    }
+}
+
+fn expression_list(expressions: &[Expression]) -> String {
+    let rendered: Vec<String> = expressions.iter().map(Expression::to_sql).collect();
+    rendered.join(", ")
+}
+
+fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn quote_identifier(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+/// Renders an identifier bare when it would lex back as the same identifier, and quoted when it
+/// is a keyword, a literal such as TRUE or NULL, or contains characters an identifier can't.
+/// Contextual keywords are quoted too, so they are never mistaken for the keyword.
+fn identifier_sql(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(char::is_alphabetic)
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !is_keyword(name)
+        && !is_contextual_keyword(name)
+        && !is_boolean(name)
+        && !name.eq_ignore_ascii_case("NULL");
+    if plain {
+        name.to_string()
+    } else {
+        quote_identifier(name)
+    }
+}
+
+fn boolean_sql(b: bool) -> String {
+    if b { "TRUE" } else { "FALSE" }.to_string()
+}
+
+/// Renders a float so it parses back to the same value and stays a float: `Display` gives the
+/// shortest digits that round-trip without an exponent, and whole numbers get a `.0`.
+fn float_sql(f: f64) -> String {
+    let rendered = f.to_string();
+    if f.is_finite() && !rendered.contains('.') {
+        format!("{}.0", rendered)
+    } else {
+        rendered
+    }
+}
+
+fn blob_sql(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("X'{}'", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeywordCase, RenderOptions};
+    use crate::ast::{Expression, Query};
+    use crate::parser::Parser;
+    use proptest::prelude::*;
+
+    fn round_trip(sql: &str) -> String {
+        let query = Parser::new(sql).unwrap().parse().unwrap();
+        let rendered = query.to_sql();
+        let reparsed = Parser::new(&rendered).unwrap().parse().unwrap();
+        assert_eq!(query, reparsed, "'{}' did not round-trip", rendered);
+        rendered
+    }
+
+    /// Tests that JSON access operators render and re-parse to the same AST.
+    #[test]
+    fn test_json_operators_round_trip() {
+        assert_eq!(
+            round_trip("SELECT data -> 'a' ->> 'b' FROM t WHERE data ->> 'k' = 'v'"),
+            "SELECT data -> 'a' ->> 'b' FROM t WHERE data ->> 'k' = 'v'"
+        );
+    }
+
+    /// Tests that logical operators keep their grouping through rendering.
+    #[test]
+    fn test_logical_grouping_round_trip() {
+        assert_eq!(
+            round_trip("SELECT a FROM t WHERE a = 1 AND (b = 2 OR NOT (c = 3 AND d = 4))"),
+            "SELECT a FROM t WHERE a = 1 AND (b = 2 OR NOT (c = 3 AND d = 4))"
+        );
+    }
+
+    /// Tests that BETWEEN renders with its bounds and optional NOT.
+    #[test]
+    fn test_between_round_trip() {
+        assert_eq!(
+            round_trip("SELECT a FROM t WHERE a NOT BETWEEN b - 1 AND c AND NOT d BETWEEN 1 AND 2"),
+            "SELECT a FROM t WHERE a NOT BETWEEN b - 1 AND c AND NOT d BETWEEN 1 AND 2"
+        );
+    }
+
+    /// Tests that named arguments render after the positional ones.
+    #[test]
+    fn test_named_arguments_round_trip() {
+        assert_eq!(
+            round_trip("SELECT make_date(2024, month=>1, day => 2) FROM t"),
+            "SELECT make_date(2024, month => 1, day => 2) FROM t"
+        );
+    }
+
+    /// Tests that LIMIT and OFFSET render after ORDER BY.
+    #[test]
+    fn test_limit_round_trip() {
+        for sql in [
+            "SELECT a FROM t ORDER BY a LIMIT 10 OFFSET 20",
+            "SELECT a FROM t LIMIT 1 + 2",
+            "SELECT a FROM t OFFSET 5",
+        ] {
+            assert_eq!(round_trip(sql), sql);
+        }
+    }
+
+    /// Tests that metadata statements render back into SQL.
+    #[test]
+    fn test_show_round_trip() {
+        for sql in ["SHOW TABLES", "SHOW COLUMNS FROM users", "DESCRIBE users"] {
+            assert_eq!(round_trip(sql), sql);
+        }
+    }
+
+    /// Tests that repeated NOTs render without extra parentheses.
+    #[test]
+    fn test_repeated_not_round_trip() {
+        assert_eq!(
+            round_trip("SELECT a FROM t WHERE NOT NOT a = 1 AND NOT (b OR c)"),
+            "SELECT a FROM t WHERE NOT NOT a = 1 AND NOT (b OR c)"
+        );
+    }
+
+    /// Tests that INSERT statements render back into SQL.
+    #[test]
+    fn test_insert_round_trip() {
+        assert_eq!(
+            round_trip("INSERT INTO t (a, b) VALUES (1, 'x')"),
+            "INSERT INTO t (a, b) VALUES (1, 'x')"
+        );
+        round_trip("INSERT INTO t (a) SELECT x FROM s JOIN u ON s.id = u.id ORDER BY x DESC");
+    }
+
+    /// Tests that UUID literals render in canonical lowercase form and parse back.
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_round_trip() {
+        assert_eq!(
+            round_trip("INSERT INTO t (id) VALUES (UUID '550E8400-E29B-41D4-A716-446655440000')"),
+            "INSERT INTO t (id) VALUES (UUID '550e8400-e29b-41d4-a716-446655440000')"
+        );
+    }
+
+    /// Tests that UPDATE statements render their assignments and WHERE clause.
+    #[test]
+    fn test_update_round_trip() {
+        for sql in [
+            "UPDATE t SET a = 1",
+            "UPDATE t SET a = a + 1, \"from\" = DEFAULT WHERE b = 'x' OR NOT c",
+        ] {
+            assert_eq!(round_trip(sql), sql);
+        }
+    }
+
+    /// Tests that quantified comparisons render with their operand in parentheses.
+    #[test]
+    fn test_quantified_round_trip() {
+        assert_eq!(
+            round_trip("SELECT a FROM t WHERE x = some(ARRAY[1, 2]) OR y <= all (select b from s)"),
+            "SELECT a FROM t WHERE x = ANY (ARRAY[1, 2]) OR y <= ALL (SELECT b FROM s)"
+        );
+    }
+
+    /// Tests that keyword casing applies to keywords and literals but not to identifiers,
+    /// strings or quoted names.
+    #[test]
+    fn test_keyword_case() {
+        let query =
+            Parser::new("SELECT Name, \"SELECT\" FROM t WHERE a <> NULL AND b = 'AND' OR TRUE")
+                .unwrap()
+                .parse()
+                .unwrap();
+        let upper = RenderOptions::default();
+        assert_eq!(query.to_sql_with(&upper), query.to_sql());
+
+        let lower = RenderOptions {
+            keyword_case: KeywordCase::Lower,
+            ..RenderOptions::default()
+        };
+        let rendered = query.to_sql_with(&lower);
+        assert_eq!(
+            rendered,
+            "select Name, \"SELECT\" from t where a != null and b = 'AND' or true"
+        );
+        assert_eq!(Parser::new(&rendered).unwrap().parse().unwrap(), query);
+    }
+
+    /// Tests that pretty-printing puts each clause on its own line and still parses back.
+    #[test]
+    fn test_pretty_print() {
+        let sql = "SELECT a, b FROM t JOIN s ON t.id = s.id WHERE a > 1 ORDER BY a DESC, b LIMIT 5";
+        let query = Parser::new(sql).unwrap().parse().unwrap();
+        let options = RenderOptions {
+            pretty: true,
+            indent: 2,
+            ..RenderOptions::default()
+        };
+        let rendered = query.to_sql_with(&options);
+        assert_eq!(
+            rendered,
+            "SELECT\n  a,\n  b\nFROM\n  t\n  JOIN s ON t.id = s.id\nWHERE\n  a > 1\n\
+             ORDER BY\n  a DESC,\n  b\nLIMIT\n  5"
+        );
+        assert_eq!(Parser::new(&rendered).unwrap().parse().unwrap(), query);
+
+        let update = Parser::new("update t set a = 1, b = 2 where c")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let options = RenderOptions {
+            keyword_case: KeywordCase::Lower,
+            pretty: true,
+            indent: 4,
+        };
+        assert_eq!(
+            update.to_sql_with(&options),
+            "update\n    t\nset\n    a = 1,\n    b = 2\nwhere\n    c"
+        );
+    }
+
+    /// Tests that CREATE TABLE renders its column definitions in canonical form.
+    #[test]
+    fn test_create_table_round_trip() {
+        assert_eq!(
+            round_trip("create table t (id int not null, name varchar default 'a', f real)"),
+            "CREATE TABLE t (id INTEGER NOT NULL, name TEXT DEFAULT 'a', f FLOAT)"
+        );
+    }
+
+    /// Tests that DROP TABLE round-trips with and without IF EXISTS.
+    #[test]
+    fn test_drop_table_round_trip() {
+        for sql in ["DROP TABLE t", "DROP TABLE IF EXISTS \"table\""] {
+            assert_eq!(round_trip(sql), sql);
+        }
+    }
+
+    /// Tests that additive expressions keep their grouping when rendered.
+    #[test]
+    fn test_interval_arithmetic_round_trip() {
+        assert_eq!(
+            round_trip("SELECT ts - INTERVAL '1 hour' FROM t WHERE d + INTERVAL '7 days' > now"),
+            "SELECT ts - INTERVAL '1 hour' FROM t WHERE d + INTERVAL '7 days' > now"
+        );
+        assert_eq!(
+            round_trip("SELECT a - (b - c) FROM t"),
+            "SELECT a - (b - c) FROM t"
+        );
+    }
+
+    /// Tests that multiplicative operators only get parentheses where precedence needs them.
+    #[test]
+    fn test_multiplicative_round_trip() {
+        for sql in [
+            "SELECT a + b * c, (a + b) * c, a * b % c, a / (b * c) FROM t",
+            "SELECT a FROM t WHERE a - b / 2 > c * (d - 1)",
+            "SELECT data -> 'n' * 2 FROM t",
+        ] {
+            assert_eq!(round_trip(sql), sql);
+        }
+    }
+
+    /// Tests that unary minus renders without parentheses where the grouping allows, and with
+    /// them where the operand would otherwise re-parse differently.
+    #[test]
+    fn test_unary_minus_round_trip() {
+        for sql in [
+            "SELECT -a * b, a - -b, -(a + b), -1, -2.5 FROM t",
+            "SELECT a FROM t WHERE a < -5 AND -(-a) > -(5)",
+            "SELECT (-1)::TEXT, -1::TEXT, (-a) -> 'k', -a -> 'k' FROM t",
+        ] {
+            assert_eq!(round_trip(sql), sql);
+        }
+        assert_eq!(round_trip("SELECT +a, - 1 FROM t"), "SELECT a, -1 FROM t");
+    }
+
+    /// Tests that AT TIME ZONE keeps its operand and zone grouped when rendered.
+    #[test]
+    fn test_at_time_zone_round_trip() {
+        assert_eq!(
+            round_trip("SELECT ts AT TIME ZONE 'UTC' FROM t"),
+            "SELECT ts AT TIME ZONE 'UTC' FROM t"
+        );
+        assert_eq!(
+            round_trip("SELECT x - (a + b) AT TIME ZONE (z AT TIME ZONE 'UTC') FROM t"),
+            "SELECT x - (a + b) AT TIME ZONE (z AT TIME ZONE 'UTC') FROM t"
+        );
+        assert_eq!(
+            round_trip("SELECT time AT TIME ZONE zone FROM t"),
+            "SELECT \"time\" AT TIME ZONE \"zone\" FROM t"
+        );
+    }
+
+    /// Tests that LIKE renders with its ESCAPE clause.
+    #[test]
+    fn test_like_round_trip() {
+        assert_eq!(
+            round_trip(r"SELECT a FROM t WHERE name LIKE 'a\%' ESCAPE '\' OR name LIKE b"),
+            r"SELECT a FROM t WHERE name LIKE 'a\%' ESCAPE '\' OR name LIKE b"
+        );
+    }
+
+    /// Tests that TABLESAMPLE clauses render after their table.
+    #[test]
+    fn test_tablesample_round_trip() {
+        assert_eq!(
+            round_trip(
+                "SELECT * FROM t TABLESAMPLE BERNOULLI (10) JOIN u TABLESAMPLE SYSTEM (0.5)"
+            ),
+            "SELECT * FROM t TABLESAMPLE BERNOULLI (10) JOIN u TABLESAMPLE SYSTEM (0.5)"
+        );
+    }
+
+    /// Tests that aggregate FILTER clauses render after the call.
+    #[test]
+    fn test_aggregate_filter_round_trip() {
+        assert_eq!(
+            round_trip("SELECT COUNT(*) FILTER (WHERE a = 1 OR b = 2) FROM t"),
+            "SELECT COUNT(*) FILTER (WHERE a = 1 OR b = 2) FROM t"
+        );
+    }
+
+    /// Tests that an aggregate's inner ORDER BY renders inside the parentheses.
+    #[test]
+    fn test_aggregate_order_by_round_trip() {
+        assert_eq!(
+            round_trip(
+                "SELECT string_agg(name, ', ' ORDER BY name DESC) FILTER (WHERE id > 1) FROM t"
+            ),
+            "SELECT string_agg(name, ', ' ORDER BY name DESC) FILTER (WHERE id > 1) FROM t"
+        );
+    }
+
+    /// Tests that wildcard modifiers render after the `*`.
+    #[test]
+    fn test_wildcard_round_trip() {
+        assert_eq!(
+            round_trip("SELECT * EXCEPT (a, b) REPLACE (c + 1 AS c) FROM t"),
+            "SELECT * EXCEPT (a, b) REPLACE (c + 1 AS c) FROM t"
+        );
+        // As names, the modifier words are quoted so they can't be read as the keywords
+        assert_eq!(
+            round_trip("SELECT REPLACE(except, 'a', 'b') AS replace FROM t"),
+            "SELECT REPLACE(\"except\", 'a', 'b') AS \"replace\" FROM t"
+        );
+
+        let lower = RenderOptions {
+            keyword_case: KeywordCase::Lower,
+            ..RenderOptions::default()
+        };
+        let query = Parser::new("SELECT * EXCEPT (a) REPLACE (REPLACE(b, 'x', 'y') AS b) FROM t")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query.to_sql_with(&lower),
+            "select * except (a) replace (REPLACE(b, 'x', 'y') as b) from t"
+        );
+    }
+
+    /// Tests that casts render as postfix `::type` and keep their operand grouped.
+    #[test]
+    fn test_cast_round_trip() {
+        assert_eq!(
+            round_trip(
+                "SELECT x::int::text FROM t WHERE c = (a + b)::float AND d -> 'k'::text = 'v'"
+            ),
+            "SELECT x::INTEGER::TEXT FROM t WHERE c = (a + b)::FLOAT AND d -> 'k'::TEXT = 'v'"
+        );
+    }
+
+    /// Tests that row constructors and IN lists render back into parenthesized lists.
+    #[test]
+    fn test_row_and_in_round_trip() {
+        assert_eq!(
+            round_trip("SELECT a FROM t WHERE (a, b) IN ((1, 2), (3, 4)) OR (a,b) = (c, d + 1)"),
+            "SELECT a FROM t WHERE (a, b) IN ((1, 2), (3, 4)) OR (a, b) = (c, d + 1)"
+        );
+    }
+
+    /// Tests that blob literals render as uppercase hex in values and expressions.
+    #[test]
+    fn test_blob_round_trip() {
+        assert_eq!(
+            round_trip("INSERT INTO t (id, data) VALUES (1, x'00ff10')"),
+            "INSERT INTO t (id, data) VALUES (1, X'00FF10')"
+        );
+        assert_eq!(
+            round_trip("SELECT a FROM t WHERE data = X'dead'"),
+            "SELECT a FROM t WHERE data = X'DEAD'"
+        );
+    }
+
+    /// Tests that transaction statements render in their canonical form.
+    #[test]
+    fn test_transaction_round_trip() {
+        assert_eq!(round_trip("START TRANSACTION"), "BEGIN");
+        assert_eq!(round_trip("COMMIT TRANSACTION"), "COMMIT");
+        assert_eq!(round_trip("ROLLBACK"), "ROLLBACK");
+    }
+
+    /// Tests that collations render quoted after the expression and before DESC.
+    #[test]
+    fn test_collate_round_trip() {
+        assert_eq!(
+            round_trip(r#"SELECT a FROM t ORDER BY a COLLATE en_US DESC, b COLLATE "x""y""#),
+            r#"SELECT a FROM t ORDER BY a COLLATE "en_US" DESC, b COLLATE "x""y""#
+        );
+    }
+
+    /// Tests that EXPLAIN renders in front of the explained statement.
+    #[test]
+    fn test_explain_round_trip() {
+        assert_eq!(
+            round_trip("EXPLAIN SELECT a FROM t ORDER BY a"),
+            "EXPLAIN SELECT a FROM t ORDER BY a"
+        );
+    }
+
+    /// Tests that JSON literals render back into typed literals.
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_literal_round_trip() {
+        assert_eq!(
+            round_trip(r#"INSERT INTO t (doc) VALUES (JSON '{"a": [1, 2]}')"#),
+            r#"INSERT INTO t (doc) VALUES (JSON '{"a":[1,2]}')"#
+        );
+    }
+
+    /// Tests that select list aliases always render with AS.
+    #[test]
+    fn test_column_alias_round_trip() {
+        assert_eq!(
+            round_trip("SELECT COUNT(*) total, a + 1 AS \"b c\" FROM t"),
+            "SELECT COUNT(*) AS total, a + 1 AS \"b c\" FROM t"
+        );
+    }
+
+    /// Tests that DISTINCT renders right after SELECT and a quoted `distinct` column stays quoted.
+    #[test]
+    fn test_select_distinct_round_trip() {
+        assert_eq!(
+            round_trip("select distinct a, \"distinct\" FROM t"),
+            "SELECT DISTINCT a, \"distinct\" FROM t"
+        );
+    }
+
+    /// Tests that table aliases render with AS before any TABLESAMPLE clause.
+    #[test]
+    fn test_table_alias_round_trip() {
+        assert_eq!(
+            round_trip("SELECT o.total FROM orders AS o TABLESAMPLE SYSTEM (5) JOIN users AS u ON o.user_id = u.id"),
+            "SELECT o.total FROM orders AS o TABLESAMPLE SYSTEM (5) JOIN users AS u ON o.user_id = u.id"
+        );
+    }
+
+    /// Tests that each join type renders with its keywords, OUTER dropped and INNER implied.
+    #[test]
+    fn test_join_types_round_trip() {
+        assert_eq!(
+            round_trip(
+                "SELECT * FROM a INNER JOIN b ON a.id = b.id LEFT OUTER JOIN c ON a.id = c.id \
+                 RIGHT JOIN d ON a.id = d.id FULL OUTER JOIN e ON a.id = e.id CROSS JOIN f"
+            ),
+            "SELECT * FROM a JOIN b ON a.id = b.id LEFT JOIN c ON a.id = c.id \
+             RIGHT JOIN d ON a.id = d.id FULL JOIN e ON a.id = e.id CROSS JOIN f"
+        );
+    }
+
+    /// Tests that subqueries in FROM render in parentheses with their alias and LATERAL.
+    #[test]
+    fn test_derived_table_round_trip() {
+        assert_eq!(
+            round_trip("SELECT * FROM t, LATERAL (SELECT x FROM u WHERE u.id = t.id) s"),
+            "SELECT * FROM t CROSS JOIN LATERAL (SELECT x FROM u WHERE u.id = t.id) AS s"
+        );
+        round_trip("SELECT s.x FROM (SELECT x FROM u) AS s");
+    }
+
+    /// Tests that identifiers are quoted only when they are keywords, literals or contain
+    /// characters a bare identifier can't, so they re-parse as the same names.
+    #[test]
+    fn test_identifier_quoting_round_trip() {
+        assert_eq!(
+            round_trip(r#"SELECT "order", "my col", t."from", "true", "a""b" FROM "select" AS t"#),
+            r#"SELECT "order", "my col", t."from", "true", "a""b" FROM "select" AS t"#
+        );
+        assert_eq!(
+            round_trip(r#"SELECT "plain", NULL, "null" FROM t WHERE "Group" = 1"#),
+            r#"SELECT plain, NULL, "null" FROM t WHERE "Group" = 1"#
+        );
+        assert_eq!(
+            round_trip(r#"INSERT INTO "order" ("key", "1st") VALUES (1, 2)"#),
+            r#"INSERT INTO "order" (key, "1st") VALUES (1, 2)"#
+        );
+        assert_eq!(round_trip(r#"DESCRIBE "from""#), r#"DESCRIBE "from""#);
+    }
+
+    /// Tests that whole-number floats keep a decimal point so they re-parse as floats.
+    #[test]
+    fn test_whole_float_round_trip() {
+        assert_eq!(
+            round_trip("SELECT 1.0, 100.0, 0.5 FROM t"),
+            "SELECT 1.0, 100.0, 0.5 FROM t"
+        );
+    }
+
+    proptest! {
+        /// Tests that any finite float literal renders and re-parses to the same value.
+        #[test]
+        fn test_float_literal_round_trip(
+            f in prop::num::f64::POSITIVE
+                | prop::num::f64::NEGATIVE
+                | prop::num::f64::ZERO
+                | prop::num::f64::NORMAL
+                | prop::num::f64::SUBNORMAL
+        ) {
+            let sql = format!("SELECT {} FROM t", Expression::Float(f).to_sql());
+            let Ok(Query::Select(select)) = Parser::new(&sql).unwrap().parse() else {
+                panic!("'{}' did not parse", sql);
+            };
+            prop_assert_eq!(&select.columns[0].expression, &Expression::Float(f), "{}", sql);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::wal::{Lsn, Wal, WalEntry};
 use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
@@ -20,7 +21,7 @@ pub enum NodeType {
 pub const PAGE_SIZE: usize = 4096;
 
 /// Data stored within a page.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageData {
     pub id: u32,
     pub node_type: NodeType,
@@ -60,63 +61,765 @@ impl Page {
     }
 }
 
+/// Bytes of a large value each overflow page holds, leaving room for the page's own fields.
+pub const OVERFLOW_CAPACITY: usize = PAGE_SIZE - 64;
+
+/// One page of an overflow chain, which stores a value too large for a single page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverflowPage {
+    pub id: u32,
+    /// The page holding the rest of the value, if any.
+    pub next: Option<u32>,
+    pub data: Vec<u8>,
+}
+
+/// What a page's slot in the file holds.
+#[derive(Serialize, Deserialize)]
+enum PageSlot<P> {
+    /// The page itself.
+    Inline(P),
+    /// The encoding of a page too large for its slot, stored in the overflow chain starting at
+    /// this page ID.
+    Overflow(u32),
+}
+
+/// Page ID reserved for the file header.
+pub const HEADER_PAGE_ID: u32 = 0;
+
+/// Bookkeeping stored in the header page, the authoritative source for page ID assignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageHeader {
+    pub next_page_id: u32,
+    pub page_count: u32,
+}
+
+/// How hard the storage engine works to get each write onto the disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Durability {
+    /// Every page and header write is synced to disk before it returns.
+    #[default]
+    Full,
+    /// Writes are left in the OS cache for bulk-load throughput.
+    ///
+    /// Until durability is set back to `Full`, which syncs the file, a crash or power loss can
+    /// lose or tear any page or header written in this mode, including the page ID counter.
+    /// Writes in this mode also skip the WAL, so `recover_to` cannot replay them.
+    Bulk,
+}
+
+/// Page-level access to a database file, as used by the buffer pool.
+///
+/// `StorageEngine` is the file-backed implementation; other backends only need to provide these
+/// operations to sit underneath a `BufferPool`. An async counterpart for tokio lives in
+/// `tokio_storage` behind the `tokio` feature.
+pub trait Storage {
+    /// Reads a page by its ID.
+    fn read_page(&mut self, page_id: u32) -> std::io::Result<PageData>;
+
+    /// Writes a page, overwriting any previous version with the same ID.
+    fn write_page(&mut self, page_data: &PageData) -> std::io::Result<()>;
+
+    /// Writes several pages as one unit and syncs them: when an error is returned, none of the
+    /// pages has changed.
+    ///
+    /// The default writes the pages in order and, if a write or the sync fails, writes back
+    /// the previous images of the pages it already wrote.
+    fn write_pages(&mut self, pages: &[PageData]) -> std::io::Result<()> {
+        let previous = pages
+            .iter()
+            .map(|page| self.read_page(page.id))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let mut written = 0;
+        let result = pages
+            .iter()
+            .try_for_each(|page| {
+                self.write_page(page)?;
+                written += 1;
+                Ok(())
+            })
+            .and_then(|()| self.sync());
+        if result.is_err() {
+            // Best effort: the caller needs the original error, not a failed restore's
+            for page in &previous[..written] {
+                let _ = self.write_page(page);
+            }
+            let _ = self.sync();
+        }
+        result
+    }
+
+    /// Allocates a new, empty page with the specified node type.
+    fn allocate_page(&mut self, node_type: NodeType) -> std::io::Result<PageData>;
+
+    /// Flushes written pages to durable storage.
+    fn sync(&mut self) -> std::io::Result<()>;
+
+    /// Switches the durability mode. Backends without a bulk mode can ignore it.
+    fn set_durability(&mut self, _durability: Durability) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Serializes a page and pads it to `PAGE_SIZE`. A page that does not fit is an error; only
+/// `StorageEngine` moves such pages to an overflow chain.
+#[cfg(feature = "tokio")]
+pub(crate) fn encode_page(page_data: &PageData) -> std::io::Result<Vec<u8>> {
+    let mut buffer = serialize(&PageSlot::Inline(page_data))?;
+    if buffer.len() > PAGE_SIZE {
+        return Err(std::io::Error::other("Page size exceeded"));
+    }
+    buffer.resize(PAGE_SIZE, 0u8);
+    Ok(buffer)
+}
+
+/// Deserializes a page read from storage.
+#[cfg(feature = "tokio")]
+pub(crate) fn decode_page(buffer: &[u8]) -> std::io::Result<PageData> {
+    match decode_slot(buffer)? {
+        PageSlot::Inline(page_data) => Ok(page_data),
+        PageSlot::Overflow(first_page_id) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Page is stored in the overflow chain at page {}",
+                first_page_id
+            ),
+        )),
+    }
+}
+
+fn decode_slot(buffer: &[u8]) -> std::io::Result<PageSlot<PageData>> {
+    bincode::deserialize(buffer)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn serialize<T: Serialize>(value: &T) -> std::io::Result<Vec<u8>> {
+    bincode::serialize(value).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Serializes the header and pads it to `PAGE_SIZE`.
+pub(crate) fn encode_header(header: &StorageHeader) -> std::io::Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = bincode::serialize(header)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    buffer.resize(PAGE_SIZE, 0u8);
+    Ok(buffer)
+}
+
+/// Deserializes the header page.
+pub(crate) fn decode_header(buffer: &[u8]) -> std::io::Result<StorageHeader> {
+    bincode::deserialize(buffer)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
 /// StorageEngine manages reading and writing pages to disk.
 pub struct StorageEngine {
     file: File,
+    header: StorageHeader,
+    durability: Durability,
+    wal: Option<Wal>,
 }
 
 impl StorageEngine {
     /// Creates a new StorageEngine with the given file path.
+    /// A new file gets a header page; an existing file has its header read back.
     pub fn new(file_path: &str) -> std::io::Result<Self> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(file_path)?;
-        Ok(StorageEngine { file })
+
+        let mut engine = StorageEngine {
+            file,
+            header: StorageHeader {
+                next_page_id: HEADER_PAGE_ID + 1,
+                page_count: 0,
+            },
+            durability: Durability::Full,
+            wal: None,
+        };
+        if engine.file.metadata()?.len() == 0 {
+            engine.write_header()?;
+        } else {
+            engine.header = engine.read_header()?;
+        }
+        Ok(engine)
     }
 
-    /// Reads a page from disk by its ID.
-    pub fn read_page(&mut self, page_id: u32) -> std::io::Result<PageData> {
+    /// Returns the current header.
+    pub fn header(&self) -> StorageHeader {
+        self.header
+    }
+
+    /// Returns the current durability mode.
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Switches the durability mode. Leaving `Bulk` syncs everything written in bulk mode.
+    pub fn set_durability(&mut self, durability: Durability) -> std::io::Result<()> {
+        if self.durability == Durability::Bulk && durability == Durability::Full {
+            self.sync()?;
+        }
+        self.durability = durability;
+        Ok(())
+    }
+
+    /// Logs every later page and header write to the WAL at `wal_path` before writing it to
+    /// the data file. Writes made in bulk mode are not logged.
+    pub fn enable_wal(&mut self, wal_path: &str) -> std::io::Result<()> {
+        self.wal = Some(Wal::open(wal_path)?);
+        Ok(())
+    }
+
+    /// Returns the LSN of the last logged write, or `None` if the WAL is not enabled.
+    pub fn wal_lsn(&self) -> Option<Lsn> {
+        self.wal.as_ref().map(Wal::last_lsn)
+    }
+
+    /// Redoes the logged writes up to and including `lsn` on the data file, then discards the
+    /// later ones so new writes continue the log from that point. Returns the LSN of the last
+    /// record applied.
+    ///
+    /// Replay only redoes writes and never undoes them, so the data file should be a copy
+    /// from before `lsn`, such as a backup taken when the log was started. Pages written in
+    /// `Durability::Bulk` mode are not in the log and cannot be replayed, so take a new backup
+    /// after a bulk load.
+    pub fn recover_to(&mut self, lsn: Lsn) -> std::io::Result<Lsn> {
+        let Some(mut wal) = self.wal.take() else {
+            return Err(std::io::Error::other("The WAL is not enabled"));
+        };
+        // Writes made while replaying must not be logged again, so the log is set aside
+        let result = self.replay(&mut wal, lsn);
+        self.wal = Some(wal);
+        result
+    }
+
+    fn replay(&mut self, wal: &mut Wal, lsn: Lsn) -> std::io::Result<Lsn> {
+        if lsn > wal.last_lsn() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("The WAL ends at LSN {}, before LSN {}", wal.last_lsn(), lsn),
+            ));
+        }
+
+        let mut applied = 0;
+        for record in wal.records()? {
+            if record.lsn > lsn {
+                break;
+            }
+            match record.entry {
+                WalEntry::Page(page_data) => self.write_page(&page_data)?,
+                WalEntry::Overflow(page) => self.write_overflow_page(&page)?,
+                WalEntry::Header(header) => {
+                    self.header = header;
+                    self.write_header()?;
+                }
+            }
+            applied = record.lsn;
+        }
+        self.sync()?;
+        wal.truncate_after(lsn)?;
+        Ok(applied)
+    }
+
+    /// Appends a write to the WAL, if there is one and the engine is not in bulk mode.
+    fn log(&mut self, entry: WalEntry) -> std::io::Result<()> {
+        match &mut self.wal {
+            Some(wal) if self.durability == Durability::Full => wal.append(entry, true).map(drop),
+            _ => Ok(()),
+        }
+    }
+
+    /// Syncs the file unless running in bulk mode.
+    fn sync_if_durable(&mut self) -> std::io::Result<()> {
+        match self.durability {
+            Durability::Full => self.sync(),
+            Durability::Bulk => Ok(()),
+        }
+    }
+
+    fn read_header(&mut self) -> std::io::Result<StorageHeader> {
         let mut buffer = vec![0u8; PAGE_SIZE];
         self.file
-            .seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+            .seek(SeekFrom::Start(HEADER_PAGE_ID as u64 * PAGE_SIZE as u64))?;
         self.file.read_exact(&mut buffer)?;
+        decode_header(&buffer)
+    }
 
-        // Deserialize the page data
-        let page_data: PageData = bincode::deserialize(&buffer)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        Ok(page_data)
+    fn write_header(&mut self) -> std::io::Result<()> {
+        let buffer = encode_header(&self.header)?;
+        self.log(WalEntry::Header(self.header))?;
+
+        self.file
+            .seek(SeekFrom::Start(HEADER_PAGE_ID as u64 * PAGE_SIZE as u64))?;
+        self.file.write_all(&buffer)?;
+        self.sync_if_durable()
     }
 
-    /// Writes a page to disk.
+    /// Reads a page from disk by its ID, following its overflow chain if it has one.
+    pub fn read_page(&mut self, page_id: u32) -> std::io::Result<PageData> {
+        let buffer = self.read_buffer(page_id)?;
+        match decode_slot(&buffer)? {
+            PageSlot::Inline(page_data) => Ok(page_data),
+            PageSlot::Overflow(first_page_id) => {
+                let encoded = self.read_overflow(first_page_id)?;
+                bincode::deserialize(&encoded)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+
+    /// Writes a page to disk. A page too large for its slot is stored in a new overflow chain,
+    /// which the slot then refers to; `read_page` reassembles it.
     pub fn write_page(&mut self, page_data: &PageData) -> std::io::Result<()> {
-        // Serialize the page data
-        let encoded: Vec<u8> = bincode::serialize(page_data)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let buffer = self.encode_slot(page_data)?;
+        self.log(WalEntry::Page(page_data.clone()))?;
+        self.write_buffer(page_data.id, &buffer)?;
+        self.sync_if_durable()
+    }
 
-        if encoded.len() > PAGE_SIZE {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Page size exceeded",
-            ));
+    /// Writes several pages as one unit, as a transaction commit needs, and syncs them. If any
+    /// write fails, the pages already written get their previous images back before the error
+    /// is returned.
+    ///
+    /// With the WAL enabled outside bulk mode, every image is logged and the log synced before
+    /// the data file is touched, so after a crash part way through, `recover_to` the last LSN
+    /// redoes the whole batch. A batch that fails is removed from the log again.
+    pub fn write_pages(&mut self, pages: &[PageData]) -> std::io::Result<()> {
+        // Overflow chains are written here, before any slot refers to them
+        let buffers = pages
+            .iter()
+            .map(|page| self.encode_slot(page))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let previous = pages
+            .iter()
+            .map(|page| self.read_buffer(page.id))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let logged_after = self.wal_lsn();
+
+        let result = self
+            .log_pages(pages)
+            .and_then(|()| {
+                for (page, buffer) in pages.iter().zip(&buffers) {
+                    self.write_buffer(page.id, buffer)?;
+                }
+                Ok(())
+            })
+            .and_then(|()| self.file.sync_data());
+        if result.is_err() {
+            // Best effort: the caller needs the original error, not a failed restore's
+            for (page, buffer) in pages.iter().zip(&previous) {
+                let _ = self.write_buffer(page.id, buffer);
+            }
+            let _ = self.file.sync_data();
+            if let (Some(wal), Some(lsn)) = (&mut self.wal, logged_after) {
+                let _ = wal.truncate_after(lsn);
+            }
         }
+        result
+    }
 
-        // Pad the buffer to PAGE_SIZE
-        let mut buffer = encoded;
+    /// Appends the images of a batch of pages to the WAL and syncs it once, under the same
+    /// conditions as `log`.
+    fn log_pages(&mut self, pages: &[PageData]) -> std::io::Result<()> {
+        match &mut self.wal {
+            Some(wal) if self.durability == Durability::Full => {
+                for page in pages {
+                    wal.append(WalEntry::Page(page.clone()), false)?;
+                }
+                wal.sync()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Encodes a page for its slot, first moving it to a new overflow chain if it does not fit.
+    ///
+    /// Rewriting a page gives it a new chain; the old one is not reclaimed, as the engine never
+    /// frees pages.
+    fn encode_slot(&mut self, page_data: &PageData) -> std::io::Result<Vec<u8>> {
+        let mut buffer = serialize(&PageSlot::Inline(page_data))?;
+        if buffer.len() > PAGE_SIZE {
+            let first_page_id = self.write_overflow(&serialize(page_data)?)?;
+            buffer = serialize(&PageSlot::<&PageData>::Overflow(first_page_id))?;
+        }
         buffer.resize(PAGE_SIZE, 0u8);
+        Ok(buffer)
+    }
+
+    fn read_buffer(&mut self, page_id: u32) -> std::io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        self.file
+            .seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+        self.file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
 
+    fn write_buffer(&mut self, page_id: u32, buffer: &[u8]) -> std::io::Result<()> {
         self.file
-            .seek(SeekFrom::Start(page_data.id as u64 * PAGE_SIZE as u64))?;
-        self.file.write_all(&buffer)?;
-        Ok(())
+            .seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+        self.file.write_all(buffer)
+    }
+
+    /// Flushes the WAL and written pages from the OS cache to the disk.
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        if let Some(wal) = &mut self.wal {
+            wal.sync()?;
+        }
+        self.file.sync_data()
     }
 
     /// Allocates a new page with the specified node type.
+    ///
+    /// The ID is reserved in the header before the page itself is written, so an allocation
+    /// whose page write fails still consumes its ID and it is never handed out twice.
     pub fn allocate_page(&mut self, node_type: NodeType) -> std::io::Result<PageData> {
-        let page_id = (self.file.metadata()?.len() / PAGE_SIZE as u64) as u32;
+        let page_id = self.reserve_page_ids(1)?;
         let page_data = PageData::new(page_id, node_type);
         self.write_page(&page_data)?;
         Ok(page_data)
     }
+
+    /// Reserves `count` consecutive page IDs in the header and returns the first.
+    fn reserve_page_ids(&mut self, count: u32) -> std::io::Result<u32> {
+        let first = self.header.next_page_id;
+        let previous = self.header;
+        self.header.next_page_id += count;
+        self.header.page_count += count;
+        if let Err(e) = self.write_header() {
+            self.header = previous;
+            return Err(e);
+        }
+        Ok(first)
+    }
+
+    /// Stores a value of any size in a chain of newly allocated overflow pages and returns
+    /// the ID of the first, which is what a page refers to the value by.
+    pub fn write_overflow(&mut self, value: &[u8]) -> std::io::Result<u32> {
+        let chunks: Vec<&[u8]> = if value.is_empty() {
+            vec![value]
+        } else {
+            value.chunks(OVERFLOW_CAPACITY).collect()
+        };
+        let first = self.reserve_page_ids(chunks.len() as u32)?;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let id = first + i as u32;
+            let page = OverflowPage {
+                id,
+                next: (i + 1 < chunks.len()).then_some(id + 1),
+                data: chunk.to_vec(),
+            };
+            self.write_overflow_page(&page)?;
+        }
+        Ok(first)
+    }
+
+    /// Reads back a value stored by `write_overflow`, following the chain from its first page.
+    pub fn read_overflow(&mut self, first_page_id: u32) -> std::io::Result<Vec<u8>> {
+        let mut value = Vec::new();
+        let mut next = Some(first_page_id);
+        let mut pages = 0;
+        while let Some(page_id) = next {
+            // A chain can't be longer than the file, so a longer one must loop
+            pages += 1;
+            if pages > self.header.page_count {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Overflow chain starting at page {} loops", first_page_id),
+                ));
+            }
+            let mut buffer = vec![0u8; PAGE_SIZE];
+            self.file
+                .seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))?;
+            self.file.read_exact(&mut buffer)?;
+            let page: OverflowPage = bincode::deserialize(&buffer)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            value.extend_from_slice(&page.data);
+            next = page.next;
+        }
+        Ok(value)
+    }
+
+    fn write_overflow_page(&mut self, page: &OverflowPage) -> std::io::Result<()> {
+        let mut buffer: Vec<u8> = bincode::serialize(page)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if buffer.len() > PAGE_SIZE {
+            return Err(std::io::Error::other("Page size exceeded"));
+        }
+        buffer.resize(PAGE_SIZE, 0u8);
+        self.log(WalEntry::Overflow(page.clone()))?;
+
+        self.file
+            .seek(SeekFrom::Start(page.id as u64 * PAGE_SIZE as u64))?;
+        self.file.write_all(&buffer)?;
+        self.sync_if_durable()
+    }
+}
+
+impl Storage for StorageEngine {
+    fn read_page(&mut self, page_id: u32) -> std::io::Result<PageData> {
+        StorageEngine::read_page(self, page_id)
+    }
+
+    fn write_page(&mut self, page_data: &PageData) -> std::io::Result<()> {
+        StorageEngine::write_page(self, page_data)
+    }
+
+    fn write_pages(&mut self, pages: &[PageData]) -> std::io::Result<()> {
+        StorageEngine::write_pages(self, pages)
+    }
+
+    fn allocate_page(&mut self, node_type: NodeType) -> std::io::Result<PageData> {
+        StorageEngine::allocate_page(self, node_type)
+    }
+
+    fn sync(&mut self) -> std::io::Result<()> {
+        StorageEngine::sync(self)
+    }
+
+    fn set_durability(&mut self, durability: Durability) -> std::io::Result<()> {
+        StorageEngine::set_durability(self, durability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_pool::BufferPool;
+    use std::fs;
+
+    /// Tests that page IDs come from the header and survive reopening the file.
+    #[test]
+    fn test_header_persists_next_page_id() {
+        let test_db = "test_header_persists.db";
+        let _ = fs::remove_file(test_db);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert_eq!(storage.allocate_page(NodeType::Leaf).unwrap().id, 1);
+        assert_eq!(storage.allocate_page(NodeType::Internal).unwrap().id, 2);
+        drop(storage);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert_eq!(
+            storage.header(),
+            StorageHeader {
+                next_page_id: 3,
+                page_count: 2,
+            }
+        );
+        assert_eq!(storage.allocate_page(NodeType::Leaf).unwrap().id, 3);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that an ID whose page write never reached the file is not handed out again.
+    #[test]
+    fn test_failed_allocation_does_not_reuse_id() {
+        let test_db = "test_failed_allocation.db";
+        let _ = fs::remove_file(test_db);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        storage.allocate_page(NodeType::Leaf).unwrap();
+        storage.allocate_page(NodeType::Leaf).unwrap();
+        drop(storage);
+
+        // Simulate the second page write being lost: the file ends after page 1
+        let file = OpenOptions::new().write(true).open(test_db).unwrap();
+        file.set_len(2 * PAGE_SIZE as u64).unwrap();
+        drop(file);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert_eq!(storage.allocate_page(NodeType::Leaf).unwrap().id, 3);
+        assert!(storage.read_page(1).is_ok());
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that replaying the WAL onto an empty data file stops at the requested LSN and
+    /// that logging continues from there.
+    #[test]
+    fn test_recover_to_intermediate_lsn() {
+        let (test_db, restored_db, test_wal) = (
+            "test_recover_to.db",
+            "test_recover_to_restored.db",
+            "test_recover_to.wal",
+        );
+        for file in [test_db, restored_db, test_wal] {
+            let _ = fs::remove_file(file);
+        }
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        storage.enable_wal(test_wal).unwrap();
+        assert_eq!(storage.wal_lsn(), Some(0));
+        let mut page = storage.allocate_page(NodeType::Leaf).unwrap();
+        let mut lsns = Vec::new();
+        for key in 1..=3 {
+            page.keys.push(key);
+            page.values.push(key as u64 * 10);
+            storage.write_page(&page).unwrap();
+            lsns.push(storage.wal_lsn().unwrap());
+        }
+        drop(storage);
+
+        // Restore from an empty data file, as if from a backup taken before any writes
+        let mut restored = StorageEngine::new(restored_db).unwrap();
+        restored.enable_wal(test_wal).unwrap();
+        assert_eq!(restored.wal_lsn(), Some(lsns[2]));
+        assert!(restored.recover_to(lsns[2] + 1).is_err());
+        assert_eq!(restored.recover_to(lsns[1]).unwrap(), lsns[1]);
+        assert_eq!(restored.read_page(page.id).unwrap().keys, vec![1, 2]);
+        assert_eq!(restored.header().page_count, 1);
+        assert_eq!(restored.wal_lsn(), Some(lsns[1]));
+
+        restored.allocate_page(NodeType::Leaf).unwrap();
+        assert_eq!(restored.wal_lsn(), Some(lsns[1] + 2));
+        drop(restored);
+        let restored = StorageEngine::new(restored_db).unwrap();
+        assert_eq!(restored.header().page_count, 2);
+
+        for file in [test_db, restored_db, test_wal] {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    /// Tests that writes made in bulk mode are not logged, and that logging resumes once
+    /// durability is back to full.
+    #[test]
+    fn test_bulk_writes_skip_wal() {
+        let (test_db, test_wal) = ("test_bulk_wal.db", "test_bulk_wal.wal");
+        for file in [test_db, test_wal] {
+            let _ = fs::remove_file(file);
+        }
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        storage.enable_wal(test_wal).unwrap();
+        let mut page = storage.allocate_page(NodeType::Leaf).unwrap();
+        let lsn = storage.wal_lsn().unwrap();
+
+        storage.set_durability(Durability::Bulk).unwrap();
+        page.keys.push(1);
+        storage.write_page(&page).unwrap();
+        storage.write_pages(std::slice::from_ref(&page)).unwrap();
+        storage.allocate_page(NodeType::Leaf).unwrap();
+        assert_eq!(storage.wal_lsn(), Some(lsn));
+
+        storage.set_durability(Durability::Full).unwrap();
+        storage.write_page(&page).unwrap();
+        assert_eq!(storage.wal_lsn(), Some(lsn + 1));
+
+        for file in [test_db, test_wal] {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    /// Tests that a batch write stores either every page or none, and that a stored batch
+    /// can be redone from the WAL.
+    #[test]
+    fn test_write_pages_batch() {
+        let (test_db, restored_db, test_wal) = (
+            "test_write_pages.db",
+            "test_write_pages_restored.db",
+            "test_write_pages.wal",
+        );
+        for file in [test_db, restored_db, test_wal] {
+            let _ = fs::remove_file(file);
+        }
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        storage.enable_wal(test_wal).unwrap();
+        let mut pages = vec![
+            storage.allocate_page(NodeType::Leaf).unwrap(),
+            storage.allocate_page(NodeType::Leaf).unwrap(),
+        ];
+        let lsn = storage.wal_lsn().unwrap();
+        pages[0].keys.push(1);
+        pages[1].keys.push(2);
+        // A page that was never allocated has no slot in the file to write to
+        let unallocated = PageData::new(storage.header().next_page_id, NodeType::Leaf);
+        assert!(storage
+            .write_pages(&[pages[0].clone(), unallocated])
+            .is_err());
+        assert!(storage.read_page(pages[0].id).unwrap().keys.is_empty());
+        assert_eq!(storage.wal_lsn(), Some(lsn));
+
+        storage.write_pages(&pages).unwrap();
+        assert_eq!(storage.read_page(pages[0].id).unwrap().keys, vec![1]);
+        assert_eq!(storage.read_page(pages[1].id).unwrap().keys, vec![2]);
+        assert_eq!(storage.wal_lsn(), Some(lsn + 2));
+        drop(storage);
+
+        let mut restored = StorageEngine::new(restored_db).unwrap();
+        restored.enable_wal(test_wal).unwrap();
+        restored.recover_to(lsn + 2).unwrap();
+        assert_eq!(restored.read_page(pages[0].id).unwrap().keys, vec![1]);
+        assert_eq!(restored.read_page(pages[1].id).unwrap().keys, vec![2]);
+
+        for file in [test_db, restored_db, test_wal] {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    /// Tests that a 10KB text value is split across linked overflow pages and read back whole,
+    /// also after reopening the file.
+    #[test]
+    fn test_overflow_chain() {
+        let test_db = "test_overflow_chain.db";
+        let _ = fs::remove_file(test_db);
+
+        let text: String = (0..10 * 1024)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        let leaf = storage.allocate_page(NodeType::Leaf).unwrap();
+        let first = storage.write_overflow(text.as_bytes()).unwrap();
+        assert_eq!(first, leaf.id + 1);
+        // 10KB needs three pages, which are allocated like any other
+        assert_eq!(storage.header().page_count, 4);
+        assert_eq!(storage.allocate_page(NodeType::Leaf).unwrap().id, first + 3);
+        assert_eq!(storage.read_overflow(first).unwrap(), text.as_bytes());
+
+        let empty = storage.write_overflow(&[]).unwrap();
+        assert!(storage.read_overflow(empty).unwrap().is_empty());
+        drop(storage);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert_eq!(storage.read_overflow(first).unwrap(), text.as_bytes());
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that a leaf holding about 10KB of entries is moved to an overflow chain by
+    /// `write_page` and `write_pages` and read back whole, also through a buffer pool and after
+    /// reopening the file.
+    #[test]
+    fn test_oversized_page() {
+        let test_db = "test_oversized_page.db";
+        let _ = fs::remove_file(test_db);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        let mut leaf = storage.allocate_page(NodeType::Leaf).unwrap();
+        leaf.keys = (0..1000).collect();
+        leaf.values = (0..1000).map(|i| i * 10).collect();
+        storage.write_page(&leaf).unwrap();
+        assert!(storage.header().page_count > 1);
+        assert_eq!(storage.read_page(leaf.id).unwrap().keys, leaf.keys);
+
+        let mut other = storage.allocate_page(NodeType::Leaf).unwrap();
+        other.keys = (1000..2000).collect();
+        other.values = leaf.values.clone();
+        leaf.keys.push(1000);
+        leaf.values.push(10000);
+        storage.write_pages(&[leaf.clone(), other.clone()]).unwrap();
+        drop(storage);
+
+        let buffer_pool = BufferPool::new(10, StorageEngine::new(test_db).unwrap());
+        let page = buffer_pool.get_page(leaf.id).unwrap();
+        assert_eq!(page.data.read().unwrap().keys, leaf.keys);
+        assert_eq!(page.data.read().unwrap().values, leaf.values);
+        let page = buffer_pool.get_page(other.id).unwrap();
+        assert_eq!(page.data.read().unwrap().keys, other.keys);
+
+        let _ = fs::remove_file(test_db);
+    }
 }
@@ -1,10 +1,13 @@
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
+    /// `*`, which is both the `SELECT *` wildcard and multiplication. The lexer can't tell them
+    /// apart; the parser decides from where the token appears.
     Asterisk,
     Identifier(String),
     Integer(i64),
     Float(f64),
     StringLiteral(String),
+    Blob(Vec<u8>),
     Boolean(bool),
     Null,
     Equal,
@@ -13,33 +16,112 @@ pub enum Token {
     LessThanOrEqual,
     GreaterThan,
     GreaterThanOrEqual,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Arrow,
+    LongArrow,
+    FatArrow,
     Comma,
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
     Dot,
+    DoubleColon,
+    /// `;`, which ends a statement.
+    Semicolon,
     Keyword(String),
 }
 
 pub fn is_keyword(literal: &str) -> bool {
     matches!(
         literal.to_uppercase().as_str(),
-        "SELECT"
+        "WITH"
+            | "RECURSIVE"
+            | "SELECT"
+            | "DISTINCT"
             | "INSERT"
             | "INTO"
             | "VALUES"
+            | "UPDATE"
+            | "SET"
+            | "CREATE"
+            | "TABLE"
+            | "DROP"
+            | "IF"
             | "FROM"
             | "JOIN"
+            | "LATERAL"
             | "ON"
             | "WHERE"
             | "GROUP"
             | "BY"
             | "HAVING"
             | "ORDER"
+            | "LIMIT"
+            | "OFFSET"
             | "ASC"
             | "DESC"
             | "AND"
             | "OR"
             | "NOT"
+            | "ARRAY"
+            | "ANY"
+            | "ALL"
+            | "SOME"
+            | "EXISTS"
+            | "EXPLAIN"
+            | "INTERVAL"
+            | "AT"
+            | "LIKE"
+            | "DEFAULT"
+            | "COLLATE"
+            | "IN"
+            | "BETWEEN"
+            | "ESCAPE"
+            | "TABLESAMPLE"
+            | "BERNOULLI"
+            | "SYSTEM"
+            | "FILTER"
+            | "UNION"
+            | "AS"
+    )
+}
+
+/// Words that are keywords only where the grammar looks for them, such as EXCEPT and REPLACE
+/// after `*` in a select list, LEFT before JOIN, FIRST after FETCH or the statement words of
+/// SHOW TABLES, DESCRIBE and the transaction statements, or TIME ZONE after AT. They lex as identifiers, so they stay usable
+/// as column, table and function names.
+pub fn is_contextual_keyword(literal: &str) -> bool {
+    matches!(
+        literal.to_uppercase().as_str(),
+        "EXCEPT"
+            | "REPLACE"
+            | "INNER"
+            | "LEFT"
+            | "RIGHT"
+            | "FULL"
+            | "OUTER"
+            | "CROSS"
+            | "FETCH"
+            | "FIRST"
+            | "NEXT"
+            | "ROW"
+            | "ROWS"
+            | "ONLY"
+            | "SHOW"
+            | "TABLES"
+            | "COLUMNS"
+            | "DESCRIBE"
+            | "BEGIN"
+            | "START"
+            | "TRANSACTION"
+            | "COMMIT"
+            | "ROLLBACK"
+            | "TIME"
+            | "ZONE"
     )
 }
 
@@ -0,0 +1,170 @@
+//! Async page storage for callers running on a tokio runtime, enabled by the `tokio` feature.
+//!
+//! [`TokioFileStorage`] reads and writes the same file format as `StorageEngine`, so a file
+//! written by one can be opened by the other.
+
+use crate::storage::{
+    decode_header, decode_page, encode_header, encode_page, NodeType, PageData, StorageHeader,
+    HEADER_PAGE_ID, PAGE_SIZE,
+};
+use std::future::Future;
+use std::io::SeekFrom;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Async counterpart of [`Storage`](crate::storage::Storage), for backends whose I/O must not
+/// block the runtime's worker threads.
+pub trait AsyncStorage {
+    /// Reads a page by its ID.
+    fn read_page(&mut self, page_id: u32)
+        -> impl Future<Output = std::io::Result<PageData>> + Send;
+
+    /// Writes a page, overwriting any previous version with the same ID.
+    fn write_page(
+        &mut self,
+        page_data: &PageData,
+    ) -> impl Future<Output = std::io::Result<()>> + Send;
+
+    /// Allocates a new, empty page with the specified node type.
+    fn allocate_page(
+        &mut self,
+        node_type: NodeType,
+    ) -> impl Future<Output = std::io::Result<PageData>> + Send;
+
+    /// Flushes written pages to durable storage.
+    fn sync(&mut self) -> impl Future<Output = std::io::Result<()>> + Send;
+}
+
+/// TokioFileStorage manages reading and writing pages to disk through `tokio::fs`.
+///
+/// Every page and header write is synced before it returns, like `Durability::Full`.
+pub struct TokioFileStorage {
+    file: File,
+    header: StorageHeader,
+}
+
+impl TokioFileStorage {
+    /// Opens or creates the file at the given path.
+    /// A new file gets a header page; an existing file has its header read back.
+    pub async fn open(file_path: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)
+            .await?;
+
+        let mut storage = TokioFileStorage {
+            file,
+            header: StorageHeader {
+                next_page_id: HEADER_PAGE_ID + 1,
+                page_count: 0,
+            },
+        };
+        if storage.file.metadata().await?.len() == 0 {
+            storage.write_header().await?;
+        } else {
+            let buffer = storage.read_at(HEADER_PAGE_ID).await?;
+            storage.header = decode_header(&buffer)?;
+        }
+        Ok(storage)
+    }
+
+    /// Returns the current header.
+    pub fn header(&self) -> StorageHeader {
+        self.header
+    }
+
+    async fn read_at(&mut self, page_id: u32) -> std::io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        self.file
+            .seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))
+            .await?;
+        self.file.read_exact(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    async fn write_at(&mut self, page_id: u32, buffer: &[u8]) -> std::io::Result<()> {
+        self.file
+            .seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64))
+            .await?;
+        self.file.write_all(buffer).await?;
+        self.file.sync_data().await
+    }
+
+    async fn write_header(&mut self) -> std::io::Result<()> {
+        let buffer = encode_header(&self.header)?;
+        self.write_at(HEADER_PAGE_ID, &buffer).await
+    }
+}
+
+impl AsyncStorage for TokioFileStorage {
+    async fn read_page(&mut self, page_id: u32) -> std::io::Result<PageData> {
+        let buffer = self.read_at(page_id).await?;
+        decode_page(&buffer)
+    }
+
+    async fn write_page(&mut self, page_data: &PageData) -> std::io::Result<()> {
+        let buffer = encode_page(page_data)?;
+        self.write_at(page_data.id, &buffer).await
+    }
+
+    /// The ID is reserved in the header before the page itself is written, as in
+    /// `StorageEngine::allocate_page`.
+    async fn allocate_page(&mut self, node_type: NodeType) -> std::io::Result<PageData> {
+        let page_id = self.header.next_page_id;
+        let previous = self.header;
+        self.header.next_page_id += 1;
+        self.header.page_count += 1;
+        if let Err(e) = self.write_header().await {
+            self.header = previous;
+            return Err(e);
+        }
+
+        let page_data = PageData::new(page_id, node_type);
+        self.write_page(&page_data).await?;
+        Ok(page_data)
+    }
+
+    async fn sync(&mut self) -> std::io::Result<()> {
+        self.file.sync_data().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageEngine;
+    use std::fs;
+
+    /// Tests that pages written asynchronously can be read back by both storage backends.
+    #[test]
+    fn test_tokio_file_storage_round_trip() {
+        let test_db = "test_tokio_file_storage.db";
+        let _ = fs::remove_file(test_db);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut storage = TokioFileStorage::open(test_db).await.unwrap();
+            let mut page = storage.allocate_page(NodeType::Leaf).await.unwrap();
+            assert_eq!(page.id, 1);
+            page.keys = vec![1, 2, 3];
+            page.values = vec![10, 20, 30];
+            storage.write_page(&page).await.unwrap();
+            assert_eq!(storage.read_page(1).await.unwrap().keys, vec![1, 2, 3]);
+            drop(storage);
+
+            let storage = TokioFileStorage::open(test_db).await.unwrap();
+            assert_eq!(storage.header().next_page_id, 2);
+        });
+
+        let mut engine = StorageEngine::new(test_db).unwrap();
+        assert_eq!(engine.read_page(1).unwrap().values, vec![10, 20, 30]);
+        assert_eq!(engine.allocate_page(NodeType::Leaf).unwrap().id, 2);
+
+        let _ = fs::remove_file(test_db);
+    }
+}
@@ -1,15 +1,246 @@
-// A file for preparing for the future. I hope you can get this far.
+//! Single-writer transactions over buffer pool pages.
+//!
+//! While a transaction is active, page writes go to private copies instead of the buffer pool.
+//! COMMIT stores every copy as one unit; ROLLBACK drops them, so none of the transaction's
+//! writes ever reach the data file.
 
-pub struct TransactionManager;
+use crate::buffer_pool::BufferPool;
+use crate::storage::PageData;
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Tracks the pages modified by the active transaction, if there is one.
+pub struct TransactionManager {
+    buffer_pool: Arc<BufferPool>,
+    // Modified copies by page ID, or `None` when no transaction is active
+    dirty: Mutex<Option<HashMap<u32, PageData>>>,
+}
 
 impl TransactionManager {
-    pub fn new() -> Self {
-        TransactionManager
+    pub fn new(buffer_pool: Arc<BufferPool>) -> Self {
+        TransactionManager {
+            buffer_pool,
+            dirty: Mutex::new(None),
+        }
+    }
+
+    /// Returns whether a transaction is in progress.
+    pub fn is_active(&self) -> bool {
+        self.dirty.lock().unwrap().is_some()
+    }
+
+    /// Starts a transaction. Only one can be active at a time.
+    pub fn begin(&self) -> io::Result<()> {
+        let mut dirty = self.dirty.lock().unwrap();
+        if dirty.is_some() {
+            return Err(io::Error::other("A transaction is already in progress"));
+        }
+        *dirty = Some(HashMap::new());
+        Ok(())
+    }
+
+    /// Reads a page, seeing the active transaction's own writes.
+    pub fn read_page(&self, page_id: u32) -> io::Result<PageData> {
+        if let Some(page) = self
+            .dirty
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|dirty| dirty.get(&page_id))
+        {
+            return Ok(page.clone());
+        }
+        let page = self.buffer_pool.get_page(page_id)?;
+        let data = page.data.read().unwrap().clone();
+        Ok(data)
+    }
+
+    /// Writes a page. Inside a transaction the write is buffered until COMMIT; outside one it
+    /// goes straight to the buffer pool and storage.
+    pub fn write_page(&self, page_data: PageData) -> io::Result<()> {
+        let mut dirty = self.dirty.lock().unwrap();
+        match dirty.as_mut() {
+            Some(dirty) => {
+                dirty.insert(page_data.id, page_data);
+                Ok(())
+            }
+            None => self.buffer_pool.put_page(page_data),
+        }
     }
 
-    pub fn begin(&self) {}
+    /// Stores every page written by the transaction as one unit and syncs them to disk.
+    ///
+    /// If storing fails, no page is changed and the transaction stays active, so the commit
+    /// can be retried or the transaction rolled back.
+    pub fn commit(&self) -> io::Result<()> {
+        let mut dirty = self.dirty.lock().unwrap();
+        let Some(pages) = dirty.as_ref() else {
+            return Err(io::Error::other("No transaction is in progress"));
+        };
+        let mut pages: Vec<PageData> = pages.values().cloned().collect();
+        pages.sort_by_key(|page| page.id);
+        self.buffer_pool.put_pages(&pages)?;
+        *dirty = None;
+        Ok(())
+    }
+
+    /// Discards every page written by the transaction.
+    pub fn rollback(&self) -> io::Result<()> {
+        self.finish().map(drop)
+    }
+
+    /// Ends the active transaction, returning the pages it wrote.
+    fn finish(&self) -> io::Result<HashMap<u32, PageData>> {
+        self.dirty
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| io::Error::other("No transaction is in progress"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{NodeType, StorageEngine};
+    use std::fs;
 
-    pub fn commit(&self) {}
+    fn open(test_db: &str) -> (Arc<BufferPool>, TransactionManager) {
+        let buffer_pool = Arc::new(BufferPool::new(10, StorageEngine::new(test_db).unwrap()));
+        let transactions = TransactionManager::new(Arc::clone(&buffer_pool));
+        (buffer_pool, transactions)
+    }
+
+    fn insert_key(transactions: &TransactionManager, page_id: u32, key: i32) {
+        let mut page = transactions.read_page(page_id).unwrap();
+        page.keys.push(key);
+        page.values.push(key as u64);
+        transactions.write_page(page).unwrap();
+    }
 
-    pub fn rollback(&self) {}
+    /// Tests that a rolled-back insert is visible only inside its transaction.
+    #[test]
+    fn test_rollback_discards_writes() {
+        let test_db = "test_transaction_rollback.db";
+        let _ = fs::remove_file(test_db);
+
+        let (buffer_pool, transactions) = open(test_db);
+        let page_id = buffer_pool
+            .allocate_page(NodeType::Leaf)
+            .unwrap()
+            .data
+            .read()
+            .unwrap()
+            .id;
+
+        transactions.begin().unwrap();
+        assert!(transactions.begin().is_err());
+        insert_key(&transactions, page_id, 42);
+        assert_eq!(transactions.read_page(page_id).unwrap().keys, vec![42]);
+        assert!(buffer_pool
+            .get_page(page_id)
+            .unwrap()
+            .data
+            .read()
+            .unwrap()
+            .keys
+            .is_empty());
+
+        transactions.rollback().unwrap();
+        assert!(!transactions.is_active());
+        assert!(transactions.read_page(page_id).unwrap().keys.is_empty());
+        assert!(transactions.rollback().is_err());
+        drop(transactions);
+        drop(buffer_pool);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert!(storage.read_page(page_id).unwrap().keys.is_empty());
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that committed writes reach the cached page and the data file.
+    #[test]
+    fn test_commit_persists_writes() {
+        let test_db = "test_transaction_commit.db";
+        let _ = fs::remove_file(test_db);
+
+        let (buffer_pool, transactions) = open(test_db);
+        let page_id = buffer_pool
+            .allocate_page(NodeType::Leaf)
+            .unwrap()
+            .data
+            .read()
+            .unwrap()
+            .id;
+
+        transactions.begin().unwrap();
+        insert_key(&transactions, page_id, 7);
+        insert_key(&transactions, page_id, 8);
+        transactions.commit().unwrap();
+        assert_eq!(
+            buffer_pool
+                .get_page(page_id)
+                .unwrap()
+                .data
+                .read()
+                .unwrap()
+                .keys,
+            vec![7, 8]
+        );
+        drop(transactions);
+        drop(buffer_pool);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        assert_eq!(storage.read_page(page_id).unwrap().keys, vec![7, 8]);
+
+        let _ = fs::remove_file(test_db);
+    }
+
+    /// Tests that a commit that cannot store every page stores none and keeps the transaction.
+    #[test]
+    fn test_failed_commit_keeps_transaction() {
+        let test_db = "test_transaction_failed_commit.db";
+        let _ = fs::remove_file(test_db);
+
+        let (buffer_pool, transactions) = open(test_db);
+        let page_ids: Vec<u32> = (0..2)
+            .map(|_| {
+                let page = buffer_pool.allocate_page(NodeType::Leaf).unwrap();
+                let id = page.data.read().unwrap().id;
+                id
+            })
+            .collect();
+
+        transactions.begin().unwrap();
+        insert_key(&transactions, page_ids[0], 7);
+        // A page that was never allocated has no slot in the file, so storing it fails
+        let unallocated = page_ids[1] + 1;
+        transactions
+            .write_page(PageData::new(unallocated, NodeType::Leaf))
+            .unwrap();
+        assert!(transactions.commit().is_err());
+        assert!(transactions.is_active());
+        assert_eq!(transactions.read_page(page_ids[0]).unwrap().keys, vec![7]);
+        assert!(buffer_pool
+            .get_page(page_ids[0])
+            .unwrap()
+            .data
+            .read()
+            .unwrap()
+            .keys
+            .is_empty());
+
+        transactions.rollback().unwrap();
+        drop(transactions);
+        drop(buffer_pool);
+
+        let mut storage = StorageEngine::new(test_db).unwrap();
+        for page_id in page_ids {
+            assert!(storage.read_page(page_id).unwrap().keys.is_empty());
+        }
+
+        let _ = fs::remove_file(test_db);
+    }
 }
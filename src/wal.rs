@@ -0,0 +1,200 @@
+//! Write-ahead log of page and header images, used to redo writes to a data file.
+//!
+//! Each record is stored as its bincode length as a little-endian `u32` followed by the
+//! encoded record. A record cut short by a crash is ignored and overwritten by the next append.
+
+use crate::storage::{OverflowPage, PageData, StorageHeader};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Log sequence number: the position of a record in the log, starting at 1.
+pub type Lsn = u64;
+
+/// A write recorded in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WalEntry {
+    /// The full image of a page as written.
+    Page(PageData),
+    /// The full image of an overflow page as written.
+    Overflow(OverflowPage),
+    /// The header as written.
+    Header(StorageHeader),
+}
+
+/// A logged write and its sequence number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalRecord {
+    pub lsn: Lsn,
+    pub entry: WalEntry,
+}
+
+/// An append-only log file of `WalRecord`s.
+pub struct Wal {
+    file: File,
+    next_lsn: Lsn,
+    // Byte length of the complete records, where the next one is appended
+    end: u64,
+}
+
+impl Wal {
+    /// Opens or creates the log at the given path and finds where it ends.
+    pub fn open(file_path: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+
+        let mut wal = Wal {
+            file,
+            next_lsn: 1,
+            end: 0,
+        };
+        let records = wal.read_records()?;
+        if let Some(last) = records.last() {
+            wal.next_lsn = last.lsn + 1;
+        }
+        Ok(wal)
+    }
+
+    /// Returns the sequence number of the last record, or 0 if the log is empty.
+    pub fn last_lsn(&self) -> Lsn {
+        self.next_lsn - 1
+    }
+
+    /// Appends an entry and returns its sequence number. The log is synced to disk first when
+    /// `sync` is set.
+    pub fn append(&mut self, entry: WalEntry, sync: bool) -> std::io::Result<Lsn> {
+        let record = WalRecord {
+            lsn: self.next_lsn,
+            entry,
+        };
+        let encoded = bincode::serialize(&record)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let length = u32::try_from(encoded.len())
+            .map_err(|_| std::io::Error::other("WAL record too large"))?;
+
+        let mut buffer = length.to_le_bytes().to_vec();
+        buffer.extend_from_slice(&encoded);
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&buffer)?;
+        if sync {
+            self.sync()?;
+        }
+
+        self.end += buffer.len() as u64;
+        self.next_lsn += 1;
+        Ok(record.lsn)
+    }
+
+    /// Flushes appended records from the OS cache to the disk.
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        self.file.sync_data()
+    }
+
+    /// Reads every complete record in LSN order.
+    pub fn records(&mut self) -> std::io::Result<Vec<WalRecord>> {
+        self.read_records()
+    }
+
+    /// Discards the records after `lsn`, so the next append follows it.
+    pub fn truncate_after(&mut self, lsn: Lsn) -> std::io::Result<()> {
+        let mut end = 0;
+        self.file.seek(SeekFrom::Start(0))?;
+        while let Some((record, length)) = self.read_record()? {
+            if record.lsn > lsn {
+                break;
+            }
+            end += length;
+        }
+        self.file.set_len(end)?;
+        self.file.sync_data()?;
+        self.end = end;
+        self.next_lsn = lsn.min(self.last_lsn()) + 1;
+        Ok(())
+    }
+
+    /// Reads the log from the start, remembering where its complete records end.
+    fn read_records(&mut self) -> std::io::Result<Vec<WalRecord>> {
+        let mut records = Vec::new();
+        let mut end = 0;
+        self.file.seek(SeekFrom::Start(0))?;
+        while let Some((record, length)) = self.read_record()? {
+            records.push(record);
+            end += length;
+        }
+        self.end = end;
+        Ok(records)
+    }
+
+    /// Reads the record at the current position and its length in bytes, or `None` at the end
+    /// of the log or at a record that was only partly written.
+    fn read_record(&mut self) -> std::io::Result<Option<(WalRecord, u64)>> {
+        let mut length = [0u8; 4];
+        if !read_full(&mut self.file, &mut length)? {
+            return Ok(None);
+        }
+        let mut encoded = vec![0u8; u32::from_le_bytes(length) as usize];
+        if !read_full(&mut self.file, &mut encoded)? {
+            return Ok(None);
+        }
+        match bincode::deserialize(&encoded) {
+            Ok(record) => Ok(Some((record, 4 + encoded.len() as u64))),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Fills `buffer`, returning false if the file ends first.
+fn read_full(file: &mut File, buffer: &mut [u8]) -> std::io::Result<bool> {
+    match file.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::NodeType;
+    use std::fs;
+
+    /// Tests that records keep their LSNs across reopening and that a torn tail is dropped.
+    #[test]
+    fn test_append_and_reopen() {
+        let test_wal = "test_wal_append.wal";
+        let _ = fs::remove_file(test_wal);
+
+        let mut wal = Wal::open(test_wal).unwrap();
+        assert_eq!(wal.last_lsn(), 0);
+        let page = PageData::new(1, NodeType::Leaf);
+        assert_eq!(wal.append(WalEntry::Page(page.clone()), true).unwrap(), 1);
+        assert_eq!(wal.append(WalEntry::Page(page), true).unwrap(), 2);
+        drop(wal);
+
+        // Simulate a crash part way through a third record
+        let mut file = OpenOptions::new().append(true).open(test_wal).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2]).unwrap();
+        drop(file);
+
+        let mut wal = Wal::open(test_wal).unwrap();
+        assert_eq!(wal.last_lsn(), 2);
+        let lsns: Vec<Lsn> = wal.records().unwrap().iter().map(|r| r.lsn).collect();
+        assert_eq!(lsns, vec![1, 2]);
+        let header = StorageHeader {
+            next_page_id: 2,
+            page_count: 1,
+        };
+        assert_eq!(wal.append(WalEntry::Header(header), true).unwrap(), 3);
+        assert_eq!(wal.records().unwrap().len(), 3);
+
+        wal.truncate_after(1).unwrap();
+        assert_eq!(wal.last_lsn(), 1);
+        assert_eq!(wal.append(WalEntry::Header(header), true).unwrap(), 2);
+
+        let _ = fs::remove_file(test_wal);
+    }
+}